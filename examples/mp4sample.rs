@@ -2,6 +2,7 @@ use std::io::prelude::*;
 use std::path::Path;
 use std::{env, io};
 
+use futures::TryStreamExt;
 use mp4::{error::MemoryStorageError, TrackType};
 use tokio::fs::File;
 use tokio::io::BufReader;
@@ -34,26 +35,21 @@ async fn samples<P: AsRef<Path>>(filename: &P) -> Result<(), mp4::Error<MemorySt
         .map(|(k, _)| *k);
 
     let track_id = keys.next().unwrap();
-    let samples_len = mp4_file.tracks.get(&track_id).unwrap().samples.len();
 
-    for idx in 0..samples_len {
-        let samp = mp4_file.tracks.get(&track_id).unwrap().samples[idx].clone();
-
-        let data = mp4_file
-            .read_sample_data(track_id, idx)
-            .await?
-            .map(|x| x.slice(0..16));
+    let samples = mp4_file.samples(track_id);
+    futures::pin_mut!(samples);
 
+    let mut idx = 0;
+    while let Some(samp) = samples.try_next().await? {
+        idx += 1;
         println!(
-            "[{} {} {}] {} - <{}> {} +{} {:?}",
-            idx + 1,
-            samp.chunk_id,
-            samp.offset,
+            "[{}] {} - <{}> {} +{} {:?}",
+            idx,
             samp.is_sync,
-            samp.size,
+            samp.bytes.len(),
             samp.start_time,
             samp.rendering_offset,
-            data.as_deref()
+            &samp.bytes[..samp.bytes.len().min(16)]
         );
     }
 
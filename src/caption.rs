@@ -0,0 +1,619 @@
+//! CEA-608/708 closed captions carried in video SEI messages (ATSC A/53
+//! `user_data_registered_itu_t_t35`) or in QuickTime `c608` tracks.
+
+use std::collections::BTreeMap;
+use std::mem;
+
+use crate::error::BoxError;
+use crate::{BoxType, Mp4Track, SampleEntry};
+
+const H264_NAL_SEI: u8 = 6;
+const HEVC_NAL_PREFIX_SEI: u8 = 39;
+const HEVC_NAL_SUFFIX_SEI: u8 = 40;
+
+const SEI_USER_DATA_REGISTERED_ITU_T_T35: u32 = 4;
+const T35_COUNTRY_CODE_USA: u8 = 0xB5;
+const T35_PROVIDER_ATSC: u16 = 0x0031;
+const ATSC_USER_IDENTIFIER: u32 = 0x4741_3934; // GA94
+const ATSC_USER_DATA_TYPE_CC: u8 = 3;
+
+/// One `cc_data` triplet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CcData {
+    pub valid: bool,
+
+    /// 0 and 1 for CEA-608 field 1 and 2, 2 and 3 for CEA-708 DTVCC packet
+    /// data and packet start.
+    pub cc_type: u8,
+    pub data: [u8; 2],
+}
+
+impl CcData {
+    pub const NTSC_FIELD_1: u8 = 0;
+    pub const NTSC_FIELD_2: u8 = 1;
+    pub const DTVCC_PACKET_DATA: u8 = 2;
+    pub const DTVCC_PACKET_START: u8 = 3;
+}
+
+/// Where the captions of a track are found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptionSource {
+    /// SEI messages of length prefixed H.264 or HEVC samples.
+    Sei { length_size: u8, hevc: bool },
+
+    /// `cdat`/`cdt2` atoms of a `c608` track.
+    C608,
+}
+
+impl CaptionSource {
+    /// Caption source of a sample, from the sample entry it refers to.
+    /// `None` if that entry cannot carry captions.
+    pub fn from_sample(track: &Mp4Track, sample_idx: usize) -> Option<Self> {
+        Self::from_entry(track.sample_entry(sample_idx)?)
+    }
+
+    /// Caption source of a sample entry, `None` for entries that cannot
+    /// carry captions.
+    pub fn from_entry(entry: &SampleEntry) -> Option<Self> {
+        match entry {
+            SampleEntry::Avc1(avc1) => Some(CaptionSource::Sei {
+                length_size: (avc1.avcc.length_size_minus_one & 0b11) + 1,
+                hevc: false,
+            }),
+            SampleEntry::Hev1(hev1) => Some(CaptionSource::Sei {
+                length_size: hev1.hvcc.length_size(),
+                hevc: true,
+            }),
+            SampleEntry::C608(_) => Some(CaptionSource::C608),
+            _ => None,
+        }
+    }
+
+    /// All `cc_data` triplets of a sample, in bitstream order.
+    pub fn cc_data(&self, sample: &[u8]) -> Result<Vec<CcData>, BoxError> {
+        let mut out = Vec::new();
+
+        match *self {
+            CaptionSource::Sei { length_size, hevc } => {
+                for nal in nal_units(sample, length_size)? {
+                    let (nal_type, header_size) = if hevc {
+                        ((nal.first().copied().unwrap_or(0) >> 1) & 0x3F, 2)
+                    } else {
+                        (nal.first().copied().unwrap_or(0) & 0x1F, 1)
+                    };
+
+                    let is_sei = if hevc {
+                        nal_type == HEVC_NAL_PREFIX_SEI || nal_type == HEVC_NAL_SUFFIX_SEI
+                    } else {
+                        nal_type == H264_NAL_SEI
+                    };
+
+                    if is_sei && nal.len() > header_size {
+                        sei_cc_data(&unescape_rbsp(&nal[header_size..]), &mut out);
+                    }
+                }
+            }
+
+            CaptionSource::C608 => {
+                let mut data = sample;
+                while data.len() >= 8 {
+                    let size = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+                    let kind =
+                        BoxType::from(u32::from_be_bytes([data[4], data[5], data[6], data[7]]));
+                    if size < 8 || size > data.len() {
+                        return Err(BoxError::InvalidData("c608: invalid atom size"));
+                    }
+
+                    let cc_type = match kind {
+                        BoxType::CdatBox => Some(CcData::NTSC_FIELD_1),
+                        BoxType::Cdt2Box => Some(CcData::NTSC_FIELD_2),
+                        _ => None,
+                    };
+
+                    if let Some(cc_type) = cc_type {
+                        out.extend(data[8..size].chunks_exact(2).map(|x| CcData {
+                            valid: true,
+                            cc_type,
+                            data: [x[0], x[1]],
+                        }));
+                    }
+
+                    data = &data[size..];
+                }
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+fn nal_units(mut data: &[u8], length_size: u8) -> Result<Vec<&[u8]>, BoxError> {
+    let length_size = length_size as usize;
+    if !(1..=4).contains(&length_size) {
+        return Err(BoxError::InvalidData("invalid nal unit length size"));
+    }
+
+    let mut units = Vec::new();
+    while data.len() >= length_size {
+        let size = data[..length_size]
+            .iter()
+            .fold(0usize, |acc, &x| (acc << 8) | x as usize);
+
+        data = &data[length_size..];
+        if size > data.len() {
+            return Err(BoxError::InvalidData("nal unit exceeds the sample"));
+        }
+
+        units.push(&data[..size]);
+        data = &data[size..];
+    }
+
+    Ok(units)
+}
+
+/// Drops the emulation prevention bytes of `00 00 03` sequences.
+fn unescape_rbsp(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut zeros = 0;
+
+    for &byte in data {
+        if zeros >= 2 && byte == 3 {
+            zeros = 0;
+            continue;
+        }
+
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        out.push(byte);
+    }
+
+    out
+}
+
+fn sei_cc_data(mut rbsp: &[u8], out: &mut Vec<CcData>) {
+    // stop at the rbsp trailing bits
+    while rbsp.len() > 1 {
+        let mut read_value = || {
+            let mut value = 0u32;
+            while let Some((&byte, rest)) = rbsp.split_first() {
+                rbsp = rest;
+                value += byte as u32;
+                if byte != 0xFF {
+                    break;
+                }
+            }
+            value
+        };
+
+        let payload_type = read_value();
+        let payload_size = read_value() as usize;
+        if payload_size > rbsp.len() {
+            return;
+        }
+
+        let (payload, rest) = rbsp.split_at(payload_size);
+        if payload_type == SEI_USER_DATA_REGISTERED_ITU_T_T35 {
+            t35_cc_data(payload, out);
+        }
+
+        rbsp = rest;
+    }
+}
+
+fn t35_cc_data(payload: &[u8], out: &mut Vec<CcData>) {
+    let [country_code, p0, p1, u0, u1, u2, u3, type_code, flags, _em_data, triplets @ ..] = payload
+    else {
+        return;
+    };
+
+    if *country_code != T35_COUNTRY_CODE_USA
+        || u16::from_be_bytes([*p0, *p1]) != T35_PROVIDER_ATSC
+        || u32::from_be_bytes([*u0, *u1, *u2, *u3]) != ATSC_USER_IDENTIFIER
+        || *type_code != ATSC_USER_DATA_TYPE_CC
+        || flags & 0x40 == 0
+    {
+        return;
+    }
+
+    let cc_count = (flags & 0x1F) as usize;
+    out.extend(triplets.chunks_exact(3).take(cc_count).map(|x| CcData {
+        valid: x[0] & 0x04 != 0,
+        cc_type: x[0] & 0x03,
+        data: [x[1], x[2]],
+    }));
+}
+
+/// A caption shown from `start_time` until `end_time`, in the time units
+/// passed to the decoder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caption {
+    pub start_time: u64,
+    pub end_time: u64,
+
+    /// Rows from top to bottom separated by `\n`.
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CaptionMode {
+    PopOn,
+    PaintOn,
+    RollUp(u8),
+    Text,
+}
+
+/// Decoder for the CC1 channel of CEA-608 field 1 byte pairs.
+#[derive(Debug, Clone)]
+pub struct Cea608Decoder {
+    mode: CaptionMode,
+    channel: u8,
+    row: u8,
+    last_control: Option<[u8; 2]>,
+    displayed: BTreeMap<u8, String>,
+    non_displayed: BTreeMap<u8, String>,
+    display_start: Option<u64>,
+    captions: Vec<Caption>,
+}
+
+impl Default for Cea608Decoder {
+    fn default() -> Self {
+        Self {
+            mode: CaptionMode::PopOn,
+            channel: 1,
+            row: 15,
+            last_control: None,
+            displayed: BTreeMap::new(),
+            non_displayed: BTreeMap::new(),
+            display_start: None,
+            captions: Vec::new(),
+        }
+    }
+}
+
+impl Cea608Decoder {
+    /// Feeds a field 1 byte pair received at `time`. Pairs must be fed in
+    /// presentation order.
+    pub fn push(&mut self, time: u64, data: [u8; 2]) {
+        let (b1, b2) = (data[0] & 0x7F, data[1] & 0x7F);
+
+        if (0x10..=0x1F).contains(&b1) {
+            // control codes are sent twice, act on the first only
+            if self.last_control.take() == Some([b1, b2]) {
+                return;
+            }
+            self.last_control = Some([b1, b2]);
+            self.channel = if b1 & 0x08 != 0 { 2 } else { 1 };
+
+            if self.channel == 1 {
+                self.control(time, b1 & !0x08, b2);
+            }
+            return;
+        }
+
+        self.last_control = None;
+        if b1 == 0 && b2 == 0 {
+            return;
+        }
+
+        if self.channel == 1 {
+            for byte in [b1, b2] {
+                if byte >= 0x20 {
+                    self.put(time, basic_char(byte));
+                }
+            }
+        }
+    }
+
+    /// Ends the caption still on screen at `time` and returns all captions.
+    pub fn finish(mut self, time: u64) -> Vec<Caption> {
+        self.flush(time);
+        self.captions
+    }
+
+    fn control(&mut self, time: u64, b1: u8, b2: u8) {
+        match (b1, b2) {
+            (0x14, 0x20) => self.mode = CaptionMode::PopOn,
+            (0x14, 0x21) => {
+                self.backspace();
+            }
+            (0x14, 0x25..=0x27) => {
+                if !matches!(self.mode, CaptionMode::RollUp(_)) {
+                    self.flush(time);
+                    self.displayed.clear();
+                    self.non_displayed.clear();
+                    self.row = 15;
+                }
+                self.mode = CaptionMode::RollUp(b2 - 0x23);
+            }
+            (0x14, 0x29) => self.mode = CaptionMode::PaintOn,
+            (0x14, 0x2A) | (0x14, 0x2B) => self.mode = CaptionMode::Text,
+            (0x14, 0x2C) => {
+                self.flush(time);
+                self.displayed.clear();
+            }
+            (0x14, 0x2D) => self.carriage_return(time),
+            (0x14, 0x2E) => self.non_displayed.clear(),
+            (0x14, 0x2F) => {
+                self.flush(time);
+                mem::swap(&mut self.displayed, &mut self.non_displayed);
+                self.non_displayed.clear();
+                if !render(&self.displayed).is_empty() {
+                    self.display_start = Some(time);
+                }
+                self.mode = CaptionMode::PopOn;
+            }
+
+            // mid-row style change, shown as a space
+            (0x11, 0x20..=0x2F) => self.put(time, ' '),
+            (0x11, 0x30..=0x3F) => self.put(time, SPECIAL_CHARS[(b2 - 0x30) as usize]),
+            (0x12, 0x20..=0x3F) | (0x13, 0x20..=0x3F) => {
+                // extended characters replace the fallback sent before them
+                self.backspace();
+                let table = if b1 == 0x12 {
+                    EXTENDED_CHARS_12
+                } else {
+                    EXTENDED_CHARS_13
+                };
+                self.put(time, table[(b2 - 0x20) as usize]);
+            }
+
+            // preamble address code
+            (_, 0x40..=0x7F) => {
+                const ROWS: [u8; 8] = [11, 1, 3, 12, 14, 5, 7, 9];
+                let row = ROWS[(b1 - 0x10) as usize] + (b2 & 0x20 != 0) as u8;
+
+                if let CaptionMode::RollUp(rows) = self.mode {
+                    // the window moves with the base row
+                    if row != self.row {
+                        let shift = row as i16 - self.row as i16;
+                        self.displayed = mem::take(&mut self.displayed)
+                            .into_iter()
+                            .map(|(r, text)| ((r as i16 + shift).clamp(1, 15) as u8, text))
+                            .filter(|&(r, _)| r + rows > row)
+                            .collect();
+                    }
+                } else if let Some(text) = self.memory().get_mut(&row) {
+                    text.clear();
+                }
+                self.row = row;
+            }
+
+            _ => {}
+        }
+    }
+
+    fn carriage_return(&mut self, time: u64) {
+        let CaptionMode::RollUp(rows) = self.mode else {
+            self.row = (self.row + 1).min(15);
+            return;
+        };
+
+        self.flush(time);
+
+        let base = self.row;
+        self.displayed = mem::take(&mut self.displayed)
+            .into_iter()
+            .filter(|&(r, _)| r > 1 && r + rows > base + 1)
+            .map(|(r, text)| (r - 1, text))
+            .collect();
+
+        if !render(&self.displayed).is_empty() {
+            self.display_start = Some(time);
+        }
+    }
+
+    fn backspace(&mut self) {
+        let row = self.row;
+        if let Some(text) = self.memory().get_mut(&row) {
+            text.pop();
+        }
+    }
+
+    fn memory(&mut self) -> &mut BTreeMap<u8, String> {
+        match self.mode {
+            CaptionMode::PopOn => &mut self.non_displayed,
+            _ => &mut self.displayed,
+        }
+    }
+
+    fn put(&mut self, time: u64, c: char) {
+        if self.mode == CaptionMode::Text {
+            return;
+        }
+
+        let row = self.row;
+        self.memory().entry(row).or_default().push(c);
+
+        if self.mode != CaptionMode::PopOn && self.display_start.is_none() {
+            self.display_start = Some(time);
+        }
+    }
+
+    fn flush(&mut self, time: u64) {
+        let Some(start_time) = self.display_start.take() else {
+            return;
+        };
+
+        let text = render(&self.displayed);
+        if !text.is_empty() && time > start_time {
+            self.captions.push(Caption {
+                start_time,
+                end_time: time,
+                text,
+            });
+        }
+    }
+}
+
+fn render(rows: &BTreeMap<u8, String>) -> String {
+    rows.values()
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn basic_char(byte: u8) -> char {
+    match byte {
+        0x2A => 'á',
+        0x5C => 'é',
+        0x5E => 'í',
+        0x5F => 'ó',
+        0x60 => 'ú',
+        0x7B => 'ç',
+        0x7C => '÷',
+        0x7D => 'Ñ',
+        0x7E => 'ñ',
+        0x7F => '█',
+        byte => byte as char,
+    }
+}
+
+const SPECIAL_CHARS: [char; 16] = [
+    '®', '°', '½', '¿', '™', '¢', '£', '♪', 'à', ' ', 'è', 'â', 'ê', 'î', 'ô', 'û',
+];
+
+const EXTENDED_CHARS_12: [char; 32] = [
+    'Á', 'É', 'Ó', 'Ú', 'Ü', 'ü', '‘', '¡', '*', '\'', '—', '©', '℠', '•', '“', '”', 'À', 'Â', 'Ç',
+    'È', 'Ê', 'Ë', 'ë', 'Î', 'Ï', 'ï', 'Ô', 'Ù', 'ù', 'Û', '«', '»',
+];
+
+const EXTENDED_CHARS_13: [char; 32] = [
+    'Ã', 'ã', 'Í', 'Ì', 'ì', 'Ò', 'ò', 'Õ', 'õ', '{', '}', '\\', '^', '_', '|', '~', 'Ä', 'ä', 'Ö',
+    'ö', 'ß', '¥', '¤', '│', 'Å', 'å', 'Ø', 'ø', '┌', '┐', '└', '┘',
+];
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::test_util::{avc_config, video_sample};
+    use crate::track::Mp4TrackWriter;
+    use crate::C608Box;
+
+    fn pairs(text: &str) -> Vec<[u8; 2]> {
+        text.as_bytes()
+            .chunks(2)
+            .map(|x| [x[0], x.get(1).copied().unwrap_or(0)])
+            .collect()
+    }
+
+    #[test]
+    fn test_sei_cc_data() {
+        // SEI with a T.35 payload carrying two triplets, with an emulation
+        // prevention byte in front of the 0x00 padding
+        let sei = [
+            0x06, 0x04, 0x11, 0xB5, 0x00, 0x31, b'G', b'A', b'9', b'4', 0x03, 0x42, 0xFF, 0xFC,
+            0x94, 0x20, 0xF9, 0x00, 0x00, 0x03, 0x00, 0xFF, 0x80,
+        ];
+        let mut sample = (sei.len() as u32).to_be_bytes().to_vec();
+        sample.extend_from_slice(&sei);
+
+        let source = CaptionSource::Sei {
+            length_size: 4,
+            hevc: false,
+        };
+        let cc_data = source.cc_data(&sample).unwrap();
+        assert_eq!(
+            cc_data,
+            [
+                CcData {
+                    valid: true,
+                    cc_type: CcData::NTSC_FIELD_1,
+                    data: [0x94, 0x20],
+                },
+                CcData {
+                    valid: false,
+                    cc_type: CcData::NTSC_FIELD_2,
+                    data: [0x00, 0x00],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_source_per_sample_entry() {
+        let mut writer = Mp4TrackWriter::new(1, &avc_config());
+        for idx in 0..10 {
+            writer.add_sample(&video_sample(idx));
+        }
+        writer.take_chunk(0);
+
+        // the samples refer to the `avc1` entry behind a `c608` one
+        let mut trak = writer.write_end(1000);
+        let stbl = &mut trak.mdia.minf.stbl;
+        stbl.stsd
+            .entries
+            .insert(0, SampleEntry::C608(C608Box::default()));
+        for entry in stbl.stsc.entries.iter_mut() {
+            entry.sample_description_index = 2;
+        }
+
+        let track = Mp4Track::new(trak, &mut BTreeSet::new()).unwrap();
+        assert_eq!(
+            CaptionSource::from_sample(&track, 3),
+            Some(CaptionSource::Sei {
+                length_size: 4,
+                hevc: false,
+            })
+        );
+        assert_eq!(CaptionSource::from_sample(&track, 10), None);
+    }
+
+    #[test]
+    fn test_c608_cc_data() {
+        let sample = [0, 0, 0, 12, b'c', b'd', b'a', b't', 0x94, 0x2C, 0x94, 0x2C];
+        let cc_data = CaptionSource::C608.cc_data(&sample).unwrap();
+        assert_eq!(cc_data.len(), 2);
+        assert!(cc_data.iter().all(|x| x.data == [0x94, 0x2C]));
+    }
+
+    #[test]
+    fn test_cea608_pop_on() {
+        // RCL twice, PAC row 15, text, PAC row 14 (CC2 text in between),
+        // special and extended characters
+        let mut data = vec![[0x14, 0x20], [0x14, 0x20], [0x14, 0x70]];
+        data.extend(pairs("HELLO"));
+        data.extend([[0x1C, 0x20], [0x41, 0x41], [0x14, 0x50]]);
+        data.extend(pairs("CAFE"));
+        data.extend([[0x13, 0x31], [0x11, 0x37]]);
+
+        let mut decoder = Cea608Decoder::default();
+        for (time, pair) in data.into_iter().enumerate() {
+            decoder.push(time as u64, pair);
+        }
+        decoder.push(100, [0x14, 0x2F]);
+        decoder.push(100, [0x14, 0x2F]);
+        decoder.push(200, [0x14, 0x2C]);
+
+        let captions = decoder.finish(300);
+        assert_eq!(
+            captions,
+            [Caption {
+                start_time: 100,
+                end_time: 200,
+                text: String::from("CAFä♪\nHELLO"),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_cea608_roll_up() {
+        let mut decoder = Cea608Decoder::default();
+        decoder.push(0, [0x14, 0x25]);
+        for pair in pairs("ONE") {
+            decoder.push(10, pair);
+        }
+        decoder.push(20, [0x14, 0x2D]);
+        for pair in pairs("TWO") {
+            decoder.push(30, pair);
+        }
+
+        let captions = decoder.finish(40);
+        let spans: Vec<_> = captions
+            .iter()
+            .map(|x| (x.start_time, x.end_time, x.text.as_str()))
+            .collect();
+        assert_eq!(spans, [(10, 20, "ONE"), (20, 40, "ONE\nTWO")]);
+    }
+}
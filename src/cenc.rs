@@ -0,0 +1,405 @@
+//! Clear key decryption and encryption of ISO/IEC 23001-7 (common
+//! encryption) protected samples.
+
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use aes::Aes128;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::ops::Range;
+
+use crate::error::BoxError;
+use crate::{BoxType, Result, SampleEncryption, SchmBox, SinfBox};
+
+/// Content keys by key ID.
+///
+/// The `cenc` (AES-CTR) and `cbcs` (AES-CBC with pattern encryption) schemes
+/// are supported. Set [`crate::Mp4File::decryption`] to decrypt samples as
+/// they are read.
+#[derive(Debug, Clone, Default)]
+pub struct ClearKey {
+    pub keys: HashMap<[u8; 16], [u8; 16]>,
+}
+
+impl ClearKey {
+    pub fn new(keys: HashMap<[u8; 16], [u8; 16]>) -> Self {
+        ClearKey { keys }
+    }
+
+    pub fn insert(&mut self, kid: [u8; 16], key: [u8; 16]) {
+        self.keys.insert(kid, key);
+    }
+
+    /// Decrypts a sample in place. Samples of entries without a protection
+    /// scheme, or marked as not protected, are left untouched.
+    pub fn decrypt(
+        &self,
+        sinf: &SinfBox,
+        info: Option<&SampleEncryption>,
+        data: &mut [u8],
+    ) -> Result<()> {
+        self.apply(sinf, info, data, false)
+    }
+
+    /// Encrypts a sample in place, the mirror of [`ClearKey::decrypt`]. The
+    /// IV and sub-sample layout in `info` are the ones to store in `senc`.
+    pub fn encrypt(
+        &self,
+        sinf: &SinfBox,
+        info: Option<&SampleEncryption>,
+        data: &mut [u8],
+    ) -> Result<()> {
+        self.apply(sinf, info, data, true)
+    }
+
+    fn apply(
+        &self,
+        sinf: &SinfBox,
+        info: Option<&SampleEncryption>,
+        data: &mut [u8],
+        encrypt: bool,
+    ) -> Result<()> {
+        let Some(tenc) = sinf.tenc() else {
+            return Err(BoxError::BoxNotFound(BoxType::TencBox));
+        };
+
+        if tenc.default_is_protected == 0 {
+            return Ok(());
+        }
+
+        let key = self
+            .keys
+            .get(&tenc.default_kid)
+            .ok_or(BoxError::InvalidData("no key for the key ID of the track"))?;
+        let cipher = Aes128::new(GenericArray::from_slice(key));
+
+        let default_info = SampleEncryption::default();
+        let info = info.unwrap_or(&default_info);
+
+        let iv = if !info.iv.is_empty() {
+            &info.iv
+        } else {
+            tenc.default_constant_iv
+                .as_deref()
+                .ok_or(BoxError::InvalidData("sample has no IV"))?
+        };
+        let iv = match iv.len() {
+            8 | 16 => {
+                let mut buf = [0; 16];
+                buf[..iv.len()].copy_from_slice(iv);
+                buf
+            }
+            _ => return Err(BoxError::InvalidData("IV size must be 8 or 16 bytes")),
+        };
+
+        let ranges = encrypted_ranges(info, data.len())?;
+        match sinf.scheme_type() {
+            Some(SchmBox::CENC) => ctr(&cipher, iv, data, &ranges),
+            Some(SchmBox::CBCS) => {
+                let pattern = (tenc.default_crypt_byte_block, tenc.default_skip_byte_block);
+                cbc(&cipher, iv, data, &ranges, pattern, encrypt)
+            }
+            _ => return Err(BoxError::InvalidData("unsupported protection scheme")),
+        }
+
+        Ok(())
+    }
+}
+
+/// The encrypted parts of a sample, all of it without sub-samples.
+fn encrypted_ranges(info: &SampleEncryption, len: usize) -> Result<Vec<Range<usize>>> {
+    if info.subsamples.is_empty() {
+        #[allow(clippy::single_range_in_vec_init)]
+        return Ok(vec![0..len]);
+    }
+
+    let mut offset = 0;
+    let mut ranges = Vec::with_capacity(info.subsamples.len());
+    for subsample in info.subsamples.iter() {
+        let start = offset + subsample.clear_bytes as usize;
+        let end = start + subsample.encrypted_bytes as usize;
+        if end > len {
+            return Err(BoxError::InvalidData(
+                "sub-samples are larger than the sample",
+            ));
+        }
+
+        ranges.push(start..end);
+        offset = end;
+    }
+
+    Ok(ranges)
+}
+
+/// AES-CTR. The keystream runs on across the ranges, and the counter is the
+/// low 64 bits of the block.
+fn ctr(cipher: &Aes128, mut counter: [u8; 16], data: &mut [u8], ranges: &[Range<usize>]) {
+    let mut keystream = GenericArray::default();
+    let mut pos = 16;
+
+    for range in ranges {
+        for byte in data[range.clone()].iter_mut() {
+            if pos == 16 {
+                keystream.copy_from_slice(&counter);
+                cipher.encrypt_block(&mut keystream);
+
+                let block = u64::from_be_bytes(counter[8..].try_into().unwrap());
+                counter[8..].copy_from_slice(&block.wrapping_add(1).to_be_bytes());
+                pos = 0;
+            }
+
+            *byte ^= keystream[pos];
+            pos += 1;
+        }
+    }
+}
+
+/// AES-CBC over the 16 byte blocks of each range, restarting from the IV at
+/// every range. Of each `crypt` + `skip` blocks only the first `crypt` are
+/// encrypted, all of them without a pattern. A trailing partial block stays
+/// in the clear.
+fn cbc(
+    cipher: &Aes128,
+    iv: [u8; 16],
+    data: &mut [u8],
+    ranges: &[Range<usize>],
+    (crypt, skip): (u8, u8),
+    encrypt: bool,
+) {
+    let (crypt, period) = if skip == 0 {
+        (1, 1)
+    } else {
+        (crypt as usize, crypt as usize + skip as usize)
+    };
+
+    for range in ranges {
+        let mut chain = iv;
+        for (idx, block) in data[range.clone()].chunks_exact_mut(16).enumerate() {
+            if idx % period >= crypt {
+                continue;
+            }
+
+            let block = GenericArray::from_mut_slice(block);
+            if encrypt {
+                block.iter_mut().zip(chain).for_each(|(x, c)| *x ^= c);
+                cipher.encrypt_block(block);
+                chain.copy_from_slice(block);
+            } else {
+                let next: [u8; 16] = block.as_slice().try_into().unwrap();
+                cipher.decrypt_block(block);
+                block.iter_mut().zip(chain).for_each(|(x, c)| *x ^= c);
+                chain = next;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FourCC, FrmaBox, SchiBox, SubsampleEncryption, TencBox};
+
+    const KID: [u8; 16] = [0x11; 16];
+    const KEY: [u8; 16] = [
+        0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f,
+        0x3c,
+    ];
+
+    fn sinf(scheme_type: FourCC, tenc: TencBox) -> SinfBox {
+        SinfBox {
+            frma: FrmaBox {
+                data_format: str::parse("avc1").unwrap(),
+            },
+            schm: Some(SchmBox {
+                scheme_type,
+                scheme_version: 0x00010000,
+                ..Default::default()
+            }),
+            schi: Some(SchiBox {
+                tenc: Some(TencBox {
+                    default_is_protected: 1,
+                    default_kid: KID,
+                    ..tenc
+                }),
+            }),
+        }
+    }
+
+    fn clear_key() -> ClearKey {
+        ClearKey::new(HashMap::from([(KID, KEY)]))
+    }
+
+    #[test]
+    fn test_cenc() {
+        // NIST SP 800-38A F.5.1, CTR-AES128
+        let sinf = sinf(
+            SchmBox::CENC,
+            TencBox {
+                default_per_sample_iv_size: 16,
+                ..Default::default()
+            },
+        );
+        let info = SampleEncryption {
+            iv: vec![
+                0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa, 0xfb, 0xfc, 0xfd,
+                0xfe, 0xff,
+            ],
+            subsamples: vec![],
+        };
+        let mut data = [
+            0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93,
+            0x17, 0x2a,
+        ];
+        clear_key().encrypt(&sinf, Some(&info), &mut data).unwrap();
+        assert_eq!(
+            data,
+            [
+                0x87, 0x4d, 0x61, 0x91, 0xb6, 0x20, 0xe3, 0x26, 0x1b, 0xef, 0x68, 0x64, 0x99, 0x0d,
+                0xb6, 0xce
+            ]
+        );
+
+        // the keystream continues across sub-samples
+        let info = SampleEncryption {
+            iv: vec![1; 8],
+            subsamples: vec![
+                SubsampleEncryption {
+                    clear_bytes: 5,
+                    encrypted_bytes: 20,
+                },
+                SubsampleEncryption {
+                    clear_bytes: 3,
+                    encrypted_bytes: 30,
+                },
+            ],
+        };
+        let clear: Vec<u8> = (0..70).collect();
+        let mut data = clear.clone();
+        clear_key().encrypt(&sinf, Some(&info), &mut data).unwrap();
+        assert_eq!(data[..5], clear[..5]);
+        assert_eq!(data[25..28], clear[25..28]);
+        assert_eq!(data[58..], clear[58..]);
+
+        let mut whole = clear.clone();
+        let ranges = [5..25, 28..58];
+        let merged: Vec<u8> = ranges
+            .iter()
+            .flat_map(|x| clear[x.clone()].to_vec())
+            .collect();
+        let mut merged_data = merged.clone();
+        clear_key()
+            .encrypt(
+                &sinf,
+                Some(&SampleEncryption {
+                    iv: vec![1; 8],
+                    subsamples: vec![],
+                }),
+                &mut merged_data,
+            )
+            .unwrap();
+        assert_eq!(data[5..25], merged_data[..20]);
+        assert_eq!(data[28..58], merged_data[20..]);
+
+        clear_key().decrypt(&sinf, Some(&info), &mut data).unwrap();
+        assert_eq!(data, clear);
+
+        // a missing key is an error, an unprotected sample is left alone
+        assert!(ClearKey::default()
+            .decrypt(&sinf, Some(&info), &mut whole)
+            .is_err());
+        let mut unprotected = sinf.clone();
+        unprotected
+            .schi
+            .as_mut()
+            .unwrap()
+            .tenc
+            .as_mut()
+            .unwrap()
+            .default_is_protected = 0;
+        clear_key()
+            .decrypt(&unprotected, Some(&info), &mut whole)
+            .unwrap();
+        assert_eq!(whole, clear);
+    }
+
+    #[test]
+    fn test_cbcs() {
+        // NIST SP 800-38A F.2.1, CBC-AES128, without a pattern
+        let iv: Vec<u8> = (0..16).collect();
+        let sinf_full = sinf(
+            SchmBox::CBCS,
+            TencBox {
+                version: 1,
+                default_constant_iv: Some(iv),
+                ..Default::default()
+            },
+        );
+        let mut data = [
+            0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93,
+            0x17, 0x2a, 0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c, 0x9e, 0xb7, 0x6f, 0xac,
+            0x45, 0xaf, 0x8e, 0x51, 0xff,
+        ];
+        clear_key().encrypt(&sinf_full, None, &mut data).unwrap();
+        assert_eq!(
+            data,
+            [
+                0x76, 0x49, 0xab, 0xac, 0x81, 0x19, 0xb2, 0x46, 0xce, 0xe9, 0x8e, 0x9b, 0x12, 0xe9,
+                0x19, 0x7d, 0x50, 0x86, 0xcb, 0x9b, 0x50, 0x72, 0x19, 0xee, 0x95, 0xdb, 0x11, 0x3a,
+                0x91, 0x76, 0x78, 0xb2, 0xff,
+            ]
+        );
+
+        // 1:9 pattern, restarting at every sub-sample
+        let sinf = sinf(
+            SchmBox::CBCS,
+            TencBox {
+                version: 1,
+                default_crypt_byte_block: 1,
+                default_skip_byte_block: 9,
+                default_per_sample_iv_size: 16,
+                ..Default::default()
+            },
+        );
+        let info = SampleEncryption {
+            iv: vec![9; 16],
+            subsamples: vec![
+                SubsampleEncryption {
+                    clear_bytes: 10,
+                    encrypted_bytes: 200,
+                },
+                SubsampleEncryption {
+                    clear_bytes: 4,
+                    encrypted_bytes: 40,
+                },
+            ],
+        };
+        let clear: Vec<u8> = (0..=255).collect();
+        let mut data = clear.clone();
+        clear_key().encrypt(&sinf, Some(&info), &mut data).unwrap();
+
+        let changed = |range: Range<usize>| data[range.clone()] != clear[range];
+        assert!(!changed(0..10));
+        assert!(changed(10..26));
+        assert!(!changed(26..170));
+        assert!(changed(170..186));
+        assert!(!changed(186..214));
+        assert!(changed(214..230));
+        assert!(!changed(230..256));
+
+        // the chain starts from the IV in both sub-samples
+        let whole = SampleEncryption {
+            iv: vec![9; 16],
+            subsamples: vec![],
+        };
+        for range in [10..26, 214..230].iter() {
+            let mut block = clear[range.clone()].to_vec();
+            clear_key()
+                .encrypt(&sinf, Some(&whole), &mut block)
+                .unwrap();
+            assert_eq!(data[range.clone()], block[..]);
+        }
+
+        clear_key().decrypt(&sinf, Some(&info), &mut data).unwrap();
+        assert_eq!(data, clear);
+    }
+}
@@ -1,13 +1,22 @@
-use bytes::Bytes;
+use async_stream::try_stream;
+use bytes::{Bytes, BytesMut};
+use futures::stream::{LocalBoxStream, StreamExt};
 use futures::Future;
 use std::collections::{BTreeSet, HashMap};
 use std::iter::FromIterator;
 use std::ops::Range;
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, SeekFrom};
 
-use crate::error::{BoxError, MemoryStorageError};
-use crate::{BlockReader, BoxHeader, BoxType, EmsgBox, FtypBox, MoofBox, MoovBox};
-use crate::{Mp4Track, HEADER_SIZE};
+use crate::error::{BoxError, Error, MemoryStorageError};
+use crate::track::Mp4SampleOffset;
+use crate::{BlockReader, BoxHeader, BoxType, EmsgBox, FtypBox, MfraBox, MfroBox, Mp4Box};
+use crate::{Caption, CaptionSource, CcData, Cea608Decoder};
+
+#[cfg(feature = "cenc")]
+use crate::ClearKey;
+use crate::{MoofBox, MoovBox, PrftBox, PsshBox, SidxBox, StypBox};
+use crate::{Mp4Sample, Mp4Track, StppSample, HEADER_SIZE};
+use crate::{Reader, SampleEncryption};
 
 pub trait DataStorage {
     type Error;
@@ -58,28 +67,143 @@ impl DataStorage for MemoryStorage {
     }
 }
 
-enum DataBlockBody {
-    Memory(Bytes),
+/// Progress reported by [`Mp4File::feed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mp4Event {
+    /// `moov` was parsed and [`Mp4File::tracks`] is populated.
+    Init,
+
+    /// A `moof` and its `mdat` are complete; the new samples of each track
+    /// are readable.
+    Fragment {
+        sequence_number: u32,
+        samples: Vec<(u32, Range<usize>)>,
+    },
+
+    /// An `emsg` arrived. It is also appended to [`Mp4File::emsgs`], where
+    /// it is tied to its fragment once the following `moof` is parsed.
+    Emsg(EmsgBox),
+}
+
+/// An `emsg` placed on the media timeline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventMessage {
+    pub emsg: EmsgBox,
+
+    /// Sequence number of the fragment the message belongs to, the first
+    /// one after it.
+    pub sequence_number: Option<u32>,
+
+    /// In the `emsg` timescale. Version 1 carries it; for version 0 the
+    /// delta is added to the decode time of the fragment (its `tfdt`), so
+    /// it is only known once the fragment is parsed.
+    pub presentation_time: Option<u64>,
+}
+
+/// Segment level boxes that precede a fragment.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FragmentInfo {
+    /// File offset of the `moof`.
+    pub offset: u64,
+    pub sequence_number: u32,
+    pub styp: Option<StypBox>,
+    pub prfts: Vec<PrftBox>,
+}
+
+/// Stream returned by [`Mp4File::samples`] and [`Mp4File::all_samples`].
+pub type SampleStream<'s, T, E> = LocalBoxStream<'s, Result<T, Error<E>>>;
+
+/// Order of the samples interleaved by [`Mp4File::all_samples`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleOrder {
+    /// As they are laid out in the file.
+    FileOffset,
+
+    /// By decode time, converted from each track's timescale.
+    DecodeTime,
+}
+
+/// Decides which data boxes (`mdat`) are copied into the [`DataStorage`]
+/// while reading the header and which are left in the source and read back
+/// from it on demand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Every data box is saved to the storage.
+    Always,
+
+    /// Data boxes smaller than the given size in bytes are saved to the
+    /// storage, larger ones are read from the source.
+    BelowSize(u64),
+
+    /// Nothing is saved, samples are always read from the source.
+    Never,
+}
+
+impl CachePolicy {
+    fn should_cache(&self, size: u64) -> bool {
+        match *self {
+            CachePolicy::Always => true,
+            CachePolicy::BelowSize(threshold) => size < threshold,
+            CachePolicy::Never => false,
+        }
+    }
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        CachePolicy::BelowSize(128 * 1024 * 1024)
+    }
+}
+
+enum DataBlockBody<Id> {
+    Storage(Id),
     Reader,
 }
 
-pub struct DataBlock {
-    kind: BoxType,
+pub struct DataBlock<Id> {
     offset: u64,
     size: u64,
-    buffer: DataBlockBody,
+    buffer: DataBlockBody<Id>,
 }
 
-pub struct Mp4File<'a, R>
+pub struct Mp4File<'a, R, S = MemoryStorage>
 where
     R: AsyncRead + AsyncSeek + Unpin,
+    S: DataStorage,
 {
     pub ftyp: Option<FtypBox>,
-    pub emsgs: Vec<EmsgBox>,
+    pub emsgs: Vec<EventMessage>,
+
+    /// One entry per `moof` parsed by [`Mp4File::read_header`] or
+    /// [`Mp4File::feed`].
+    pub fragments: Vec<FragmentInfo>,
+
+    /// Protection system headers from `moov` and every `moof`, in file
+    /// order.
+    pub psshs: Vec<PsshBox>,
+
+    /// Segment indexes, each with the file offset of the first byte after
+    /// the box, which its references are relative to.
+    pub sidxs: Vec<(u64, SidxBox)>,
+
+    /// Random access index of the fragments, usually at the end of the
+    /// file.
+    pub mfra: Option<MfraBox>,
     pub tracks: HashMap<u32, Mp4Track>,
     pub reader: &'a mut R,
     pub offsets: BTreeSet<u64>,
-    pub data_blocks: Vec<DataBlock>,
+    pub data_blocks: Vec<DataBlock<S::Id>>,
+    pub storage: S,
+    pub cache_policy: CachePolicy,
+
+    /// Keys to decrypt the samples of protected tracks with as they are
+    /// read. Without them the samples are returned as stored.
+    #[cfg(feature = "cenc")]
+    pub decryption: Option<ClearKey>,
+    feed_buffer: BytesMut,
+    feed_offset: u64,
+    pending_fragment: Option<Mp4Event>,
+    pending_info: FragmentInfo,
 }
 
 impl<'a, R> Mp4File<'a, R>
@@ -87,131 +211,322 @@ where
     R: AsyncRead + Unpin + AsyncSeek + 'a,
 {
     pub fn new(reader: &'a mut R) -> Self {
+        Self::with_storage(reader, MemoryStorage::default(), CachePolicy::default())
+    }
+}
+
+impl<'a, R, S> Mp4File<'a, R, S>
+where
+    R: AsyncRead + Unpin + AsyncSeek + 'a,
+    S: DataStorage,
+{
+    pub fn with_storage(reader: &'a mut R, storage: S, cache_policy: CachePolicy) -> Self {
         Self {
             ftyp: None,
             emsgs: Vec::new(),
+            fragments: Vec::new(),
+            psshs: Vec::new(),
+            sidxs: Vec::new(),
+            mfra: None,
             tracks: HashMap::new(),
             reader,
             offsets: BTreeSet::new(),
             data_blocks: Vec::new(),
+            storage,
+            cache_policy,
+            #[cfg(feature = "cenc")]
+            decryption: None,
+            feed_buffer: BytesMut::new(),
+            feed_offset: 0,
+            pending_fragment: None,
+            pending_info: FragmentInfo::default(),
         }
     }
-}
 
-impl<'a, R> Mp4File<'a, R>
-where
-    R: AsyncRead + Unpin + AsyncSeek + 'a,
-{
-    pub async fn read_header(&mut self) -> Result<bool, BoxError> {
+    /// Reads every top-level box and returns whether a `moov` was found.
+    ///
+    /// Since 0.15 this returns [`Error`] rather than [`BoxError`], so that
+    /// failures of the [`DataStorage`] holding `mdat` can be reported as
+    /// [`Error::DataStorageError`]; box errors are in [`Error::BoxError`].
+    pub async fn read_header(&mut self) -> Result<bool, Error<S::Error>> {
+        let (got_moov, _) = self.read_boxes(0, false).await?;
+        self.read_aux_encryption().await?;
+
+        Ok(got_moov)
+    }
+
+    /// Like [`Mp4File::read_header`], but stops at the first `moof` if the
+    /// file ends with an `mfra`, which is then read through its `mfro`.
+    /// Fragments are left unread: find them with
+    /// [`Mp4File::fragment_offset`] and load them with
+    /// [`Mp4File::read_fragment`]. Without an `mfra` the whole file is read.
+    pub async fn read_header_fast(&mut self) -> Result<bool, Error<S::Error>> {
+        let (mut got_moov, moof_offset) = self.read_boxes(0, true).await?;
+
+        if let Some(moof_offset) = moof_offset {
+            self.mfra = self.read_mfra().await?;
+
+            if self.mfra.is_none() {
+                self.reader.seek(SeekFrom::Start(moof_offset)).await?;
+                got_moov |= self.read_boxes(moof_offset, false).await?.0;
+            }
+        }
+
+        self.read_aux_encryption().await?;
+
+        Ok(got_moov)
+    }
+
+    /// Reads the top-level boxes from `offset`, where the reader is. With
+    /// `until_moof` it returns at the first `moof`, along with its offset.
+    async fn read_boxes(
+        &mut self,
+        mut offset: u64,
+        until_moof: bool,
+    ) -> Result<(bool, Option<u64>), Error<S::Error>> {
         let mut buff = Vec::with_capacity(8192);
         let mut got_moov = false;
-        let mut offset = 0u64;
 
-        while let Some(BoxHeader { kind, size: mut s }) =
-            BoxHeader::read(&mut self.reader, &mut offset).await?
-        {
+        loop {
+            let box_offset = offset;
+            let Some(BoxHeader { kind, size: mut s }) =
+                BoxHeader::read(&mut self.reader, &mut offset).await?
+            else {
+                break;
+            };
+
+            if kind == BoxType::MoofBox && until_moof {
+                // what was read in front of it is kept for this moof alone
+                self.pending_info.offset = box_offset;
+                return Ok((got_moov, Some(box_offset)));
+            }
+
             if s >= HEADER_SIZE {
                 s -= HEADER_SIZE; // size without header
             }
             match kind {
-                BoxType::FtypBox => {
-                    println!("ftyp");
-
+                BoxType::FtypBox
+                | BoxType::MoovBox
+                | BoxType::MoofBox
+                | BoxType::EmsgBox
+                | BoxType::SidxBox
+                | BoxType::MfraBox
+                | BoxType::StypBox
+                | BoxType::PrftBox => {
                     if buff.len() < s as usize {
                         buff.resize(s as usize, 0);
                     }
+
                     self.reader.read_exact(&mut buff[0..s as usize]).await?;
                     offset += s;
 
-                    self.ftyp = Some(FtypBox::read_block(&mut &buff[0..s as usize])?);
+                    if let Some(Mp4Event::Init) =
+                        self.parse_box(kind, box_offset, &buff[0..s as usize])?
+                    {
+                        got_moov = true;
+                    }
                 }
 
-                BoxType::MoovBox => {
-                    println!("moov");
+                BoxType::MdatBox => {
+                    self.save_mdat(s, offset).await?;
+                    self.pending_fragment = None;
+                    offset += s;
+                }
 
-                    if buff.len() < s as usize {
-                        buff.resize(s as usize, 0);
-                    }
+                _ => {
+                    debug!(r#type = %kind, size = s, offset = box_offset, "skipping box");
 
-                    self.reader.read_exact(&mut buff[0..s as usize]).await?;
+                    self.skip_box(s).await?;
                     offset += s;
-
-                    got_moov = true;
-                    self.set_moov(MoovBox::read_block(&mut &buff[0..s as usize])?)?;
                 }
+            }
+        }
 
-                BoxType::MoofBox => {
-                    println!("moof");
+        Ok((got_moov, None))
+    }
 
-                    if buff.len() < s as usize {
-                        buff.resize(s as usize, 0);
-                    }
+    /// Reads the `mfra` at the end of the file, located through the `mfro`
+    /// it ends with.
+    pub async fn read_mfra(&mut self) -> Result<Option<MfraBox>, Error<S::Error>> {
+        let end = self.reader.seek(SeekFrom::End(0)).await?;
+        let mfro_size = MfroBox::default().box_size();
+        if end < mfro_size {
+            return Ok(None);
+        }
 
-                    let begin_offset = offset;
-                    self.reader.read_exact(&mut buff[0..s as usize]).await?;
-                    offset += s;
+        let mut mfro = [0; 16];
+        self.reader.seek(SeekFrom::Start(end - mfro_size)).await?;
+        self.reader.read_exact(&mut mfro).await?;
 
-                    self.add_moof(
-                        begin_offset,
-                        MoofBox::read_block(&mut &buff[0..s as usize])?,
-                    )?;
-                }
+        let mut data = &mfro[..];
+        let header = BoxHeader::read_sync(&mut data, &mut 0)?;
+        if header.map(|x| (x.kind, x.size)) != Some((BoxType::MfroBox, mfro_size)) {
+            return Ok(None);
+        }
 
-                BoxType::EmsgBox => {
-                    println!("emsg");
+        let size = MfroBox::read_block(&mut data)?.size as u64;
+        if size < HEADER_SIZE + mfro_size || size > end {
+            return Ok(None);
+        }
 
-                    if buff.len() < s as usize {
-                        buff.resize(s as usize, 0);
-                    }
+        let mut offset = end - size;
+        self.reader.seek(SeekFrom::Start(offset)).await?;
+        match BoxHeader::read(&mut self.reader, &mut offset).await? {
+            Some(header) if header.kind == BoxType::MfraBox && header.size == size => {}
+            _ => return Ok(None),
+        }
 
-                    self.reader.read_exact(&mut buff[0..s as usize]).await?;
-                    offset += s;
+        let mut buff = vec![0; (size - HEADER_SIZE) as usize];
+        self.reader.read_exact(&mut buff).await?;
 
-                    self.emsgs
-                        .push(EmsgBox::read_block(&mut &buff[0..s as usize])?);
-                }
+        Ok(Some(MfraBox::read_block(&mut &buff[..])?))
+    }
 
-                BoxType::MdatBox => {
-                    println!("mdat");
-                    self.save_box(BoxType::MdatBox, s, offset).await?;
-                    offset += s;
+    /// File offset of the `moof` with the last random access sample of a
+    /// track at or before the presentation `time`, in the track timescale,
+    /// according to the `mfra`.
+    pub fn fragment_offset(&self, track_id: u32, time: u64) -> Option<u64> {
+        let tfra = self.mfra.as_ref()?.tfra(track_id)?;
+        let entry = tfra.entry_at(time).or(tfra.entries.first())?;
+        Some(entry.moof_offset)
+    }
+
+    /// Reads the `moof` at `offset` and the `mdat` that follows it, adding
+    /// their samples to the tracks. Returns the new samples of each track,
+    /// none if the fragment was already read.
+    ///
+    /// Samples are inserted in decode order wherever the fragment is read
+    /// from, which moves the indices of the samples decoded after them.
+    pub async fn read_fragment(
+        &mut self,
+        offset: u64,
+    ) -> Result<Vec<(u32, Range<usize>)>, Error<S::Error>> {
+        let mut end = offset;
+        self.reader.seek(SeekFrom::Start(offset)).await?;
+        let moof_size = match BoxHeader::read(&mut self.reader, &mut end).await? {
+            Some(header) if header.kind == BoxType::MoofBox && header.size >= HEADER_SIZE => {
+                header.size - HEADER_SIZE
+            }
+            _ => return Err(BoxError::BoxNotFound(BoxType::MoofBox).into()),
+        };
+
+        let mut buff = vec![0; moof_size as usize];
+        self.reader.read_exact(&mut buff).await?;
+        end += moof_size;
+
+        let mdat_size = match BoxHeader::read(&mut self.reader, &mut end).await? {
+            Some(header) if header.kind == BoxType::MdatBox && header.size >= HEADER_SIZE => {
+                header.size - HEADER_SIZE
+            }
+            _ => return Err(BoxError::BoxNotFound(BoxType::MdatBox).into()),
+        };
+
+        if self.data_blocks.iter().any(|x| x.offset == end) {
+            return Ok(Vec::new());
+        }
+
+        let moof = MoofBox::read_block(&mut &buff[..])?;
+        let sequence_number = moof.mfhd.sequence_number;
+        let samples = self.add_moof(offset, moof)?;
+        let leading = self.pending_info.offset == offset;
+        self.start_fragment(offset, sequence_number, &samples, leading);
+        self.save_mdat(mdat_size, end).await?;
+
+        Ok(samples)
+    }
+
+    /// Resolves the sample encryption information of progressive tracks,
+    /// which `saiz`/`saio` point to in the data boxes. Fragments carry it in
+    /// `senc` instead.
+    async fn read_aux_encryption(&mut self) -> Result<(), Error<S::Error>> {
+        // (track_id, offset, [(sample_idx, size, iv_size)]) of each contiguous
+        // run of information
+        let mut runs = Vec::new();
+
+        for track in self.tracks.values() {
+            let stbl = &track.mdia.minf.stbl;
+            let (Some(saiz), Some(saio)) = (&stbl.saiz, &stbl.saio) else {
+                continue;
+            };
+
+            if !track.encryption.is_empty() || saio.offsets.is_empty() {
+                continue;
+            }
+
+            // one offset for all samples or one per chunk
+            let per_chunk = saio.offsets.len() > 1;
+            let mut offsets = saio.offsets.iter().copied();
+            let mut chunk_id = None;
+
+            for (idx, sample) in track.samples.iter().enumerate() {
+                let new_run = match chunk_id {
+                    None => true,
+                    Some(chunk_id) => per_chunk && chunk_id != sample.chunk_id,
+                };
+                if new_run {
+                    let Some(offset) = offsets.next() else {
+                        break;
+                    };
+                    runs.push((track.track_id, offset, Vec::new()));
                 }
+                chunk_id = Some(sample.chunk_id);
 
-                bt => {
-                    println!("{}", bt);
+                let iv_size = track
+                    .sample_entry(idx)
+                    .and_then(|x| x.sinf())
+                    .and_then(|x| x.tenc())
+                    .map(|x| x.default_per_sample_iv_size as usize)
+                    .unwrap_or(0);
 
-                    self.skip_box(bt, s).await?;
-                    offset += s;
+                if let Some((_, _, entries)) = runs.last_mut() {
+                    entries.push((idx, saiz.sample_info_size(idx), iv_size));
                 }
             }
-            println!("\n");
         }
 
-        Ok(got_moov)
+        for (track_id, offset, entries) in runs {
+            let size = entries.iter().map(|&(_, size, _)| size as u32).sum();
+            let Some(data) = self.read_data(offset, size).await? else {
+                continue;
+            };
+
+            let mut data = &data[..];
+            let track = self.tracks.get_mut(&track_id).unwrap();
+            for (idx, size, iv_size) in entries {
+                let mut info = Reader::take(&mut data, size as usize)?;
+                let use_subsamples = size as usize > iv_size;
+                let sample = SampleEncryption::read(&mut info, iv_size, use_subsamples)?;
+                track.encryption.insert(idx, sample);
+            }
+        }
+
+        Ok(())
     }
 
-    async fn skip_box(&mut self, bt: BoxType, size: u64) -> Result<(), BoxError> {
-        println!("skip {:?}", bt);
+    async fn skip_box(&mut self, size: u64) -> Result<(), BoxError> {
         self.reader.seek(SeekFrom::Current(size as _)).await?;
         Ok(())
     }
 
-    async fn save_box(&mut self, kind: BoxType, size: u64, offset: u64) -> Result<(), BoxError> {
-        println!("data_block {:?} {} - {}", kind, offset, offset + size);
+    async fn save_mdat(&mut self, size: u64, offset: u64) -> Result<(), Error<S::Error>> {
+        let cached = self.cache_policy.should_cache(size);
+        debug!(size, offset, cached, "mdat data block");
+
+        if cached {
+            let id = self
+                .storage
+                .save_data(&mut self.reader.take(size))
+                .await
+                .map_err(Error::DataStorageError)?;
 
-        if size < 128 * 1024 * 1024 {
-            let mut buffer = Vec::new();
-            tokio::io::copy(&mut self.reader.take(size), &mut buffer).await?;
             self.data_blocks.push(DataBlock {
-                kind,
                 offset,
                 size,
-                buffer: DataBlockBody::Memory(buffer.into()),
+                buffer: DataBlockBody::Storage(id),
             });
         } else {
-            self.skip_box(kind, size).await?;
+            self.skip_box(size).await?;
             self.data_blocks.push(DataBlock {
-                kind,
                 offset,
                 size,
                 buffer: DataBlockBody::Reader,
@@ -222,34 +537,260 @@ where
     }
 
     fn set_moov(&mut self, moov: MoovBox) -> Result<(), BoxError> {
+        self.psshs.extend(moov.psshs);
+
         for trak in moov.traks {
-            self.tracks
-                .insert(trak.tkhd.track_id, Mp4Track::new(trak, &mut self.offsets)?);
+            let mut track = Mp4Track::new(trak, &mut self.offsets)?;
+            track.movie_timescale = moov.mvhd.timescale;
+            track.trex = moov.mvex.as_ref().and_then(|mvex| {
+                mvex.trexs
+                    .iter()
+                    .find(|trex| trex.track_id == track.track_id)
+                    .cloned()
+            });
+            self.tracks.insert(track.track_id, track);
         }
 
         Ok(())
     }
 
-    fn add_moof(&mut self, offset: u64, moof: MoofBox) -> Result<(), BoxError> {
+    fn add_moof(
+        &mut self,
+        offset: u64,
+        moof: MoofBox,
+    ) -> Result<Vec<(u32, Range<usize>)>, BoxError> {
+        let mut samples = Vec::with_capacity(moof.trafs.len());
+        self.psshs.extend(moof.psshs);
+
         for traf in moof.trafs {
             let track_id = traf.tfhd.track_id;
 
             if let Some(track) = self.tracks.get_mut(&track_id) {
-                track.add_traf(offset, moof.mfhd.sequence_number, traf, &mut self.offsets)
+                let range =
+                    track.add_traf(offset, moof.mfhd.sequence_number, traf, &mut self.offsets);
+                samples.push((track_id, range));
             } else {
-                return Err(BoxError::TrakNotFound(track_id).into());
+                return Err(BoxError::TrakNotFound(track_id));
             }
         }
 
-        Ok(())
+        Ok(samples)
+    }
+
+    /// Records a fragment in file order. If the boxes read last came right
+    /// before its `moof`, they are recorded with it and the pending `emsg`s
+    /// are tied to it.
+    fn start_fragment(
+        &mut self,
+        offset: u64,
+        sequence_number: u32,
+        samples: &[(u32, Range<usize>)],
+        leading: bool,
+    ) {
+        let mut info = if leading {
+            std::mem::take(&mut self.pending_info)
+        } else {
+            FragmentInfo::default()
+        };
+        info.offset = offset;
+        info.sequence_number = sequence_number;
+        let idx = self.fragments.partition_point(|x| x.offset < offset);
+        self.fragments.insert(idx, info);
+
+        if !leading {
+            return;
+        }
+
+        // decode time of the fragment, with its timescale
+        let decode_time = samples
+            .iter()
+            .find(|(_, x)| !x.is_empty())
+            .map(|(track_id, range)| {
+                let track = &self.tracks[track_id];
+                (
+                    track.samples[range.start].start_time,
+                    track.mdia.mdhd.timescale.max(1),
+                )
+            });
+
+        for message in self.emsgs.iter_mut().rev() {
+            if message.sequence_number.is_some() {
+                break;
+            }
+
+            message.sequence_number = Some(sequence_number);
+            if let (Some(delta), Some((time, timescale))) =
+                (message.emsg.presentation_time_delta, decode_time)
+            {
+                let time = time as u128 * message.emsg.timescale as u128 / timescale as u128;
+                message.presentation_time = Some(time as u64 + delta as u64);
+            }
+        }
+    }
+
+    /// Parses a complete top-level metadata box.
+    fn parse_box(
+        &mut self,
+        kind: BoxType,
+        offset: u64,
+        mut data: &[u8],
+    ) -> Result<Option<Mp4Event>, BoxError> {
+        let _span = box_span!(kind, data.len(), offset);
+
+        Ok(match kind {
+            BoxType::FtypBox => {
+                self.ftyp = Some(FtypBox::read_block(&mut data)?);
+                None
+            }
+
+            BoxType::MoovBox => {
+                self.set_moov(MoovBox::read_block(&mut data)?)?;
+                Some(Mp4Event::Init)
+            }
+
+            BoxType::MoofBox => {
+                // default-base-is-moof offsets are relative to the first byte
+                // of the moof box, header included
+                let moof = MoofBox::read_block(&mut data)?;
+                let sequence_number = moof.mfhd.sequence_number;
+                let samples = self.add_moof(offset, moof)?;
+                self.start_fragment(offset, sequence_number, &samples, true);
+
+                // samples are only readable once the following mdat is in
+                self.pending_fragment = Some(Mp4Event::Fragment {
+                    sequence_number,
+                    samples,
+                });
+                None
+            }
+
+            BoxType::SidxBox => {
+                let anchor = offset + HEADER_SIZE + data.len() as u64;
+                self.sidxs.push((anchor, SidxBox::read_block(&mut data)?));
+                None
+            }
+
+            BoxType::MfraBox => {
+                self.mfra = Some(MfraBox::read_block(&mut data)?);
+                None
+            }
+
+            BoxType::StypBox => {
+                self.pending_info.styp = Some(StypBox::read_block(&mut data)?);
+                None
+            }
+
+            BoxType::PrftBox => {
+                self.pending_info
+                    .prfts
+                    .push(PrftBox::read_block(&mut data)?);
+                None
+            }
+
+            BoxType::EmsgBox => {
+                let emsg = EmsgBox::read_block(&mut data)?;
+                self.emsgs.push(EventMessage {
+                    sequence_number: None,
+                    presentation_time: emsg.presentation_time,
+                    emsg: emsg.clone(),
+                });
+                Some(Mp4Event::Emsg(emsg))
+            }
+
+            _ => None,
+        })
     }
 
+    /// Pushes the next bytes of a (possibly growing) file to the parser and
+    /// returns what became available with them.
+    ///
+    /// This is the live counterpart of [`Mp4File::read_header`]: boxes are
+    /// parsed as soon as they are complete, and since the data may not be
+    /// in the source reader, every `mdat` is saved to the storage regardless
+    /// of the cache policy. Incomplete boxes are buffered until the rest
+    /// arrives.
+    pub async fn feed(&mut self, data: &[u8]) -> Result<Vec<Mp4Event>, Error<S::Error>> {
+        self.feed_buffer.extend_from_slice(data);
+
+        let mut events = Vec::new();
+        loop {
+            let buffered = self.feed_buffer.len();
+            if buffered < 8 || (buffered < 16 && self.feed_buffer[0..4] == [0, 0, 0, 1]) {
+                break;
+            }
+
+            let mut header_size = 0;
+            let Some(BoxHeader { kind, size }) =
+                BoxHeader::read_sync(&mut &self.feed_buffer[..], &mut header_size)?
+            else {
+                break;
+            };
+
+            if size < HEADER_SIZE {
+                return Err(BoxError::InvalidData("box extending to the end of the stream").into());
+            }
+
+            let box_size = header_size + size - HEADER_SIZE;
+            if (buffered as u64) < box_size {
+                break;
+            }
+
+            let box_offset = self.feed_offset;
+            let payload = self
+                .feed_buffer
+                .split_to(box_size as usize)
+                .freeze()
+                .slice(header_size as usize..);
+            self.feed_offset += box_size;
+
+            match kind {
+                BoxType::MdatBox => {
+                    let id = self
+                        .storage
+                        .save_data(&mut &payload[..])
+                        .await
+                        .map_err(Error::DataStorageError)?;
+
+                    self.data_blocks.push(DataBlock {
+                        offset: box_offset + header_size,
+                        size: payload.len() as u64,
+                        buffer: DataBlockBody::Storage(id),
+                    });
+
+                    events.extend(self.pending_fragment.take());
+                }
+
+                _ => {
+                    if let Some(event) = self.parse_box(kind, box_offset, &payload)? {
+                        events.push(event);
+                    } else if !matches!(
+                        kind,
+                        BoxType::FtypBox
+                            | BoxType::MoofBox
+                            | BoxType::SidxBox
+                            | BoxType::MfraBox
+                            | BoxType::StypBox
+                            | BoxType::PrftBox
+                    ) {
+                        debug!(r#type = %kind, size = box_size, offset = box_offset, "skipping box");
+                    }
+                }
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Reads the data of a sample, `None` if the track or sample is unknown
+    /// or the sample lies outside the read data boxes.
+    ///
+    /// Like [`Mp4File::read_header`], this returns [`Error`] since 0.15.
     #[inline]
     pub async fn read_sample_data(
         &mut self,
         track_id: u32,
         sample_idx: usize,
-    ) -> Result<Option<Bytes>, BoxError> {
+    ) -> Result<Option<Bytes>, Error<S::Error>> {
         let Some(track) = self.tracks.get(&track_id) else {
             return Ok(None);
         };
@@ -258,18 +799,223 @@ where
             return Ok(None);
         };
 
+        let (offset, size) = (sample.offset, sample.size);
+        let Some(bytes) = self.read_data(offset, size).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(self.decrypt(track_id, sample_idx, bytes)?))
+    }
+
+    /// Byte range of the indexed subsegment (`moof` + `mdat` pairs) that
+    /// holds the presentation `time` of a track, in the track timescale, as
+    /// given by the `sidx` boxes that reference the track. Times before the
+    /// first subsegment map to it.
+    pub fn segment_byte_range(&self, track_id: u32, time: u64) -> Option<Range<u64>> {
+        let timescale = self.tracks.get(&track_id)?.mdia.mdhd.timescale.max(1) as u128;
+
+        // the earliest subsegment, with its start in the track timescale
+        let mut first: Option<(u64, Range<u64>)> = None;
+        for (anchor, sidx) in self.sidxs.iter() {
+            if sidx.reference_id != track_id {
+                continue;
+            }
+
+            let sidx_timescale = sidx.timescale.max(1) as u128;
+            let sidx_time = (time as u128 * sidx_timescale / timescale) as u64;
+            let Some((start_time, range, reference)) = sidx.subsegment_at(*anchor, sidx_time)
+            else {
+                continue;
+            };
+
+            // references to other sidx boxes are resolved through those
+            if reference.reference_type {
+                continue;
+            }
+
+            if start_time <= sidx_time {
+                return Some(range);
+            }
+
+            let start_time = (start_time as u128 * timescale / sidx_timescale) as u64;
+            if first.as_ref().map(|x| start_time < x.0).unwrap_or(true) {
+                first = Some((start_time, range));
+            }
+        }
+
+        first.map(|(_, range)| range)
+    }
+
+    /// Reads a `stpp` sample, split into the XML document and its image
+    /// resources along the `subs` entries of the track.
+    pub async fn read_stpp_sample(
+        &mut self,
+        track_id: u32,
+        sample_idx: usize,
+    ) -> Result<Option<StppSample>, Error<S::Error>> {
+        let Some(bytes) = self.read_sample_data(track_id, sample_idx).await? else {
+            return Ok(None);
+        };
+
+        let subsamples = self.tracks[&track_id].subsamples(sample_idx);
+        Ok(Some(StppSample::parse(bytes, subsamples)?))
+    }
+
+    /// Decodes the CEA-608 CC1 captions of a H.264/HEVC track (from SEI
+    /// messages) or a `c608` track. Times are in the track timescale.
+    pub async fn read_captions(&mut self, track_id: u32) -> Result<Vec<Caption>, Error<S::Error>> {
+        let track = self
+            .tracks
+            .get(&track_id)
+            .ok_or(BoxError::TrakNotFound(track_id))?;
+
+        // each sample goes by its own sample entry, as a track may have
+        // several
+        let mut samples: Vec<_> = (0..track.samples.len())
+            .filter_map(|idx| {
+                let source = CaptionSource::from_sample(track, idx)?;
+                Some((source, track.samples[idx].clone()))
+            })
+            .collect();
+
+        // byte pairs are stored in decode order but shown in presentation order
+        samples.sort_by_key(|(_, x)| x.presentation_time());
+
+        let mut decoder = Cea608Decoder::default();
+        let mut end_time = 0;
+        for (source, sample) in samples.iter() {
+            let time = sample.presentation_time().max(0) as u64;
+            end_time = end_time.max(time + sample.duration as u64);
+
+            let bytes = self
+                .read_data(sample.offset, sample.size)
+                .await?
+                .ok_or(BoxError::InvalidData("sample is outside of any data box"))?;
+
+            for cc in source.cc_data(&bytes)? {
+                if cc.valid && cc.cc_type == CcData::NTSC_FIELD_1 {
+                    decoder.push(time, cc.data);
+                }
+            }
+        }
+
+        Ok(decoder.finish(end_time))
+    }
+
+    /// Streams the samples of a track in decode order.
+    pub fn samples(&mut self, track_id: u32) -> SampleStream<'_, Mp4Sample, S::Error> {
+        let stream = try_stream! {
+            let track = self
+                .tracks
+                .get(&track_id)
+                .ok_or(BoxError::TrakNotFound(track_id))?;
+
+            for (sample_idx, sample) in track.samples.clone().into_iter().enumerate() {
+                yield self.read_sample(track_id, sample_idx, &sample).await?;
+            }
+        };
+
+        stream.boxed_local()
+    }
+
+    /// Streams the samples of all tracks, interleaved in the given order and
+    /// tagged with their track id.
+    pub fn all_samples(
+        &mut self,
+        order: SampleOrder,
+    ) -> SampleStream<'_, (u32, Mp4Sample), S::Error> {
+        let stream = try_stream! {
+            let mut samples = Vec::new();
+            for track in self.tracks.values() {
+                let timescale = track.mdia.mdhd.timescale.max(1) as u128;
+
+                samples.extend(track.samples.iter().enumerate().map(|(sample_idx, sample)| {
+                    let key = match order {
+                        SampleOrder::FileOffset => sample.offset as u128,
+                        SampleOrder::DecodeTime => {
+                            sample.start_time as u128 * 1_000_000_000 / timescale
+                        }
+                    };
+
+                    (key, track.track_id, sample_idx, sample.clone())
+                }));
+            }
+
+            // stable, so samples with the same key keep their track order
+            samples.sort_by_key(|&(key, track_id, _, _)| (key, track_id));
+
+            for (_, track_id, sample_idx, sample) in samples {
+                yield (track_id, self.read_sample(track_id, sample_idx, &sample).await?);
+            }
+        };
+
+        stream.boxed_local()
+    }
+
+    async fn read_sample(
+        &mut self,
+        track_id: u32,
+        sample_idx: usize,
+        sample: &Mp4SampleOffset,
+    ) -> Result<Mp4Sample, Error<S::Error>> {
+        let bytes = self
+            .read_data(sample.offset, sample.size)
+            .await?
+            .ok_or(BoxError::InvalidData("sample is outside of any data box"))?;
+        let bytes = self.decrypt(track_id, sample_idx, bytes)?;
+
+        Ok(Mp4Sample {
+            start_time: sample.start_time,
+            duration: sample.duration,
+            rendering_offset: sample.rendering_offset,
+            is_sync: sample.is_sync,
+            bytes,
+        })
+    }
+
+    /// Decrypts a sample with [`Mp4File::decryption`] if its sample entry
+    /// is protected.
+    #[cfg(feature = "cenc")]
+    fn decrypt(&self, track_id: u32, sample_idx: usize, bytes: Bytes) -> Result<Bytes, BoxError> {
+        let Some(ref keys) = self.decryption else {
+            return Ok(bytes);
+        };
+
+        let track = &self.tracks[&track_id];
+        let Some(sinf) = track.sample_entry(sample_idx).and_then(|x| x.sinf()) else {
+            return Ok(bytes);
+        };
+
+        let mut data = BytesMut::from(bytes.as_ref());
+        keys.decrypt(sinf, track.sample_encryption(sample_idx), &mut data)?;
+        Ok(data.freeze())
+    }
+
+    #[cfg(not(feature = "cenc"))]
+    fn decrypt(&self, _track_id: u32, _sample_idx: usize, bytes: Bytes) -> Result<Bytes, BoxError> {
+        Ok(bytes)
+    }
+
+    async fn read_data(
+        &mut self,
+        offset: u64,
+        size: u32,
+    ) -> Result<Option<Bytes>, Error<S::Error>> {
         for block in &self.data_blocks {
             let range = block.offset..block.offset + block.size;
 
-            if range.contains(&sample.offset) {
+            if range.contains(&offset) {
                 return Ok(Some(match &block.buffer {
-                    DataBlockBody::Memory(mem) => {
-                        let offset = sample.offset - block.offset;
-                        mem.slice(offset as usize..offset as usize + sample.size as usize)
+                    DataBlockBody::Storage(id) => {
+                        let offset = offset - block.offset;
+                        self.storage
+                            .read_data(id, offset..offset + size as u64)
+                            .await
+                            .map_err(Error::DataStorageError)?
                     }
                     DataBlockBody::Reader => {
-                        let mut buff = vec![0u8; sample.size as _];
-                        self.reader.seek(SeekFrom::Start(sample.offset)).await?;
+                        let mut buff = vec![0u8; size as _];
+                        self.reader.seek(SeekFrom::Start(offset)).await?;
                         self.reader.read_exact(&mut buff).await?;
                         Bytes::from_iter(buff)
                     }
@@ -280,3 +1026,654 @@ where
         Ok(None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use futures::TryStreamExt;
+
+    use super::*;
+    use crate::test_util::{avc_config, config};
+    use crate::{
+        AacConfig, EncvBox, FourCC, FragmentBoundary, FrmaBox, MediaConfig, MediaType, Mp4Box,
+        Mp4FragmentWriter, Mp4Writer, SaioBox, SaizBox, SampleEntry, SchiBox, SchmBox, SeekMode,
+        SencBox, SinfBox, TencBox, TfraBox, TfraEntry, TrackConfig, WriteBox,
+    };
+
+    /// A fragmented file with one AVC track of 40ms samples, starting a
+    /// fragment every `gop` samples. Returns the track id along with it.
+    async fn fragmented_avc(samples: Vec<Vec<u8>>, gop: usize) -> (u32, Vec<u8>) {
+        let mut writer = Mp4FragmentWriter::new(
+            Cursor::new(Vec::new()),
+            &config(),
+            FragmentBoundary::Keyframe,
+        );
+        let track_id = writer.add_track(&avc_config()).unwrap();
+        for (idx, bytes) in samples.into_iter().enumerate() {
+            let sample = Mp4Sample {
+                start_time: idx as u64 * 40,
+                duration: 40,
+                rendering_offset: 0,
+                is_sync: idx % gop == 0,
+                bytes: Bytes::from(bytes),
+            };
+            writer.write_sample(track_id, &sample).await.unwrap();
+        }
+        writer.write_end().await.unwrap();
+
+        (track_id, writer.into_writer().into_inner())
+    }
+
+    /// Samples of `10 + idx` bytes of `idx`.
+    fn numbered_samples(count: usize) -> Vec<Vec<u8>> {
+        (0..count).map(|idx| vec![idx as u8; 10 + idx]).collect()
+    }
+
+    /// Edits the `moov` and each `moof` of a file, moving the sample data
+    /// offsets by as much as the `moof` grew.
+    fn rewrite(
+        data: &[u8],
+        mut edit_moov: impl FnMut(&mut MoovBox),
+        mut edit_moof: impl FnMut(&mut MoofBox),
+    ) -> Vec<u8> {
+        let mut rewritten = Vec::new();
+        for bx in top_level_boxes(data) {
+            match &bx[4..8] {
+                b"moov" => {
+                    let mut moov = MoovBox::read_block(&mut &bx[8..]).unwrap();
+                    edit_moov(&mut moov);
+                    moov.write_box(&mut rewritten).unwrap();
+                }
+
+                b"moof" => {
+                    let mut moof = MoofBox::read_block(&mut &bx[8..]).unwrap();
+                    edit_moof(&mut moof);
+
+                    let delta = moof.box_size() as i32 - bx.len() as i32;
+                    for trun in moof.trafs.iter_mut().filter_map(|x| x.trun.as_mut()) {
+                        trun.data_offset = trun.data_offset.map(|x| x + delta);
+                    }
+                    moof.write_box(&mut rewritten).unwrap();
+                }
+
+                _ => rewritten.extend_from_slice(bx),
+            }
+        }
+        rewritten
+    }
+
+    #[tokio::test]
+    async fn test_cache_policy() {
+        let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config())
+            .await
+            .unwrap();
+        let track_id = writer
+            .add_track(&TrackConfig::from(MediaConfig::AacConfig(
+                AacConfig::default(),
+            )))
+            .unwrap();
+        for idx in 0..10u8 {
+            let sample = Mp4Sample {
+                start_time: idx as u64 * 1024,
+                duration: 1024,
+                rendering_offset: 0,
+                is_sync: true,
+                bytes: Bytes::from(vec![idx; 16 + idx as usize]),
+            };
+            writer.write_sample(track_id, &sample).await.unwrap();
+        }
+        writer.write_end().await.unwrap();
+        let data = writer.into_writer().into_inner();
+
+        for policy in [
+            CachePolicy::Always,
+            CachePolicy::BelowSize(16),
+            CachePolicy::Never,
+        ] {
+            let mut reader = Cursor::new(data.clone());
+            let mut mp4_file = Mp4File::with_storage(&mut reader, MemoryStorage::default(), policy);
+            assert!(mp4_file.read_header().await.unwrap());
+
+            let cached = if policy == CachePolicy::Always { 1 } else { 0 };
+            assert_eq!(mp4_file.storage.data.len(), cached);
+
+            for idx in 0..10u8 {
+                let bytes = mp4_file
+                    .read_sample_data(track_id, idx as usize)
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(bytes, vec![idx; 16 + idx as usize]);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sample_streams() {
+        let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config())
+            .await
+            .unwrap();
+        let video = writer.add_track(&avc_config()).unwrap();
+        let audio = writer
+            .add_track(&TrackConfig::from(MediaConfig::AacConfig(
+                AacConfig::default(),
+            )))
+            .unwrap();
+        let audio_timescale =
+            TrackConfig::from(MediaConfig::AacConfig(AacConfig::default())).timescale;
+
+        // 3s of 40ms video frames and 1024 sample audio frames
+        let audio_count = (3 * audio_timescale).div_ceil(1024);
+        for idx in 0..75u32 {
+            let sample = Mp4Sample {
+                start_time: idx as u64 * 40,
+                duration: 40,
+                rendering_offset: 0,
+                is_sync: idx == 0,
+                bytes: Bytes::from(vec![idx as u8; 8]),
+            };
+            writer.write_sample(video, &sample).await.unwrap();
+        }
+        for idx in 0..audio_count {
+            let sample = Mp4Sample {
+                start_time: idx as u64 * 1024,
+                duration: 1024,
+                rendering_offset: 0,
+                is_sync: true,
+                bytes: Bytes::from(vec![!(idx as u8); 4]),
+            };
+            writer.write_sample(audio, &sample).await.unwrap();
+        }
+        writer.write_end().await.unwrap();
+
+        let mut reader = Cursor::new(writer.into_writer().into_inner());
+        let mut mp4_file = Mp4File::new(&mut reader);
+        assert!(mp4_file.read_header().await.unwrap());
+
+        let samples: Vec<_> = mp4_file.samples(video).try_collect().await.unwrap();
+        assert_eq!(samples.len(), 75);
+        for (idx, sample) in samples.iter().enumerate() {
+            assert_eq!(sample.start_time, idx as u64 * 40);
+            assert_eq!(sample.bytes, vec![idx as u8; 8]);
+        }
+
+        {
+            let missing = mp4_file.samples(3);
+            futures::pin_mut!(missing);
+            assert!(missing.try_next().await.is_err());
+        }
+
+        // the first two seconds of video are flushed before any audio, the
+        // last chunks of both tracks only at the end
+        let samples: Vec<_> = mp4_file
+            .all_samples(SampleOrder::FileOffset)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(samples.len(), 75 + audio_count as usize);
+        assert!(samples[..50].iter().all(|(track_id, _)| *track_id == video));
+        assert_eq!(samples[50].0, audio);
+
+        let samples: Vec<_> = mp4_file
+            .all_samples(SampleOrder::DecodeTime)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(samples.len(), 75 + audio_count as usize);
+        let times: Vec<_> = samples
+            .iter()
+            .map(|(track_id, sample)| {
+                let timescale = if *track_id == video {
+                    1000
+                } else {
+                    audio_timescale
+                };
+                sample.start_time as f64 / timescale as f64
+            })
+            .collect();
+        assert!(times.windows(2).all(|x| x[0] <= x[1]));
+        assert_eq!((samples[0].0, samples[1].0), (video, audio));
+    }
+
+    #[tokio::test]
+    async fn test_feed() {
+        let (track_id, mut data) = fragmented_avc(numbered_samples(50), 25).await;
+
+        // put an emsg in front of the first fragment
+        let emsg = EmsgBox {
+            version: 1,
+            timescale: 1000,
+            presentation_time: Some(500),
+            scheme_id_uri: String::from("urn:test"),
+            ..Default::default()
+        };
+        let mut emsg_data = Vec::new();
+        emsg.write_box(&mut emsg_data).unwrap();
+        let moof_pos = data.windows(4).position(|x| x == b"moof").unwrap() - 4;
+        data.splice(moof_pos..moof_pos, emsg_data);
+
+        let mut reader = Cursor::new(Vec::new());
+        let mut mp4_file = Mp4File::new(&mut reader);
+
+        let mut events = Vec::new();
+        for chunk in data.chunks(100) {
+            let new_events = mp4_file.feed(chunk).await.unwrap();
+
+            // samples announced by an event are readable right away
+            for event in new_events.iter() {
+                if let Mp4Event::Fragment { samples, .. } = event {
+                    for (track_id, range) in samples {
+                        for idx in range.clone() {
+                            let bytes = mp4_file
+                                .read_sample_data(*track_id, idx)
+                                .await
+                                .unwrap()
+                                .unwrap();
+                            assert_eq!(bytes, vec![idx as u8; 10 + idx]);
+                        }
+                    }
+                }
+            }
+
+            events.extend(new_events);
+        }
+
+        assert_eq!(
+            events,
+            vec![
+                Mp4Event::Init,
+                Mp4Event::Emsg(emsg),
+                Mp4Event::Fragment {
+                    sequence_number: 1,
+                    samples: vec![(track_id, 0..25)],
+                },
+                Mp4Event::Fragment {
+                    sequence_number: 2,
+                    samples: vec![(track_id, 25..50)],
+                },
+            ]
+        );
+        assert_eq!(mp4_file.tracks[&track_id].samples.len(), 50);
+    }
+
+    fn top_level_boxes(mut data: &[u8]) -> Vec<&[u8]> {
+        let mut boxes = Vec::new();
+        while data.len() >= 8 {
+            let size = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+            let (bx, rest) = data.split_at(size);
+            boxes.push(bx);
+            data = rest;
+        }
+        boxes
+    }
+
+    fn cenc_sinf(data_format: FourCC, iv_size: u8) -> SinfBox {
+        SinfBox {
+            frma: FrmaBox { data_format },
+            schm: Some(SchmBox {
+                scheme_type: SchmBox::CENC,
+                scheme_version: 0x00010000,
+                ..Default::default()
+            }),
+            schi: Some(SchiBox {
+                tenc: Some(TencBox {
+                    default_is_protected: 1,
+                    default_per_sample_iv_size: iv_size,
+                    default_kid: [0x11; 16],
+                    ..Default::default()
+                }),
+            }),
+        }
+    }
+
+    fn protect(entry: &mut SampleEntry, iv_size: u8) {
+        let sinf = cenc_sinf(u32::from(entry.get_type()).into(), iv_size);
+        *entry = SampleEntry::Encv(EncvBox::new(entry.clone(), sinf));
+    }
+
+    #[tokio::test]
+    async fn test_protected_fragments() {
+        let (track_id, data) = fragmented_avc(numbered_samples(50), 25).await;
+
+        let pssh = PsshBox {
+            system_id: PsshBox::SYSTEM_ID_COMMON,
+            version: 1,
+            kids: vec![[0x11; 16]],
+            ..Default::default()
+        };
+
+        // protect the track, and give every fragment a senc and a pssh
+        let protected = rewrite(
+            &data,
+            |moov| {
+                protect(&mut moov.traks[0].mdia.minf.stbl.stsd.entries[0], 8);
+                moov.psshs.push(pssh.clone());
+            },
+            |moof| {
+                let traf = &mut moof.trafs[0];
+                let sample_count = traf.trun.as_ref().unwrap().sample_count;
+                let samples: Vec<_> = (0..sample_count)
+                    .map(|idx| SampleEncryption {
+                        iv: vec![idx as u8; 8],
+                        subsamples: vec![],
+                    })
+                    .collect();
+                traf.senc = Some(SencBox::new(&samples));
+                moof.psshs.push(pssh.clone());
+            },
+        );
+
+        let mut reader = Cursor::new(protected);
+        let mut mp4_file = Mp4File::new(&mut reader);
+        assert!(mp4_file.read_header().await.unwrap());
+        assert_eq!(mp4_file.psshs, vec![pssh; 3]);
+
+        let track = &mp4_file.tracks[&track_id];
+        assert_eq!(track.media_type().unwrap(), MediaType::H264);
+        let sinf = track.sample_entry(30).unwrap().sinf().unwrap();
+        assert_eq!(sinf.scheme_type(), Some(SchmBox::CENC));
+        assert_eq!(sinf.tenc().unwrap().default_kid, [0x11; 16]);
+        assert_eq!(track.sample_encryption(30).unwrap().iv, vec![5; 8]);
+
+        let bytes = mp4_file.read_sample_data(track_id, 30).await.unwrap();
+        assert_eq!(bytes.unwrap(), vec![30; 40]);
+    }
+
+    #[tokio::test]
+    async fn test_aux_encryption_info() {
+        let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config())
+            .await
+            .unwrap();
+        let track_id = writer
+            .add_track(&TrackConfig::from(MediaConfig::AacConfig(
+                AacConfig::default(),
+            )))
+            .unwrap();
+        for idx in 0..10u8 {
+            let sample = Mp4Sample {
+                start_time: idx as u64 * 1024,
+                duration: 1024,
+                rendering_offset: 0,
+                is_sync: true,
+                bytes: Bytes::from(vec![idx; 16]),
+            };
+            writer.write_sample(track_id, &sample).await.unwrap();
+        }
+        writer.write_end().await.unwrap();
+        let data = writer.into_writer().into_inner();
+
+        // the IVs go into an extra mdat after the moov, which is written last
+        let boxes = top_level_boxes(&data);
+        let mut protected: Vec<u8> = boxes[..boxes.len() - 1].concat();
+        let mut moov = MoovBox::read_block(&mut &boxes[boxes.len() - 1][8..]).unwrap();
+        let stbl = &mut moov.traks[0].mdia.minf.stbl;
+        protect(&mut stbl.stsd.entries[0], 16);
+        stbl.saiz = Some(SaizBox {
+            default_sample_info_size: 16,
+            sample_count: 10,
+            ..Default::default()
+        });
+        stbl.saio = Some(SaioBox {
+            offsets: vec![0],
+            ..Default::default()
+        });
+
+        let offset = protected.len() as u64 + moov.box_size() + HEADER_SIZE;
+        moov.traks[0].mdia.minf.stbl.saio.as_mut().unwrap().offsets = vec![offset];
+        moov.write_box(&mut protected).unwrap();
+
+        let ivs: Vec<u8> = (0..10u8).flat_map(|idx| [idx; 16]).collect();
+        protected.extend_from_slice(&(ivs.len() as u32 + 8).to_be_bytes());
+        protected.extend_from_slice(b"mdat");
+        protected.extend_from_slice(&ivs);
+
+        let mut reader = Cursor::new(protected);
+        let mut mp4_file = Mp4File::new(&mut reader);
+        assert!(mp4_file.read_header().await.unwrap());
+
+        let track = &mp4_file.tracks[&track_id];
+        assert_eq!(track.encryption.len(), 10);
+        assert_eq!(track.sample_encryption(7).unwrap().iv, vec![7; 16]);
+        assert!(track.sample_encryption(7).unwrap().subsamples.is_empty());
+    }
+
+    #[cfg(feature = "cenc")]
+    #[tokio::test]
+    async fn test_decryption() {
+        use crate::SubsampleEncryption;
+
+        let sinf = cenc_sinf(str::parse("avc1").unwrap(), 8);
+        let keys = ClearKey::new(HashMap::from([([0x11; 16], [0x22; 16])]));
+
+        // encrypt everything but a 4 byte header of each sample
+        let mut samples = Vec::new();
+        let mut infos = Vec::new();
+        for idx in 0..20usize {
+            let mut bytes = vec![idx as u8; 40 + idx];
+            let info = SampleEncryption {
+                iv: vec![idx as u8; 8],
+                subsamples: vec![SubsampleEncryption {
+                    clear_bytes: 4,
+                    encrypted_bytes: bytes.len() as u32 - 4,
+                }],
+            };
+            keys.encrypt(&sinf, Some(&info), &mut bytes).unwrap();
+            samples.push(bytes);
+            infos.push(info);
+        }
+        let (track_id, data) = fragmented_avc(samples, 10).await;
+
+        let mut infos = infos.into_iter();
+        let protected = rewrite(
+            &data,
+            |moov| protect(&mut moov.traks[0].mdia.minf.stbl.stsd.entries[0], 8),
+            |moof| {
+                let traf = &mut moof.trafs[0];
+                let sample_count = traf.trun.as_ref().unwrap().sample_count;
+                let samples: Vec<_> = infos.by_ref().take(sample_count as usize).collect();
+                traf.senc = Some(SencBox::new(&samples));
+            },
+        );
+
+        let mut reader = Cursor::new(protected);
+        let mut mp4_file = Mp4File::new(&mut reader);
+        assert!(mp4_file.read_header().await.unwrap());
+
+        let encrypted = mp4_file.read_sample_data(track_id, 12).await.unwrap();
+        assert_eq!(encrypted.as_deref().unwrap()[..4], [12; 4]);
+        assert_ne!(encrypted.unwrap(), vec![12; 52]);
+
+        mp4_file.decryption = Some(keys);
+        let bytes = mp4_file.read_sample_data(track_id, 12).await.unwrap();
+        assert_eq!(bytes.unwrap(), vec![12; 52]);
+
+        let samples: Vec<_> = mp4_file.samples(track_id).try_collect().await.unwrap();
+        assert_eq!(samples.len(), 20);
+        for (idx, sample) in samples.iter().enumerate() {
+            assert_eq!(sample.bytes, vec![idx as u8; 40 + idx]);
+        }
+
+        // a missing key is an error
+        mp4_file.decryption = Some(ClearKey::default());
+        assert!(mp4_file.read_sample_data(track_id, 12).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mfra_fast_open() {
+        let (track_id, mut data) = fragmented_avc(numbered_samples(100), 25).await;
+
+        // index the first sample of every fragment
+        let mut moof_offset = 0;
+        let mut entries = Vec::new();
+        for bx in top_level_boxes(&data) {
+            if &bx[4..8] == b"moof" {
+                entries.push(TfraEntry {
+                    time: entries.len() as u64 * 1000,
+                    moof_offset,
+                    traf_number: 1,
+                    trun_number: 1,
+                    sample_number: 1,
+                });
+            }
+            moof_offset += bx.len() as u64;
+        }
+        assert_eq!(entries.len(), 4);
+        let moof_offsets: Vec<u64> = entries.iter().map(|x| x.moof_offset).collect();
+
+        let without_mfra = data.clone();
+        let mfra = MfraBox {
+            tfras: vec![TfraBox {
+                version: 1,
+                track_id,
+                entries,
+                ..Default::default()
+            }],
+            mfro: MfroBox::default(),
+        };
+        mfra.write_box(&mut data).unwrap();
+
+        let mut reader = Cursor::new(data.clone());
+        let mut mp4_file = Mp4File::new(&mut reader);
+        assert!(mp4_file.read_header_fast().await.unwrap());
+        assert!(mp4_file.tracks[&track_id].samples.is_empty());
+        assert_eq!(mp4_file.mfra.as_ref().unwrap().tfras[0].entries.len(), 4);
+
+        let offset = mp4_file.fragment_offset(track_id, 2500).unwrap();
+        assert_eq!(offset, moof_offsets[2]);
+        let samples = mp4_file.read_fragment(offset).await.unwrap();
+        assert_eq!(samples, vec![(track_id, 0..25)]);
+        assert_eq!(mp4_file.tracks[&track_id].samples[0].start_time, 2000);
+        let bytes = mp4_file.read_sample_data(track_id, 0).await.unwrap();
+        assert_eq!(bytes.unwrap(), vec![50; 60]);
+
+        // a fragment is only added once
+        assert!(mp4_file.read_fragment(offset).await.unwrap().is_empty());
+        assert!(mp4_file.read_fragment(moof_offsets[0] + 1).await.is_err());
+
+        // an earlier fragment goes in front, so seeking still works
+        let samples = mp4_file.read_fragment(moof_offsets[1]).await.unwrap();
+        assert_eq!(samples, vec![(track_id, 0..25)]);
+        let track = &mp4_file.tracks[&track_id];
+        assert_eq!(track.samples[25].start_time, 2000);
+        assert_eq!(track.seek(1000, SeekMode::Exact), Some(0));
+        assert_eq!(track.seek(2000, SeekMode::Exact), Some(25));
+        let bytes = mp4_file.read_sample_data(track_id, 25).await.unwrap();
+        assert_eq!(bytes.unwrap(), vec![50; 60]);
+
+        // the regular path reads mfra along with everything else
+        let mut reader = Cursor::new(data);
+        let mut mp4_file = Mp4File::new(&mut reader);
+        assert!(mp4_file.read_header().await.unwrap());
+        assert_eq!(mp4_file.tracks[&track_id].samples.len(), 100);
+        assert!(mp4_file.mfra.is_some());
+
+        // without mfra the fast path falls back to reading the fragments
+        let mut reader = Cursor::new(without_mfra);
+        let mut mp4_file = Mp4File::new(&mut reader);
+        assert!(mp4_file.read_header_fast().await.unwrap());
+        assert_eq!(mp4_file.tracks[&track_id].samples.len(), 100);
+        assert!(mp4_file.mfra.is_none());
+        assert_eq!(mp4_file.fragment_offset(track_id, 0), None);
+    }
+
+    #[tokio::test]
+    async fn test_segment_boxes() {
+        let (track_id, data) = fragmented_avc(numbered_samples(50), 25).await;
+
+        let absolute = EmsgBox {
+            version: 1,
+            timescale: 1000,
+            presentation_time: Some(500),
+            scheme_id_uri: String::from("urn:test"),
+            ..Default::default()
+        };
+        let relative = EmsgBox {
+            version: 0,
+            timescale: 90000,
+            presentation_time_delta: Some(4500),
+            scheme_id_uri: String::from("urn:test"),
+            ..Default::default()
+        };
+        let styp = StypBox {
+            major_brand: str::parse("cmfs").unwrap(),
+            minor_version: 0,
+            compatible_brands: vec![str::parse("cmfs").unwrap()],
+        };
+        let prft = PrftBox {
+            version: 1,
+            flags: PrftBox::TIME_ENCODER_OUTPUT,
+            reference_track_id: track_id,
+            ntp_timestamp: 0xE9A1_B2C3_0000_0000,
+            media_time: 1000,
+        };
+
+        // an absolute emsg before the first fragment, a styp, prft and
+        // relative emsg before the second
+        let mut segmented = Vec::new();
+        let mut moof_offsets = Vec::new();
+        for bx in top_level_boxes(&data) {
+            if &bx[4..8] == b"moof" {
+                if moof_offsets.is_empty() {
+                    absolute.write_box(&mut segmented).unwrap();
+                } else {
+                    styp.write_box(&mut segmented).unwrap();
+                    prft.write_box(&mut segmented).unwrap();
+                    relative.write_box(&mut segmented).unwrap();
+                }
+                moof_offsets.push(segmented.len() as u64);
+            }
+            segmented.extend_from_slice(bx);
+        }
+        MfraBox::default().write_box(&mut segmented).unwrap();
+
+        let mut reader = Cursor::new(segmented.clone());
+        let mut mp4_file = Mp4File::new(&mut reader);
+        assert!(mp4_file.read_header().await.unwrap());
+
+        assert_eq!(mp4_file.fragments.len(), 2);
+        assert_eq!(mp4_file.fragments[0].styp, None);
+        assert!(mp4_file.fragments[0].prfts.is_empty());
+        assert_eq!(mp4_file.fragments[1].sequence_number, 2);
+        assert_eq!(mp4_file.fragments[1].styp, Some(styp));
+        assert_eq!(mp4_file.fragments[1].prfts, vec![prft]);
+
+        assert_eq!(
+            mp4_file.emsgs,
+            vec![
+                EventMessage {
+                    emsg: absolute,
+                    sequence_number: Some(1),
+                    presentation_time: Some(500),
+                },
+                EventMessage {
+                    emsg: relative,
+                    sequence_number: Some(2),
+                    presentation_time: Some(90000 + 4500),
+                },
+            ]
+        );
+
+        // fragments read on their own are recorded in file order, and what
+        // the fast open read in front of the first moof stays with it
+        let mut reader = Cursor::new(segmented);
+        let mut mp4_file = Mp4File::new(&mut reader);
+        assert!(mp4_file.read_header_fast().await.unwrap());
+        assert!(mp4_file.fragments.is_empty());
+        assert_eq!(mp4_file.emsgs[0].sequence_number, None);
+
+        mp4_file.read_fragment(moof_offsets[1]).await.unwrap();
+        assert_eq!(mp4_file.fragments.len(), 1);
+        assert_eq!(mp4_file.fragments[0].offset, moof_offsets[1]);
+        assert_eq!(mp4_file.fragments[0].sequence_number, 2);
+        assert_eq!(mp4_file.fragments[0].styp, None);
+        assert_eq!(mp4_file.emsgs[0].sequence_number, None);
+
+        mp4_file.read_fragment(moof_offsets[0]).await.unwrap();
+        assert_eq!(mp4_file.fragments.len(), 2);
+        assert_eq!(mp4_file.fragments[0].offset, moof_offsets[0]);
+        assert_eq!(mp4_file.fragments[0].sequence_number, 1);
+        assert_eq!(mp4_file.emsgs[0].sequence_number, Some(1));
+        assert_eq!(mp4_file.emsgs[0].presentation_time, Some(500));
+    }
+}
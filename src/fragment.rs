@@ -0,0 +1,638 @@
+use bytes::BytesMut;
+use std::convert::TryFrom;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::error::BoxError;
+use crate::track::Mp4TrackWriter;
+use crate::writer::{write_box, Mp4Config};
+use crate::{
+    BoxHeader, BoxType, FtypBox, MfhdBox, MoofBox, MoovBox, Mp4Sample, MvexBox, SampleFlags,
+    SidxBox, SidxReference, TfdtBox, TfhdBox, TrackConfig, TrackType, TrafBox, TrexBox, TrunBox,
+};
+use crate::{Mp4Box, Result, WriteBox, HEADER_SIZE};
+
+/// Where [`Mp4FragmentWriter`] starts a new fragment.
+///
+/// Fragments are always cut right before a sync sample of the reference
+/// track (the first video track, or the first track if there is no video).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FragmentBoundary {
+    /// Every sync sample of the reference track starts a new fragment.
+    Keyframe,
+
+    /// A new fragment starts on the first sync sample after the current one
+    /// reaches the given duration, in movie timescale.
+    Duration(u64),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FragmentSample {
+    duration: u32,
+    size: u32,
+    flags: u32,
+    cts: i32,
+}
+
+struct FragmentTrack {
+    track_id: u32,
+    timescale: u32,
+    base_media_decode_time: u64,
+    duration: u64,
+    samples: Vec<FragmentSample>,
+    data: BytesMut,
+}
+
+impl FragmentTrack {
+    /// Earliest presentation time of the pending samples.
+    fn earliest_presentation_time(&self) -> u64 {
+        let mut decode_time = self.base_media_decode_time as i64;
+        let mut earliest = i64::MAX;
+        for sample in self.samples.iter() {
+            earliest = earliest.min(decode_time + sample.cts as i64);
+            decode_time += sample.duration as i64;
+        }
+        earliest.max(0) as u64
+    }
+
+    fn traf(&self, data_offset: i32) -> TrafBox {
+        let mut tfhd = TfhdBox {
+            flags: TfhdBox::FLAG_DEFAULT_BASE_IS_MOOF,
+            track_id: self.track_id,
+            ..Default::default()
+        };
+
+        let mut trun = TrunBox {
+            flags: TrunBox::FLAG_DATA_OFFSET,
+            sample_count: self.samples.len() as u32,
+            data_offset: Some(data_offset),
+            ..Default::default()
+        };
+
+        let first = self.samples[0];
+        let rest = &self.samples[1..];
+
+        if rest.iter().all(|x| x.duration == first.duration) {
+            tfhd.flags |= TfhdBox::FLAG_DEFAULT_SAMPLE_DURATION;
+            tfhd.default_sample_duration = Some(first.duration);
+        } else {
+            trun.flags |= TrunBox::FLAG_SAMPLE_DURATION;
+            trun.sample_durations = self.samples.iter().map(|x| x.duration).collect();
+        }
+
+        if rest.iter().all(|x| x.size == first.size) {
+            tfhd.flags |= TfhdBox::FLAG_DEFAULT_SAMPLE_SIZE;
+            tfhd.default_sample_size = Some(first.size);
+        } else {
+            trun.flags |= TrunBox::FLAG_SAMPLE_SIZE;
+            trun.sample_sizes = self.samples.iter().map(|x| x.size).collect();
+        }
+
+        if rest.iter().all(|x| x.flags == first.flags) {
+            tfhd.flags |= TfhdBox::FLAG_DEFAULT_SAMPLE_FLAGS;
+            tfhd.default_sample_flags = Some(first.flags);
+        } else if rest.iter().all(|x| x.flags == rest[0].flags) {
+            tfhd.flags |= TfhdBox::FLAG_DEFAULT_SAMPLE_FLAGS;
+            tfhd.default_sample_flags = Some(rest[0].flags);
+            trun.flags |= TrunBox::FLAG_FIRST_SAMPLE_FLAGS;
+            trun.first_sample_flags = Some(first.flags);
+        } else {
+            trun.flags |= TrunBox::FLAG_SAMPLE_FLAGS;
+            trun.sample_flags = self.samples.iter().map(|x| x.flags).collect();
+        }
+
+        if self.samples.iter().any(|x| x.cts != 0) {
+            trun.flags |= TrunBox::FLAG_SAMPLE_CTS;
+            trun.sample_cts = self.samples.iter().map(|x| x.cts as u32).collect();
+
+            if self.samples.iter().any(|x| x.cts < 0) {
+                trun.version = 1;
+            }
+        }
+
+        TrafBox {
+            tfhd,
+            tfdt: Some(TfdtBox {
+                version: 1,
+                flags: 0,
+                base_media_decode_time: self.base_media_decode_time,
+            }),
+            trun: Some(trun),
+            subs: None,
+            senc: None,
+            saiz: None,
+            saio: None,
+        }
+    }
+}
+
+/// Fragments held back until the `sidx` that indexes them is written.
+#[derive(Default)]
+struct SegmentIndex {
+    earliest_presentation_time: Option<u64>,
+    references: Vec<SidxReference>,
+    data: Vec<u8>,
+}
+
+/// Fragmented MP4 (fMP4/CMAF) muxer.
+///
+/// The init segment (`ftyp` + `moov` with empty sample tables and an `mvex`)
+/// is written before the first sample; media then follows as `moof` + `mdat`
+/// pairs, one per fragment. Unlike [`crate::Mp4Writer`] the output is never
+/// seeked, so it can be streamed as it is produced.
+pub struct Mp4FragmentWriter<W> {
+    writer: W,
+    config: Mp4Config,
+    boundary: FragmentBoundary,
+    track_configs: Vec<TrackConfig>,
+    tracks: Vec<FragmentTrack>,
+    reference_track: usize,
+    sequence_number: u32,
+    init_written: bool,
+    segment_index: Option<SegmentIndex>,
+}
+
+impl<W> Mp4FragmentWriter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    pub fn new(writer: W, config: &Mp4Config, boundary: FragmentBoundary) -> Self {
+        Self {
+            writer,
+            config: config.clone(),
+            boundary,
+            track_configs: Vec::new(),
+            tracks: Vec::new(),
+            reference_track: 0,
+            sequence_number: 1,
+            init_written: false,
+            segment_index: None,
+        }
+    }
+
+    /// Writes a `sidx` indexing every fragment of the reference track after
+    /// the init segment, as in the DASH on-demand profile. The output then
+    /// can't be streamed: the fragments are held in memory until
+    /// [`Mp4FragmentWriter::write_end`].
+    pub fn set_segment_index(&mut self, enabled: bool) -> Result<()> {
+        if self.sequence_number > 1 {
+            return Err(BoxError::InvalidData(
+                "the segment index can't be changed after the first fragment",
+            ));
+        }
+
+        self.segment_index = enabled.then(SegmentIndex::default);
+        Ok(())
+    }
+
+    /// Adds a track and returns its track id.
+    ///
+    /// Tracks can only be added before the init segment is written.
+    pub fn add_track(&mut self, config: &TrackConfig) -> Result<u32> {
+        if self.init_written {
+            return Err(BoxError::InvalidData(
+                "tracks can't be added after the init segment",
+            ));
+        }
+
+        let track_id = self.tracks.len() as u32 + 1;
+
+        if config.track_type == TrackType::Video
+            && self
+                .track_configs
+                .get(self.reference_track)
+                .map(|x| x.track_type != TrackType::Video)
+                .unwrap_or(true)
+        {
+            self.reference_track = self.tracks.len();
+        }
+
+        self.track_configs.push(config.clone());
+        self.tracks.push(FragmentTrack {
+            track_id,
+            timescale: config.timescale,
+            base_media_decode_time: 0,
+            duration: 0,
+            samples: Vec::new(),
+            data: BytesMut::new(),
+        });
+
+        Ok(track_id)
+    }
+
+    /// Writes `ftyp` + `moov`. Called implicitly by the first
+    /// [`Mp4FragmentWriter::write_sample`] if not done explicitly.
+    pub async fn write_init_segment(&mut self) -> Result<()> {
+        if self.init_written {
+            return Ok(());
+        }
+
+        let ftyp = FtypBox {
+            major_brand: self.config.major_brand,
+            minor_version: self.config.minor_version,
+            compatible_brands: self.config.compatible_brands.clone(),
+        };
+        write_box(&mut self.writer, &ftyp).await?;
+
+        let mut moov = MoovBox::default();
+        moov.mvhd.timescale = self.config.timescale;
+        moov.mvhd.next_track_id = self.tracks.len() as u32 + 1;
+
+        let mut mvex = MvexBox::default();
+        for (track, config) in self.tracks.iter().zip(self.track_configs.iter()) {
+            moov.traks
+                .push(Mp4TrackWriter::new(track.track_id, config).write_end(self.config.timescale));
+
+            mvex.trexs.push(TrexBox {
+                track_id: track.track_id,
+                default_sample_description_index: 1,
+                ..Default::default()
+            });
+        }
+        moov.mvex = Some(mvex);
+
+        write_box(&mut self.writer, &moov).await?;
+        self.init_written = true;
+
+        Ok(())
+    }
+
+    pub async fn write_sample(&mut self, track_id: u32, sample: &Mp4Sample) -> Result<()> {
+        if track_id == 0 || track_id as usize > self.tracks.len() {
+            return Err(BoxError::TrakNotFound(track_id));
+        }
+
+        self.write_init_segment().await?;
+
+        let idx = track_id as usize - 1;
+        if idx == self.reference_track && sample.is_sync && self.is_fragment_full() {
+            self.flush_fragment().await?;
+        }
+
+        let track = &mut self.tracks[idx];
+        if track.samples.is_empty() {
+            track.base_media_decode_time = sample.start_time;
+            track.duration = 0;
+        }
+
+        track.samples.push(FragmentSample {
+            duration: sample.duration,
+            size: sample.bytes.len() as u32,
+            flags: if sample.is_sync {
+                SampleFlags::SYNC.into()
+            } else {
+                SampleFlags::NON_SYNC.into()
+            },
+            cts: sample.rendering_offset,
+        });
+        track.duration += sample.duration as u64;
+        track.data.extend_from_slice(&sample.bytes);
+
+        Ok(())
+    }
+
+    fn is_fragment_full(&self) -> bool {
+        let track = &self.tracks[self.reference_track];
+        if track.samples.is_empty() {
+            return false;
+        }
+
+        match self.boundary {
+            FragmentBoundary::Keyframe => true,
+            FragmentBoundary::Duration(duration) => {
+                track.duration * self.config.timescale as u64 / track.timescale.max(1) as u64
+                    >= duration
+            }
+        }
+    }
+
+    /// Writes the pending samples as a `moof` + `mdat` pair.
+    pub async fn flush_fragment(&mut self) -> Result<()> {
+        if self.tracks.iter().all(|x| x.samples.is_empty()) {
+            return Ok(());
+        }
+
+        self.write_init_segment().await?;
+
+        let mut moof = MoofBox {
+            mfhd: MfhdBox {
+                sequence_number: self.sequence_number,
+                ..Default::default()
+            },
+            trafs: Vec::new(),
+            psshs: Vec::new(),
+        };
+
+        // `data_offset` has a fixed size, so the `moof` size can be taken
+        // before the real offsets are known.
+        for track in self.tracks.iter().filter(|x| !x.samples.is_empty()) {
+            moof.trafs.push(track.traf(0));
+        }
+
+        let mut data_offset = moof.box_size() + HEADER_SIZE;
+        moof.trafs.clear();
+
+        let mut mdat_size = HEADER_SIZE;
+        for track in self.tracks.iter().filter(|x| !x.samples.is_empty()) {
+            moof.trafs.push(track.traf(data_offset as i32));
+            data_offset += track.data.len() as u64;
+            mdat_size += track.data.len() as u64;
+        }
+
+        let mut buf = Vec::with_capacity(data_offset as usize);
+        moof.write_box(&mut buf)?;
+        BoxHeader::new(BoxType::MdatBox, mdat_size).write(&mut buf)?;
+
+        if let Some(ref mut index) = self.segment_index {
+            let track = &self.tracks[self.reference_track];
+            if !track.samples.is_empty() {
+                index
+                    .earliest_presentation_time
+                    .get_or_insert(track.earliest_presentation_time());
+            }
+
+            let starts_with_sap = track
+                .samples
+                .first()
+                .map(|x| !SampleFlags::from(x.flags).is_non_sync)
+                .unwrap_or(false);
+            // `referenced_size` has 31 bits
+            let referenced_size = u32::try_from(data_offset)
+                .ok()
+                .filter(|&x| x <= 0x7FFF_FFFF)
+                .ok_or(BoxError::InvalidData("fragment is too large for sidx"))?;
+            let subsegment_duration = u32::try_from(track.duration)
+                .map_err(|_| BoxError::InvalidData("fragment is too long for sidx"))?;
+
+            index.references.push(SidxReference {
+                reference_type: false,
+                referenced_size,
+                subsegment_duration,
+                starts_with_sap,
+                sap_type: if starts_with_sap { 1 } else { 0 },
+                sap_delta_time: 0,
+            });
+        }
+
+        for track in self.tracks.iter_mut() {
+            if track.samples.is_empty() {
+                continue;
+            }
+
+            buf.extend_from_slice(&track.data);
+
+            track.base_media_decode_time += track.duration;
+            track.duration = 0;
+            track.samples.clear();
+            track.data.clear();
+        }
+
+        match self.segment_index {
+            Some(ref mut index) => index.data.extend_from_slice(&buf),
+            None => self.writer.write_all(&buf).await?,
+        }
+
+        self.sequence_number += 1;
+
+        Ok(())
+    }
+
+    pub async fn write_end(&mut self) -> Result<()> {
+        self.write_init_segment().await?;
+        self.flush_fragment().await?;
+
+        if let Some(index) = self.segment_index.take() {
+            let track = &self.tracks[self.reference_track];
+            let sidx = SidxBox {
+                version: 1,
+                flags: 0,
+                reference_id: track.track_id,
+                timescale: track.timescale,
+                earliest_presentation_time: index.earliest_presentation_time.unwrap_or(0),
+                first_offset: 0,
+                references: index.references,
+            };
+            write_box(&mut self.writer, &sidx).await?;
+            self.writer.write_all(&index.data).await?;
+        }
+
+        self.writer.flush().await?;
+
+        Ok(())
+    }
+
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::test_util::{audio_sample, avc_config, config, video_sample};
+    use crate::{AacConfig, Av1Config, MediaConfig, MediaType, Mp4File, SampleEntry};
+
+    #[tokio::test]
+    async fn test_write_read_fragmented() {
+        let mut writer = Mp4FragmentWriter::new(
+            Cursor::new(Vec::new()),
+            &config(),
+            FragmentBoundary::Duration(2000),
+        );
+
+        let audio = writer
+            .add_track(&TrackConfig::from(MediaConfig::AacConfig(
+                AacConfig::default(),
+            )))
+            .unwrap();
+
+        let video = writer.add_track(&avc_config()).unwrap();
+
+        for idx in 0..100 {
+            writer
+                .write_sample(video, &video_sample(idx))
+                .await
+                .unwrap();
+            writer
+                .write_sample(audio, &audio_sample(idx))
+                .await
+                .unwrap();
+        }
+        writer.write_end().await.unwrap();
+
+        let mut reader = Cursor::new(writer.into_writer().into_inner());
+        let mut mp4_file = Mp4File::new(&mut reader);
+        assert!(mp4_file.read_header().await.unwrap());
+        assert_eq!(mp4_file.tracks.len(), 2);
+
+        let track = mp4_file.tracks.get(&video).unwrap();
+        assert_eq!(track.track_type(), TrackType::Video);
+        assert_eq!(track.samples.len(), 100);
+
+        // 2s fragments cut on every other keyframe
+        let fragments = track
+            .samples
+            .iter()
+            .map(|x| x.chunk_id)
+            .collect::<std::collections::BTreeSet<_>>();
+        assert_eq!(fragments.len(), 2);
+
+        let track = mp4_file.tracks.get(&audio).unwrap();
+        assert_eq!(track.track_type(), TrackType::Audio);
+        assert_eq!(track.samples.len(), 100);
+
+        for idx in 0..100u32 {
+            let expected = video_sample(idx);
+            let samp = mp4_file.tracks.get(&video).unwrap().samples[idx as usize].clone();
+            assert_eq!(samp.start_time, expected.start_time);
+            assert_eq!(samp.duration, expected.duration);
+            assert_eq!(samp.rendering_offset, expected.rendering_offset);
+            assert_eq!(samp.is_sync, expected.is_sync);
+
+            let data = mp4_file
+                .read_sample_data(video, idx as usize)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(data, expected.bytes);
+
+            let expected = audio_sample(idx);
+            let samp = mp4_file.tracks.get(&audio).unwrap().samples[idx as usize].clone();
+            assert_eq!(samp.start_time, expected.start_time);
+
+            let data = mp4_file
+                .read_sample_data(audio, idx as usize)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(data, expected.bytes);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_read_fragmented_av1() {
+        let mut writer = Mp4FragmentWriter::new(
+            Cursor::new(Vec::new()),
+            &config(),
+            FragmentBoundary::Keyframe,
+        );
+
+        let av1 = Av1Config {
+            width: 640,
+            height: 360,
+            seq_level_idx_0: 4,
+            bit_depth: 8,
+            chroma_subsampling_x: true,
+            chroma_subsampling_y: true,
+            config_obus: vec![
+                0x0a, 0x0b, 0x00, 0x00, 0x00, 0x24, 0xc4, 0xff, 0xdf, 0x00, 0x68,
+            ],
+            ..Default::default()
+        };
+        let video = writer
+            .add_track(&TrackConfig::from(MediaConfig::Av1Config(av1.clone())))
+            .unwrap();
+
+        for idx in 0..50 {
+            writer
+                .write_sample(video, &video_sample(idx))
+                .await
+                .unwrap();
+        }
+        writer.write_end().await.unwrap();
+
+        let mut reader = Cursor::new(writer.into_writer().into_inner());
+        let mut mp4_file = Mp4File::new(&mut reader);
+        assert!(mp4_file.read_header().await.unwrap());
+
+        let track = mp4_file.tracks.get(&video).unwrap();
+        assert_eq!(track.track_type(), TrackType::Video);
+        assert_eq!(track.media_type().unwrap(), MediaType::AV1);
+        assert_eq!(track.samples.len(), 50);
+        match track.sample_entry(49) {
+            Some(SampleEntry::Av01(av01)) => {
+                assert_eq!((av01.width, av01.height), (640, 360));
+                assert_eq!(av01.av1c.config_obus, av1.config_obus);
+            }
+            _ => panic!("expected an av01 sample entry"),
+        }
+
+        let data = mp4_file.read_sample_data(video, 30).await.unwrap().unwrap();
+        assert_eq!(data, video_sample(30).bytes);
+    }
+
+    #[tokio::test]
+    async fn test_segment_index_overflow() {
+        let mut writer = Mp4FragmentWriter::new(
+            Cursor::new(Vec::new()),
+            &config(),
+            FragmentBoundary::Keyframe,
+        );
+        writer.set_segment_index(true).unwrap();
+
+        let video = writer.add_track(&avc_config()).unwrap();
+        for idx in 0..2 {
+            let sample = Mp4Sample {
+                duration: u32::MAX,
+                ..video_sample(idx)
+            };
+            writer.write_sample(video, &sample).await.unwrap();
+        }
+
+        assert!(matches!(
+            writer.flush_fragment().await,
+            Err(BoxError::InvalidData("fragment is too long for sidx"))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_segment_index() {
+        let mut writer = Mp4FragmentWriter::new(
+            Cursor::new(Vec::new()),
+            &config(),
+            FragmentBoundary::Keyframe,
+        );
+        writer.set_segment_index(true).unwrap();
+
+        let video = writer.add_track(&avc_config()).unwrap();
+
+        for idx in 0..100 {
+            writer
+                .write_sample(video, &video_sample(idx))
+                .await
+                .unwrap();
+        }
+        writer.write_end().await.unwrap();
+        assert!(writer.set_segment_index(false).is_err());
+
+        let data = writer.into_writer().into_inner();
+        let mut reader = Cursor::new(data.clone());
+        let mut mp4_file = Mp4File::new(&mut reader);
+        assert!(mp4_file.read_header().await.unwrap());
+        assert_eq!(mp4_file.tracks[&video].samples.len(), 100);
+
+        assert_eq!(mp4_file.sidxs.len(), 1);
+        let (anchor, sidx) = &mp4_file.sidxs[0];
+        assert_eq!(sidx.reference_id, video);
+        assert_eq!(sidx.references.len(), 4);
+        assert!(sidx
+            .references
+            .iter()
+            .all(|x| x.starts_with_sap && x.subsegment_duration == 1000));
+        assert_eq!(&data[*anchor as usize + 4..*anchor as usize + 8], b"moof");
+
+        // one second per fragment, the last one ends the file
+        let range = mp4_file.segment_byte_range(video, 2500).unwrap();
+        assert_eq!(
+            &data[range.start as usize + 4..range.start as usize + 8],
+            b"moof"
+        );
+        assert_eq!(mp4_file.segment_byte_range(video, 2000), Some(range));
+        assert_eq!(
+            mp4_file.segment_byte_range(video, 3999).unwrap().end,
+            data.len() as u64
+        );
+        assert_eq!(mp4_file.segment_byte_range(video, 4000), None);
+    }
+}
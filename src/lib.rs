@@ -1,3 +1,6 @@
+#[macro_use]
+mod trace;
+
 pub mod error;
 use error::BoxError;
 pub use error::Error;
@@ -13,9 +16,26 @@ pub use mp4box::*;
 mod file;
 
 mod track;
-pub use track::Mp4Track;
+pub use track::{EditSegment, Mp4SampleOffset, Mp4Track, SeekMode, TrackConfig};
 
 pub use file::*;
+
+mod writer;
+pub use writer::*;
+
+mod fragment;
+pub use fragment::*;
+
+mod caption;
+pub use caption::*;
+
+#[cfg(feature = "cenc")]
+mod cenc;
+#[cfg(feature = "cenc")]
+pub use cenc::*;
+
+#[cfg(test)]
+mod test_util;
 // mod async_reader;
 // pub use async_reader::{AsyncMp4Reader, Mp4Header};
 
@@ -0,0 +1,323 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::fmt;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// AC-3 sample entry, see ETSI TS 102 366 annex F.3.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Ac3Box {
+    pub data_reference_index: u16,
+    pub channelcount: u16,
+    pub samplesize: u16,
+
+    #[serde(with = "value_u32")]
+    pub samplerate: FixedPointU16,
+    pub dac3: Dac3Box,
+}
+
+impl Default for Ac3Box {
+    fn default() -> Self {
+        Self {
+            data_reference_index: 0,
+            channelcount: 2,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(48000),
+            dac3: Dac3Box::default(),
+        }
+    }
+}
+
+impl Ac3Box {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::Ac3Box
+    }
+
+    pub fn get_size(&self) -> u64 {
+        HEADER_SIZE + 8 + 20 + self.dac3.box_size()
+    }
+}
+
+impl Mp4Box for Ac3Box {
+    const TYPE: BoxType = BoxType::Ac3Box;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "channel_count={} sample_size={} sample_rate={}",
+            self.channelcount,
+            self.samplesize,
+            self.samplerate.value()
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for Ac3Box {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        reader.get_u32(); // reserved
+        reader.get_u16(); // reserved
+
+        let data_reference_index = reader.get_u16();
+
+        reader.get_u64(); // reserved
+
+        let channelcount = reader.get_u16();
+        let samplesize = reader.get_u16();
+
+        reader.get_u32(); // pre-defined, reserved
+
+        let samplerate = FixedPointU16::new_raw(reader.get_u32());
+
+        Ok(Ac3Box {
+            data_reference_index,
+            channelcount,
+            samplesize,
+            samplerate,
+            dac3: reader.find_box::<Dac3Box>()?,
+        })
+    }
+
+    fn size_hint() -> usize {
+        28
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Ac3Box {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u64::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.channelcount)?;
+        writer.write_u16::<BigEndian>(self.samplesize)?;
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u32::<BigEndian>(self.samplerate.raw_value())?;
+
+        self.dac3.write_box(writer)?;
+
+        Ok(size)
+    }
+}
+
+/// Audio coding mode (`acmod`) of an AC-3 or E-AC-3 stream, i.e. the
+/// arrangement of its full bandwidth channels as front/rear.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum Ac3ChannelMode {
+    DualMono = 0,
+    Mono = 1,
+    #[default]
+    Stereo = 2,
+    ThreeZero = 3,
+    TwoOne = 4,
+    ThreeOne = 5,
+    TwoTwo = 6,
+    ThreeTwo = 7,
+}
+
+impl Ac3ChannelMode {
+    /// Full bandwidth channels, LFE excluded.
+    pub fn channel_count(&self) -> u16 {
+        match self {
+            Ac3ChannelMode::DualMono => 2,
+            Ac3ChannelMode::Mono => 1,
+            Ac3ChannelMode::Stereo => 2,
+            Ac3ChannelMode::ThreeZero => 3,
+            Ac3ChannelMode::TwoOne => 3,
+            Ac3ChannelMode::ThreeOne => 4,
+            Ac3ChannelMode::TwoTwo => 4,
+            Ac3ChannelMode::ThreeTwo => 5,
+        }
+    }
+}
+
+impl From<u8> for Ac3ChannelMode {
+    fn from(acmod: u8) -> Self {
+        match acmod & 0b111 {
+            0 => Ac3ChannelMode::DualMono,
+            1 => Ac3ChannelMode::Mono,
+            2 => Ac3ChannelMode::Stereo,
+            3 => Ac3ChannelMode::ThreeZero,
+            4 => Ac3ChannelMode::TwoOne,
+            5 => Ac3ChannelMode::ThreeOne,
+            6 => Ac3ChannelMode::TwoTwo,
+            _ => Ac3ChannelMode::ThreeTwo,
+        }
+    }
+}
+
+impl fmt::Display for Ac3ChannelMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Ac3ChannelMode::DualMono => "1+1",
+            Ac3ChannelMode::Mono => "1/0",
+            Ac3ChannelMode::Stereo => "2/0",
+            Ac3ChannelMode::ThreeZero => "3/0",
+            Ac3ChannelMode::TwoOne => "2/1",
+            Ac3ChannelMode::ThreeOne => "3/1",
+            Ac3ChannelMode::TwoTwo => "2/2",
+            Ac3ChannelMode::ThreeTwo => "3/2",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// Sample rate for an AC-3 `fscod`.
+pub(crate) fn ac3_sample_rate(fscod: u8) -> Option<u32> {
+    match fscod {
+        0 => Some(48000),
+        1 => Some(44100),
+        2 => Some(32000),
+        _ => None,
+    }
+}
+
+/// AC3SpecificBox, annex F.4.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct Dac3Box {
+    pub fscod: u8,
+    pub bsid: u8,
+    pub bsmod: u8,
+    pub acmod: Ac3ChannelMode,
+    pub lfeon: bool,
+    pub bit_rate_code: u8,
+}
+
+impl Dac3Box {
+    const BIT_RATES: [u32; 19] = [
+        32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 448, 512, 576, 640,
+    ];
+
+    pub fn sample_rate(&self) -> Option<u32> {
+        ac3_sample_rate(self.fscod)
+    }
+
+    /// Nominal bit rate in kbit/s.
+    pub fn bit_rate(&self) -> Option<u32> {
+        Self::BIT_RATES.get(self.bit_rate_code as usize).copied()
+    }
+
+    pub fn channel_count(&self) -> u16 {
+        self.acmod.channel_count() + self.lfeon as u16
+    }
+
+    /// Layout as "front/rear[.1]", e.g. "3/2.1" for 5.1.
+    pub fn channel_layout(&self) -> String {
+        format!("{}{}", self.acmod, if self.lfeon { ".1" } else { "" })
+    }
+}
+
+impl Mp4Box for Dac3Box {
+    const TYPE: BoxType = BoxType::Dac3Box;
+
+    fn box_size(&self) -> u64 {
+        HEADER_SIZE + 3
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "fscod={} bsid={} bsmod={} channel_layout={} bit_rate_code={}",
+            self.fscod,
+            self.bsid,
+            self.bsmod,
+            self.channel_layout(),
+            self.bit_rate_code
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for Dac3Box {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let bits = reader.get_u24();
+
+        Ok(Dac3Box {
+            fscod: (bits >> 22) as u8 & 0b11,
+            bsid: (bits >> 17) as u8 & 0b11111,
+            bsmod: (bits >> 14) as u8 & 0b111,
+            acmod: Ac3ChannelMode::from((bits >> 11) as u8),
+            lfeon: (bits >> 10) & 1 == 1,
+            bit_rate_code: (bits >> 5) as u8 & 0b11111,
+        })
+    }
+
+    fn size_hint() -> usize {
+        3
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Dac3Box {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        writer.write_u24::<BigEndian>(
+            (self.fscod as u32 & 0b11) << 22
+                | (self.bsid as u32 & 0b11111) << 17
+                | (self.bsmod as u32 & 0b111) << 14
+                | (self.acmod as u32) << 11
+                | (self.lfeon as u32) << 10
+                | (self.bit_rate_code as u32 & 0b11111) << 5,
+        )?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_ac3() {
+        let src_box = Ac3Box {
+            data_reference_index: 1,
+            channelcount: 2,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(48000),
+            dac3: Dac3Box {
+                fscod: 0,
+                bsid: 8,
+                bsmod: 0,
+                acmod: Ac3ChannelMode::ThreeTwo,
+                lfeon: true,
+                bit_rate_code: 15,
+            },
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        // 48 kHz, bsid 8, 3/2.1 at 448 kbit/s as muxed by ffmpeg
+        assert_eq!(&buf[44..47], &[0x10, 0x3d, 0xe0]);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::Ac3Box);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = Ac3Box::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert_eq!(dst_box.dac3.sample_rate(), Some(48000));
+        assert_eq!(dst_box.dac3.bit_rate(), Some(448));
+        assert_eq!(dst_box.dac3.channel_count(), 6);
+        assert_eq!(dst_box.dac3.channel_layout(), "3/2.1");
+    }
+}
@@ -0,0 +1,312 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// Apple Lossless sample entry. The decoder configuration lives in a child
+/// box that shares the `alac` fourcc, see `AlacSpecificBox`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AlacBox {
+    pub data_reference_index: u16,
+    pub channelcount: u16,
+    pub samplesize: u16,
+
+    #[serde(with = "value_u32")]
+    pub samplerate: FixedPointU16,
+    pub alac: Option<AlacSpecificBox>,
+}
+
+impl Default for AlacBox {
+    fn default() -> Self {
+        Self {
+            data_reference_index: 0,
+            channelcount: 2,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(44100),
+            alac: None,
+        }
+    }
+}
+
+impl AlacBox {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::AlacBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + 8 + 20;
+        if let Some(ref alac) = self.alac {
+            size += alac.box_size();
+        }
+        size
+    }
+
+    /// Format of the decoded samples. ALAC decoders output native (little
+    /// endian) signed integer PCM.
+    pub fn config(&self) -> PcmConfig {
+        match self.alac {
+            Some(ref alac) => PcmConfig {
+                sample_rate: alac.sample_rate,
+                channel_count: alac.num_channels as u16,
+                sample_size: alac.bit_depth as u16,
+                endianness: Endianness::Little,
+                float: false,
+            },
+            None => PcmConfig {
+                sample_rate: self.samplerate.value() as u32,
+                channel_count: self.channelcount,
+                sample_size: self.samplesize,
+                endianness: Endianness::Little,
+                float: false,
+            },
+        }
+    }
+}
+
+impl Mp4Box for AlacBox {
+    const TYPE: BoxType = BoxType::AlacBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let config = self.config();
+        let s = format!(
+            "channel_count={} sample_size={} sample_rate={}",
+            config.channel_count, config.sample_size, config.sample_rate
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for AlacBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        reader.get_u32(); // reserved
+        reader.get_u16(); // reserved
+
+        let data_reference_index = reader.get_u16();
+
+        reader.get_u64(); // reserved
+
+        let channelcount = reader.get_u16();
+        let samplesize = reader.get_u16();
+
+        reader.get_u32(); // pre-defined, reserved
+
+        let samplerate = FixedPointU16::new_raw(reader.get_u32());
+
+        Ok(AlacBox {
+            data_reference_index,
+            channelcount,
+            samplesize,
+            samplerate,
+            alac: reader.try_find_box::<AlacSpecificBox>()?,
+        })
+    }
+
+    fn size_hint() -> usize {
+        28
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for AlacBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u64::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.channelcount)?;
+        writer.write_u16::<BigEndian>(self.samplesize)?;
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u32::<BigEndian>(self.samplerate.raw_value())?;
+
+        if let Some(ref alac) = self.alac {
+            alac.write_box(writer)?;
+        }
+
+        Ok(size)
+    }
+}
+
+/// ALAC specific config, the "magic cookie" handed to the decoder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AlacSpecificBox {
+    pub version: u8,
+    pub flags: u32,
+    pub frame_length: u32,
+    pub compatible_version: u8,
+    pub bit_depth: u8,
+    pub pb: u8,
+    pub mb: u8,
+    pub kb: u8,
+    pub num_channels: u8,
+    pub max_run: u16,
+    pub max_frame_bytes: u32,
+    pub avg_bit_rate: u32,
+    pub sample_rate: u32,
+
+    /// Trailing data such as a channel layout info, kept as is.
+    #[serde(skip)]
+    pub extra: Vec<u8>,
+}
+
+impl Default for AlacSpecificBox {
+    fn default() -> Self {
+        Self {
+            version: 0,
+            flags: 0,
+            frame_length: 4096,
+            compatible_version: 0,
+            bit_depth: 16,
+            pb: 40,
+            mb: 10,
+            kb: 14,
+            num_channels: 2,
+            max_run: 255,
+            max_frame_bytes: 0,
+            avg_bit_rate: 0,
+            sample_rate: 44100,
+            extra: Vec::new(),
+        }
+    }
+}
+
+impl AlacSpecificBox {
+    const SIZE: u64 = 24;
+
+    /// Returns the `ALACSpecificConfig` as expected by decoders.
+    pub fn magic_cookie(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::SIZE as usize + self.extra.len());
+        self.write_config(&mut buf).unwrap();
+        buf
+    }
+
+    fn write_config<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_u32::<BigEndian>(self.frame_length)?;
+        writer.write_u8(self.compatible_version)?;
+        writer.write_u8(self.bit_depth)?;
+        writer.write_u8(self.pb)?;
+        writer.write_u8(self.mb)?;
+        writer.write_u8(self.kb)?;
+        writer.write_u8(self.num_channels)?;
+        writer.write_u16::<BigEndian>(self.max_run)?;
+        writer.write_u32::<BigEndian>(self.max_frame_bytes)?;
+        writer.write_u32::<BigEndian>(self.avg_bit_rate)?;
+        writer.write_u32::<BigEndian>(self.sample_rate)?;
+        writer.write_all(&self.extra)?;
+
+        Ok(())
+    }
+}
+
+impl Mp4Box for AlacSpecificBox {
+    const TYPE: BoxType = BoxType::AlacBox;
+
+    fn box_size(&self) -> u64 {
+        HEADER_SIZE + HEADER_EXT_SIZE + Self::SIZE + self.extra.len() as u64
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "frame_length={} bit_depth={} num_channels={} sample_rate={}",
+            self.frame_length, self.bit_depth, self.num_channels, self.sample_rate
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for AlacSpecificBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let (version, flags) = read_box_header_ext(reader);
+
+        Ok(AlacSpecificBox {
+            version,
+            flags,
+            frame_length: reader.get_u32(),
+            compatible_version: reader.get_u8(),
+            bit_depth: reader.get_u8(),
+            pb: reader.get_u8(),
+            mb: reader.get_u8(),
+            kb: reader.get_u8(),
+            num_channels: reader.get_u8(),
+            max_run: reader.get_u16(),
+            max_frame_bytes: reader.get_u32(),
+            avg_bit_rate: reader.get_u32(),
+            sample_rate: reader.get_u32(),
+            extra: reader.collect_remaining(),
+        })
+    }
+
+    fn size_hint() -> usize {
+        HEADER_EXT_SIZE as usize + Self::SIZE as usize
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for AlacSpecificBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+        self.write_config(writer)?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_alac() {
+        let src_box = AlacBox {
+            data_reference_index: 1,
+            channelcount: 2,
+            samplesize: 24,
+            samplerate: FixedPointU16::new(48000),
+            alac: Some(AlacSpecificBox {
+                bit_depth: 24,
+                sample_rate: 48000,
+                max_frame_bytes: 24576,
+                avg_bit_rate: 2304000,
+                ..Default::default()
+            }),
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::AlacBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = AlacBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+
+        let cookie = dst_box.alac.as_ref().unwrap().magic_cookie();
+        assert_eq!(cookie.len(), 24);
+        assert_eq!(&cookie[..4], &4096u32.to_be_bytes());
+
+        let config = dst_box.config();
+        assert_eq!(config.sample_rate, 48000);
+        assert_eq!(config.sample_size, 24);
+        assert_eq!(config.channel_count, 2);
+    }
+}
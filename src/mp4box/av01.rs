@@ -0,0 +1,354 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Av01Box {
+    pub data_reference_index: u16,
+    pub width: u16,
+    pub height: u16,
+
+    #[serde(with = "value_u32")]
+    pub horizresolution: FixedPointU16,
+
+    #[serde(with = "value_u32")]
+    pub vertresolution: FixedPointU16,
+    pub frame_count: u16,
+    pub depth: u16,
+    pub av1c: Av1CBox,
+}
+
+impl Default for Av01Box {
+    fn default() -> Self {
+        Av01Box {
+            data_reference_index: 0,
+            width: 0,
+            height: 0,
+            horizresolution: FixedPointU16::new(0x48),
+            vertresolution: FixedPointU16::new(0x48),
+            frame_count: 1,
+            depth: 0x0018,
+            av1c: Av1CBox::default(),
+        }
+    }
+}
+
+impl Av01Box {
+    pub fn new(config: &Av1Config) -> Self {
+        Av01Box {
+            data_reference_index: 1,
+            width: config.width,
+            height: config.height,
+            horizresolution: FixedPointU16::new(0x48),
+            vertresolution: FixedPointU16::new(0x48),
+            frame_count: 1,
+            depth: 0x0018,
+            av1c: Av1CBox::new(config),
+        }
+    }
+
+    pub fn get_type(&self) -> BoxType {
+        BoxType::Av01Box
+    }
+
+    pub fn get_size(&self) -> u64 {
+        HEADER_SIZE + 8 + 70 + self.av1c.box_size()
+    }
+}
+
+impl Mp4Box for Av01Box {
+    const TYPE: BoxType = BoxType::Av01Box;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "data_reference_index={} width={} height={} frame_count={}",
+            self.data_reference_index, self.width, self.height, self.frame_count
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for Av01Box {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        reader.get_u32(); // reserved
+        reader.get_u16(); // reserved
+
+        let data_reference_index = reader.get_u16();
+
+        reader.get_u32(); // pre-defined, reserved
+        reader.get_u64(); // pre-defined
+        reader.get_u32(); // pre-defined
+
+        let width = reader.get_u16();
+        let height = reader.get_u16();
+
+        let horizresolution = FixedPointU16::new_raw(reader.get_u32());
+        let vertresolution = FixedPointU16::new_raw(reader.get_u32());
+
+        reader.get_u32(); // reserved
+
+        let frame_count = reader.get_u16();
+
+        reader.skip(32); // compressorname
+
+        let depth = reader.get_u16();
+
+        reader.get_i16(); // pre-defined
+
+        Ok(Av01Box {
+            data_reference_index,
+            width,
+            height,
+            horizresolution,
+            vertresolution,
+            frame_count,
+            depth,
+            av1c: reader.find_box::<Av1CBox>()?,
+        })
+    }
+
+    fn size_hint() -> usize {
+        78
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Av01Box {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u32::<BigEndian>(0)?; // pre-defined, reserved
+        writer.write_u64::<BigEndian>(0)?; // pre-defined
+        writer.write_u32::<BigEndian>(0)?; // pre-defined
+        writer.write_u16::<BigEndian>(self.width)?;
+        writer.write_u16::<BigEndian>(self.height)?;
+        writer.write_u32::<BigEndian>(self.horizresolution.raw_value())?;
+        writer.write_u32::<BigEndian>(self.vertresolution.raw_value())?;
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.frame_count)?;
+        // skip compressorname
+        write_zeros(writer, 32)?;
+        writer.write_u16::<BigEndian>(self.depth)?;
+        writer.write_i16::<BigEndian>(-1)?; // pre-defined
+
+        self.av1c.write_box(writer)?;
+
+        Ok(size)
+    }
+}
+
+/// AV1CodecConfigurationRecord, see the AV1 Codec ISO Media File Format
+/// Binding, section 2.3.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct Av1CBox {
+    pub seq_profile: u8,
+    pub seq_level_idx_0: u8,
+    pub seq_tier_0: bool,
+    pub high_bitdepth: bool,
+    pub twelve_bit: bool,
+    pub monochrome: bool,
+    pub chroma_subsampling_x: bool,
+    pub chroma_subsampling_y: bool,
+    pub chroma_sample_position: u8,
+    pub initial_presentation_delay_minus_one: Option<u8>,
+
+    /// Sequence header OBU (and optionally metadata OBUs) in low overhead
+    /// bitstream format.
+    pub config_obus: Vec<u8>,
+}
+
+impl Av1CBox {
+    const MARKER_VERSION: u8 = 0x81;
+
+    pub fn new(config: &Av1Config) -> Self {
+        Self {
+            seq_profile: config.seq_profile,
+            seq_level_idx_0: config.seq_level_idx_0,
+            seq_tier_0: config.seq_tier_0,
+            high_bitdepth: config.bit_depth > 8,
+            twelve_bit: config.bit_depth == 12,
+            monochrome: config.monochrome,
+            chroma_subsampling_x: config.chroma_subsampling_x,
+            chroma_subsampling_y: config.chroma_subsampling_y,
+            chroma_sample_position: config.chroma_sample_position,
+            initial_presentation_delay_minus_one: None,
+            config_obus: config.config_obus.clone(),
+        }
+    }
+
+    pub fn bit_depth(&self) -> u8 {
+        match (self.high_bitdepth, self.twelve_bit) {
+            (true, true) => 12,
+            (true, false) => 10,
+            _ => 8,
+        }
+    }
+}
+
+impl Mp4Box for Av1CBox {
+    const TYPE: BoxType = BoxType::Av1CBox;
+
+    fn box_size(&self) -> u64 {
+        HEADER_SIZE + 4 + self.config_obus.len() as u64
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "seq_profile={} seq_level_idx_0={} bit_depth={}",
+            self.seq_profile,
+            self.seq_level_idx_0,
+            self.bit_depth()
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for Av1CBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        if reader.get_u8() != Self::MARKER_VERSION {
+            return Err(BoxError::InvalidData("av1C: unsupported marker or version"));
+        }
+
+        let byte = reader.get_u8();
+        let seq_profile = byte >> 5;
+        let seq_level_idx_0 = byte & 0b0001_1111;
+
+        let byte = reader.get_u8();
+        let seq_tier_0 = byte & 0b1000_0000 != 0;
+        let high_bitdepth = byte & 0b0100_0000 != 0;
+        let twelve_bit = byte & 0b0010_0000 != 0;
+        let monochrome = byte & 0b0001_0000 != 0;
+        let chroma_subsampling_x = byte & 0b0000_1000 != 0;
+        let chroma_subsampling_y = byte & 0b0000_0100 != 0;
+        let chroma_sample_position = byte & 0b0000_0011;
+
+        let byte = reader.get_u8();
+        let initial_presentation_delay_minus_one = if byte & 0b0001_0000 != 0 {
+            Some(byte & 0b0000_1111)
+        } else {
+            None
+        };
+
+        Ok(Av1CBox {
+            seq_profile,
+            seq_level_idx_0,
+            seq_tier_0,
+            high_bitdepth,
+            twelve_bit,
+            monochrome,
+            chroma_subsampling_x,
+            chroma_subsampling_y,
+            chroma_sample_position,
+            initial_presentation_delay_minus_one,
+            config_obus: reader.collect_remaining(),
+        })
+    }
+
+    fn size_hint() -> usize {
+        4
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Av1CBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        writer.write_u8(Self::MARKER_VERSION)?;
+        writer.write_u8((self.seq_profile << 5) | (self.seq_level_idx_0 & 0b0001_1111))?;
+        writer.write_u8(
+            (self.seq_tier_0 as u8) << 7
+                | (self.high_bitdepth as u8) << 6
+                | (self.twelve_bit as u8) << 5
+                | (self.monochrome as u8) << 4
+                | (self.chroma_subsampling_x as u8) << 3
+                | (self.chroma_subsampling_y as u8) << 2
+                | (self.chroma_sample_position & 0b11),
+        )?;
+        writer.write_u8(match self.initial_presentation_delay_minus_one {
+            Some(delay) => 0b0001_0000 | (delay & 0b0000_1111),
+            None => 0,
+        })?;
+        writer.write_all(&self.config_obus)?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_av01() {
+        let src_box = Av01Box::new(&Av1Config {
+            width: 1920,
+            height: 1080,
+            seq_profile: 0,
+            seq_level_idx_0: 8,
+            bit_depth: 10,
+            chroma_subsampling_x: true,
+            chroma_subsampling_y: true,
+            config_obus: vec![
+                0x0a, 0x0b, 0x00, 0x00, 0x00, 0x42, 0xab, 0xbf, 0xc3, 0x71, 0xab,
+            ],
+            ..Default::default()
+        });
+        assert_eq!(src_box.av1c.bit_depth(), 10);
+
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+        assert_eq!(&buf[94..98], &[0x81, 0x08, 0x4c, 0x00]);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::Av01Box);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = Av01Box::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[tokio::test]
+    async fn test_av1c_presentation_delay() {
+        let src_box = Av1CBox {
+            seq_profile: 1,
+            seq_level_idx_0: 31,
+            seq_tier_0: true,
+            chroma_sample_position: 2,
+            initial_presentation_delay_minus_one: Some(3),
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::Av1CBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = Av1CBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+}
@@ -0,0 +1,93 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// QuickTime CEA-608 closed caption sample entry. Samples hold `cdat` (field
+/// 1) and `cdt2` (field 2) atoms with the raw byte pairs.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct C608Box {
+    pub data_reference_index: u16,
+}
+
+impl C608Box {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::C608Box
+    }
+
+    pub fn get_size(&self) -> u64 {
+        HEADER_SIZE + 8
+    }
+}
+
+impl Mp4Box for C608Box {
+    const TYPE: BoxType = BoxType::C608Box;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        Ok(format!(
+            "data_reference_index={}",
+            self.data_reference_index
+        ))
+    }
+}
+
+impl BlockReader for C608Box {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        reader.get_u32(); // reserved
+        reader.get_u16(); // reserved
+
+        Ok(C608Box {
+            data_reference_index: reader.get_u16(),
+        })
+    }
+
+    fn size_hint() -> usize {
+        8
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for C608Box {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_c608() {
+        let src_box = C608Box {
+            data_reference_index: 1,
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::C608Box);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = C608Box::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+}
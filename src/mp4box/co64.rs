@@ -67,8 +67,6 @@ impl BlockReader for Co64Box {
 
         let entry_size = size_of::<u64>(); // chunk_offset
         let entry_count = reader.get_u32();
-        println!("{}", reader.remaining() / entry_size);
-        println!("entry_count: {}", entry_count);
         if entry_count as usize > reader.remaining() / entry_size {
             return Err(BoxError::InvalidData(
                 "co64 entry_count indicates more entries than could fit in the box",
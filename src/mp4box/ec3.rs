@@ -0,0 +1,401 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::ac3::ac3_sample_rate;
+use crate::mp4box::*;
+
+/// E-AC-3 sample entry, see ETSI TS 102 366 annex F.5.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Ec3Box {
+    pub data_reference_index: u16,
+    pub channelcount: u16,
+    pub samplesize: u16,
+
+    #[serde(with = "value_u32")]
+    pub samplerate: FixedPointU16,
+    pub dec3: Dec3Box,
+}
+
+impl Default for Ec3Box {
+    fn default() -> Self {
+        Self {
+            data_reference_index: 0,
+            channelcount: 2,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(48000),
+            dec3: Dec3Box::default(),
+        }
+    }
+}
+
+impl Ec3Box {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::Ec3Box
+    }
+
+    pub fn get_size(&self) -> u64 {
+        HEADER_SIZE + 8 + 20 + self.dec3.box_size()
+    }
+}
+
+impl Mp4Box for Ec3Box {
+    const TYPE: BoxType = BoxType::Ec3Box;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "channel_count={} sample_size={} sample_rate={}",
+            self.channelcount,
+            self.samplesize,
+            self.samplerate.value()
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for Ec3Box {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        reader.get_u32(); // reserved
+        reader.get_u16(); // reserved
+
+        let data_reference_index = reader.get_u16();
+
+        reader.get_u64(); // reserved
+
+        let channelcount = reader.get_u16();
+        let samplesize = reader.get_u16();
+
+        reader.get_u32(); // pre-defined, reserved
+
+        let samplerate = FixedPointU16::new_raw(reader.get_u32());
+
+        Ok(Ec3Box {
+            data_reference_index,
+            channelcount,
+            samplesize,
+            samplerate,
+            dec3: reader.find_box::<Dec3Box>()?,
+        })
+    }
+
+    fn size_hint() -> usize {
+        28
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Ec3Box {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u64::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.channelcount)?;
+        writer.write_u16::<BigEndian>(self.samplesize)?;
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u32::<BigEndian>(self.samplerate.raw_value())?;
+
+        self.dec3.write_box(writer)?;
+
+        Ok(size)
+    }
+}
+
+/// EC3SpecificBox, annex F.6.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Dec3Box {
+    /// Bit rate in kbit/s.
+    pub data_rate: u16,
+    pub substreams: Vec<Ec3IndependentSubstream>,
+
+    /// Dolby Atmos JOC complexity index (ETSI TS 103 420), if signalled.
+    pub complexity_index_type_a: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct Ec3IndependentSubstream {
+    pub fscod: u8,
+    pub bsid: u8,
+    pub asvc: bool,
+    pub bsmod: u8,
+    pub acmod: Ac3ChannelMode,
+    pub lfeon: bool,
+    pub num_dep_sub: u8,
+
+    /// Channel locations added by the dependent substreams, table F.6.1.
+    pub chan_loc: u16,
+}
+
+impl Default for Dec3Box {
+    fn default() -> Self {
+        Self {
+            data_rate: 0,
+            substreams: vec![Ec3IndependentSubstream::default()],
+            complexity_index_type_a: None,
+        }
+    }
+}
+
+impl Ec3IndependentSubstream {
+    /// Channels of each `chan_loc` bit, most significant first: Lc/Rc,
+    /// Lrs/Rrs, Cs, Ts, Lsd/Rsd, Lw/Rw, Lvh/Rvh, Cvh, LFE2.
+    const CHAN_LOC_CHANNELS: [u16; 9] = [2, 2, 1, 1, 2, 2, 2, 1, 1];
+
+    pub fn sample_rate(&self) -> Option<u32> {
+        ac3_sample_rate(self.fscod)
+    }
+
+    /// Channels of the substream together with its dependent substreams.
+    pub fn channel_count(&self) -> u16 {
+        let dependent = if self.num_dep_sub > 0 {
+            Self::CHAN_LOC_CHANNELS
+                .iter()
+                .enumerate()
+                .filter(|(idx, _)| self.chan_loc & (1 << (8 - idx)) != 0)
+                .map(|(_, channels)| channels)
+                .sum()
+        } else {
+            0
+        };
+
+        self.acmod.channel_count() + self.lfeon as u16 + dependent
+    }
+}
+
+impl Dec3Box {
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.substreams.first().and_then(|x| x.sample_rate())
+    }
+
+    /// Channels of the main program, i.e. the first independent substream.
+    pub fn channel_count(&self) -> u16 {
+        self.substreams
+            .first()
+            .map(|x| x.channel_count())
+            .unwrap_or(0)
+    }
+
+    /// Layout of the first independent substream as "front/rear[.1]", plus
+    /// the extra channels of its dependent substreams, e.g. "3/2.1+2".
+    pub fn channel_layout(&self) -> String {
+        let Some(substream) = self.substreams.first() else {
+            return String::new();
+        };
+
+        let base = substream.acmod.channel_count() + substream.lfeon as u16;
+        let extra = substream.channel_count() - base;
+
+        let mut layout = format!("{}", substream.acmod);
+        if substream.lfeon {
+            layout.push_str(".1");
+        }
+        if extra > 0 {
+            layout.push_str(&format!("+{extra}"));
+        }
+        layout
+    }
+}
+
+impl Mp4Box for Dec3Box {
+    const TYPE: BoxType = BoxType::Dec3Box;
+
+    fn box_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + 2;
+        for substream in self.substreams.iter() {
+            size += if substream.num_dep_sub > 0 { 4 } else { 3 };
+        }
+        if self.complexity_index_type_a.is_some() {
+            size += 2;
+        }
+        size
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "data_rate={} num_ind_sub={} channel_layout={}",
+            self.data_rate,
+            self.substreams.len(),
+            self.channel_layout()
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for Dec3Box {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let bits = reader.get_u16();
+        let data_rate = bits >> 3;
+        let num_ind_sub = (bits & 0b111) as usize + 1;
+
+        let mut substreams = Vec::with_capacity(num_ind_sub);
+        for _ in 0..num_ind_sub {
+            if reader.remaining() < 3 {
+                return Err(BoxError::InvalidData("dec3: substream truncated"));
+            }
+
+            let b0 = reader.get_u8();
+            let b1 = reader.get_u8();
+            let b2 = reader.get_u8();
+
+            let num_dep_sub = (b2 >> 1) & 0b1111;
+            let chan_loc = if num_dep_sub > 0 {
+                ((b2 as u16 & 1) << 8) | reader.get_u8() as u16
+            } else {
+                0
+            };
+
+            substreams.push(Ec3IndependentSubstream {
+                fscod: b0 >> 6,
+                bsid: (b0 >> 1) & 0b11111,
+                asvc: b1 >> 7 == 1,
+                bsmod: (b1 >> 4) & 0b111,
+                acmod: Ac3ChannelMode::from(b1 >> 1),
+                lfeon: b1 & 1 == 1,
+                num_dep_sub,
+                chan_loc,
+            });
+        }
+
+        let complexity_index_type_a = if reader.remaining() >= 2 {
+            let flag = reader.get_u8() & 1 == 1;
+            let index = reader.get_u8();
+            flag.then_some(index)
+        } else {
+            None
+        };
+
+        Ok(Dec3Box {
+            data_rate,
+            substreams,
+            complexity_index_type_a,
+        })
+    }
+
+    fn size_hint() -> usize {
+        5
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Dec3Box {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        if self.substreams.is_empty() || self.substreams.len() > 8 {
+            return Err(BoxError::InvalidData(
+                "dec3: expected 1 to 8 independent substreams",
+            ));
+        }
+
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        writer.write_u16::<BigEndian>(
+            (self.data_rate & 0x1FFF) << 3 | (self.substreams.len() as u16 - 1),
+        )?;
+
+        for substream in self.substreams.iter() {
+            writer.write_u8((substream.fscod & 0b11) << 6 | (substream.bsid & 0b11111) << 1)?;
+            writer.write_u8(
+                (substream.asvc as u8) << 7
+                    | (substream.bsmod & 0b111) << 4
+                    | (substream.acmod as u8) << 1
+                    | substream.lfeon as u8,
+            )?;
+
+            let num_dep_sub = substream.num_dep_sub & 0b1111;
+            if num_dep_sub > 0 {
+                writer.write_u8(num_dep_sub << 1 | (substream.chan_loc >> 8) as u8 & 1)?;
+                writer.write_u8(substream.chan_loc as u8)?;
+            } else {
+                writer.write_u8(num_dep_sub << 1)?;
+            }
+        }
+
+        if let Some(index) = self.complexity_index_type_a {
+            writer.write_u8(1)?;
+            writer.write_u8(index)?;
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_ec3() {
+        // 5.1 core with a dependent substream adding Lrs/Rrs for 7.1
+        let src_box = Ec3Box {
+            data_reference_index: 1,
+            channelcount: 2,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(48000),
+            dec3: Dec3Box {
+                data_rate: 768,
+                substreams: vec![Ec3IndependentSubstream {
+                    fscod: 0,
+                    bsid: 16,
+                    asvc: false,
+                    bsmod: 0,
+                    acmod: Ac3ChannelMode::ThreeTwo,
+                    lfeon: true,
+                    num_dep_sub: 1,
+                    chan_loc: 0b0_1000_0000,
+                }],
+                complexity_index_type_a: Some(16),
+            },
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::Ec3Box);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = Ec3Box::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert_eq!(dst_box.dec3.sample_rate(), Some(48000));
+        assert_eq!(dst_box.dec3.channel_count(), 8);
+        assert_eq!(dst_box.dec3.channel_layout(), "3/2.1+2");
+    }
+
+    #[tokio::test]
+    async fn test_dec3_stereo() {
+        let src_box = Dec3Box {
+            data_rate: 128,
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(
+            buf,
+            [0, 0, 0, 13, b'd', b'e', b'c', b'3', 0x04, 0x00, 0x00, 0x04, 0x00]
+        );
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::Dec3Box);
+
+        let dst_box = Dec3Box::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert_eq!(dst_box.channel_layout(), "2/0");
+    }
+}
@@ -17,11 +17,19 @@ pub struct ElstBox {
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct ElstEntry {
     pub segment_duration: u64,
-    pub media_time: u64,
+    pub media_time: i64,
     pub media_rate: u16,
     pub media_rate_fraction: u16,
 }
 
+impl ElstEntry {
+    /// Empty edits (`media_time == -1`) insert `segment_duration` of nothing.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.media_time == -1
+    }
+}
+
 impl ElstBox {
     pub fn get_type(&self) -> BoxType {
         BoxType::ElstBox
@@ -81,9 +89,9 @@ impl BlockReader for ElstBox {
         let mut entries = Vec::with_capacity(entry_count as usize);
         for _ in 0..entry_count {
             let (segment_duration, media_time) = if version == 1 {
-                (reader.get_u64(), reader.get_u64())
+                (reader.get_u64(), reader.get_i64())
             } else {
-                (reader.get_u32() as u64, reader.get_u32() as u64)
+                (reader.get_u32() as u64, reader.get_i32() as i64)
             };
 
             entries.push(ElstEntry {
@@ -117,10 +125,10 @@ impl<W: Write> WriteBox<&mut W> for ElstBox {
         for entry in self.entries.iter() {
             if self.version == 1 {
                 writer.write_u64::<BigEndian>(entry.segment_duration)?;
-                writer.write_u64::<BigEndian>(entry.media_time)?;
+                writer.write_i64::<BigEndian>(entry.media_time)?;
             } else {
                 writer.write_u32::<BigEndian>(entry.segment_duration as u32)?;
-                writer.write_u32::<BigEndian>(entry.media_time as u32)?;
+                writer.write_i32::<BigEndian>(entry.media_time as i32)?;
             }
             writer.write_u16::<BigEndian>(entry.media_rate)?;
             writer.write_u16::<BigEndian>(entry.media_rate_fraction)?;
@@ -184,4 +192,38 @@ mod tests {
         let dst_box = ElstBox::read_block(&mut reader).unwrap();
         assert_eq!(src_box, dst_box);
     }
+
+    #[tokio::test]
+    async fn test_elst_empty_edit() {
+        let src_box = ElstBox {
+            version: 0,
+            flags: 0,
+            entries: vec![
+                ElstEntry {
+                    segment_duration: 1000,
+                    media_time: -1,
+                    media_rate: 1,
+                    media_rate_fraction: 0,
+                },
+                ElstEntry {
+                    segment_duration: 634634,
+                    media_time: 2112,
+                    media_rate: 1,
+                    media_rate_fraction: 0,
+                },
+            ],
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::ElstBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = ElstBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert!(dst_box.entries[0].is_empty());
+    }
 }
@@ -0,0 +1,227 @@
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::sinf::SinfBox;
+use crate::mp4box::*;
+
+/// Protected sample entry, `encv` for video or `enca` for audio, see
+/// ISO/IEC 23001-7. It is the original entry, whose type is kept in
+/// `sinf.frma`, with a `sinf` box added.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EncvBox {
+    /// `EncvBox` or `EncaBox`.
+    #[serde(skip)]
+    pub kind: BoxType,
+    pub sinf: SinfBox,
+
+    /// The entry as it was before protection.
+    pub entry: Box<SampleEntry>,
+}
+
+impl EncvBox {
+    pub fn new(entry: SampleEntry, sinf: SinfBox) -> Self {
+        let kind = match entry {
+            SampleEntry::Mp4a(_)
+            | SampleEntry::Opus(_)
+            | SampleEntry::Flac(_)
+            | SampleEntry::Ac3(_)
+            | SampleEntry::Ec3(_)
+            | SampleEntry::Pcm(_)
+            | SampleEntry::Twos(_)
+            | SampleEntry::Lpcm(_)
+            | SampleEntry::Alac(_) => BoxType::EncaBox,
+            _ => BoxType::EncvBox,
+        };
+
+        Self {
+            kind,
+            sinf,
+            entry: Box::new(entry),
+        }
+    }
+
+    pub fn get_type(&self) -> BoxType {
+        self.kind
+    }
+
+    pub fn get_size(&self) -> u64 {
+        self.entry.box_size() + self.sinf.box_size()
+    }
+
+    /// Reads the body of an `encv` or `enca` box. The fields in front of the
+    /// child boxes differ between the two.
+    pub(crate) fn read_entry<'a>(kind: BoxType, reader: &mut impl Reader<'a>) -> Result<Self> {
+        let data = reader.collect_remaining();
+
+        let children = if kind == BoxType::EncaBox {
+            // version of the QuickTime sound sample description
+            match data.get(8..10) {
+                Some([0, 1]) => 44,
+                Some([0, 2]) => 64,
+                _ => 28,
+            }
+        } else {
+            78
+        };
+
+        let Some(mut rest) = data.get(children..) else {
+            return Err(BoxError::InvalidData("protected sample entry is too short"));
+        };
+
+        // the original entry is everything but `sinf`, so that an entry kept
+        // as raw data does not carry a second copy of it
+        let mut entry = data[..children].to_vec();
+        let mut sinf = None;
+        loop {
+            let start = data.len() - rest.len();
+            let is_sinf = match rest.get_box()? {
+                Some(mut bx) => match bx.try_read::<SinfBox>()? {
+                    Some(bx) => sinf.replace(bx).is_none(),
+                    None => false,
+                },
+                None => break,
+            };
+
+            if !is_sinf {
+                entry.extend_from_slice(&data[start..data.len() - rest.len()]);
+            }
+        }
+        entry.extend_from_slice(rest);
+
+        let Some(sinf) = sinf else {
+            return Err(BoxError::BoxNotFound(BoxType::SinfBox));
+        };
+
+        let mut bx = BoxReader {
+            kind: BoxType::from(u32::from(sinf.frma.data_format)),
+            inner: entry.as_slice(),
+            m: PhantomData,
+        };
+
+        Ok(EncvBox {
+            kind,
+            entry: Box::new(SampleEntry::read(&mut bx)?),
+            sinf,
+        })
+    }
+}
+
+impl Mp4Box for EncvBox {
+    const TYPE: BoxType = BoxType::EncvBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        self.sinf.summary()
+    }
+}
+
+impl BlockReader for EncvBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        Self::read_entry(Self::TYPE, reader)
+    }
+
+    fn size_hint() -> usize {
+        78
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for EncvBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.kind, size).write(writer)?;
+
+        // the original entry with its header swapped for the one above
+        let mut entry = Vec::with_capacity(self.entry.box_size() as usize);
+        self.entry.write_box(&mut entry)?;
+        writer.write_all(&entry[HEADER_SIZE as usize..])?;
+
+        self.sinf.write_box(writer)?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::sinf::{FrmaBox, SchmBox};
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_enca() {
+        let src_box = EncvBox::new(
+            SampleEntry::Mp4a(Mp4aBox::new(&AacConfig::default())),
+            SinfBox {
+                frma: FrmaBox {
+                    data_format: str::parse("mp4a").unwrap(),
+                },
+                schm: Some(SchmBox {
+                    scheme_type: SchmBox::CENC,
+                    scheme_version: 0x00010000,
+                    ..Default::default()
+                }),
+                schi: None,
+            },
+        );
+        assert_eq!(src_box.kind, BoxType::EncaBox);
+
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::EncaBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = EncvBox::read_entry(header.kind, &mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert_eq!(dst_box.entry.media_type(), Some(MediaType::AAC));
+    }
+
+    #[tokio::test]
+    async fn test_encv_unknown_format() {
+        let mut data = vec![0; 78];
+        BoxHeader::new(BoxType::UnknownBox(0x61626344), HEADER_SIZE + 4)
+            .write(&mut data)
+            .unwrap();
+        data.extend_from_slice(&[1, 2, 3, 4]);
+
+        let src_box = EncvBox::new(
+            SampleEntry::Unknown {
+                kind: BoxType::UnknownBox(0x61626364),
+                data,
+            },
+            SinfBox {
+                frma: FrmaBox {
+                    data_format: str::parse("abcd").unwrap(),
+                },
+                schm: None,
+                schi: None,
+            },
+        );
+        assert_eq!(src_box.kind, BoxType::EncvBox);
+
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::EncvBox);
+
+        let dst_box = EncvBox::read_entry(header.kind, &mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+
+        let mut out = Vec::new();
+        dst_box.write_box(&mut out).unwrap();
+        assert_eq!(buf, out);
+    }
+}
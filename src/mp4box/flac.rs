@@ -0,0 +1,410 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// FLAC sample entry, see "Encapsulation of FLAC in ISO Base Media File
+/// Format", section 3.3.1.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FlacBox {
+    pub data_reference_index: u16,
+    pub channelcount: u16,
+    pub samplesize: u16,
+
+    /// Zero for sample rates that do not fit in 16 bits, see `dfla`.
+    #[serde(with = "value_u32")]
+    pub samplerate: FixedPointU16,
+    pub dfla: DflaBox,
+}
+
+impl Default for FlacBox {
+    fn default() -> Self {
+        Self {
+            data_reference_index: 0,
+            channelcount: 2,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(48000),
+            dfla: DflaBox::default(),
+        }
+    }
+}
+
+impl FlacBox {
+    pub fn new(config: &FlacConfig) -> Self {
+        let stream_info = &config.stream_info;
+        Self {
+            data_reference_index: 1,
+            channelcount: stream_info.channels as u16,
+            samplesize: stream_info.bits_per_sample as u16,
+            samplerate: FixedPointU16::new(if stream_info.sample_rate > 0xFFFF {
+                0
+            } else {
+                stream_info.sample_rate as u16
+            }),
+            dfla: DflaBox::new(config),
+        }
+    }
+
+    pub fn get_type(&self) -> BoxType {
+        BoxType::FlacBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        HEADER_SIZE + 8 + 20 + self.dfla.box_size()
+    }
+}
+
+impl Mp4Box for FlacBox {
+    const TYPE: BoxType = BoxType::FlacBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "channel_count={} sample_size={} sample_rate={}",
+            self.channelcount, self.samplesize, self.dfla.stream_info.sample_rate
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for FlacBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        reader.get_u32(); // reserved
+        reader.get_u16(); // reserved
+
+        let data_reference_index = reader.get_u16();
+
+        reader.get_u64(); // reserved
+
+        let channelcount = reader.get_u16();
+        let samplesize = reader.get_u16();
+
+        reader.get_u32(); // pre-defined, reserved
+
+        let samplerate = FixedPointU16::new_raw(reader.get_u32());
+
+        Ok(FlacBox {
+            data_reference_index,
+            channelcount,
+            samplesize,
+            samplerate,
+            dfla: reader.find_box::<DflaBox>()?,
+        })
+    }
+
+    fn size_hint() -> usize {
+        28
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for FlacBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u64::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.channelcount)?;
+        writer.write_u16::<BigEndian>(self.samplesize)?;
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u32::<BigEndian>(self.samplerate.raw_value())?;
+
+        self.dfla.write_box(writer)?;
+
+        Ok(size)
+    }
+}
+
+/// FLAC specific box, section 3.3.2. Holds the STREAMINFO block followed by
+/// any other metadata blocks of the stream.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct DflaBox {
+    pub version: u8,
+    pub flags: u32,
+    pub stream_info: FlacStreamInfo,
+    pub metadata_blocks: Vec<FlacMetadataBlock>,
+}
+
+/// Decoded STREAMINFO metadata block.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct FlacStreamInfo {
+    pub min_block_size: u16,
+    pub max_block_size: u16,
+    pub min_frame_size: u32,
+    pub max_frame_size: u32,
+    pub sample_rate: u32,
+    pub channels: u8,
+    pub bits_per_sample: u8,
+
+    /// Inter-channel samples in the stream, 0 if unknown.
+    pub total_samples: u64,
+    pub md5: [u8; 16],
+}
+
+/// Metadata block other than STREAMINFO, kept as is.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct FlacMetadataBlock {
+    pub block_type: u8,
+    pub data: Vec<u8>,
+}
+
+impl FlacStreamInfo {
+    pub const BLOCK_TYPE: u8 = 0;
+    const SIZE: usize = 34;
+
+    fn read<'a>(reader: &mut impl Reader<'a>) -> Self {
+        let min_block_size = reader.get_u16();
+        let max_block_size = reader.get_u16();
+        let min_frame_size = reader.get_u24();
+        let max_frame_size = reader.get_u24();
+
+        let packed = reader.get_u64();
+        let sample_rate = (packed >> 44) as u32;
+        let channels = ((packed >> 41) & 0b111) as u8 + 1;
+        let bits_per_sample = ((packed >> 36) & 0b11111) as u8 + 1;
+        let total_samples = packed & 0xF_FFFF_FFFF;
+
+        let mut md5 = [0u8; 16];
+        for byte in md5.iter_mut() {
+            *byte = reader.get_u8();
+        }
+
+        Self {
+            min_block_size,
+            max_block_size,
+            min_frame_size,
+            max_frame_size,
+            sample_rate,
+            channels,
+            bits_per_sample,
+            total_samples,
+            md5,
+        }
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_u16::<BigEndian>(self.min_block_size)?;
+        writer.write_u16::<BigEndian>(self.max_block_size)?;
+        writer.write_u24::<BigEndian>(self.min_frame_size)?;
+        writer.write_u24::<BigEndian>(self.max_frame_size)?;
+        writer.write_u64::<BigEndian>(
+            (self.sample_rate as u64 & 0xF_FFFF) << 44
+                | (self.channels.saturating_sub(1) as u64 & 0b111) << 41
+                | (self.bits_per_sample.saturating_sub(1) as u64 & 0b11111) << 36
+                | (self.total_samples & 0xF_FFFF_FFFF),
+        )?;
+        writer.write_all(&self.md5)?;
+
+        Ok(())
+    }
+}
+
+impl DflaBox {
+    pub fn new(config: &FlacConfig) -> Self {
+        Self {
+            version: 0,
+            flags: 0,
+            stream_info: config.stream_info.clone(),
+            metadata_blocks: config.metadata_blocks.clone(),
+        }
+    }
+}
+
+impl Mp4Box for DflaBox {
+    const TYPE: BoxType = BoxType::DflaBox;
+
+    fn box_size(&self) -> u64 {
+        HEADER_SIZE
+            + HEADER_EXT_SIZE
+            + 4
+            + FlacStreamInfo::SIZE as u64
+            + self
+                .metadata_blocks
+                .iter()
+                .map(|x| 4 + x.data.len() as u64)
+                .sum::<u64>()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "sample_rate={} channels={} bits_per_sample={} total_samples={} metadata_blocks={}",
+            self.stream_info.sample_rate,
+            self.stream_info.channels,
+            self.stream_info.bits_per_sample,
+            self.stream_info.total_samples,
+            self.metadata_blocks.len()
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for DflaBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let (version, flags) = read_box_header_ext(reader);
+
+        let mut stream_info = None;
+        let mut metadata_blocks = Vec::new();
+
+        while reader.remaining() >= 4 {
+            let header = reader.get_u32();
+            let is_last = header >> 31 == 1;
+            let block_type = ((header >> 24) & 0x7F) as u8;
+            let length = (header & 0xFF_FFFF) as usize;
+
+            if reader.remaining() < length {
+                return Err(BoxError::InvalidData("dfLa: metadata block truncated"));
+            }
+
+            if stream_info.is_none() {
+                if block_type != FlacStreamInfo::BLOCK_TYPE || length != FlacStreamInfo::SIZE {
+                    return Err(BoxError::InvalidData("dfLa: STREAMINFO must come first"));
+                }
+
+                stream_info = Some(FlacStreamInfo::read(reader));
+            } else {
+                metadata_blocks.push(FlacMetadataBlock {
+                    block_type,
+                    data: reader.collect(length)?,
+                });
+            }
+
+            if is_last {
+                break;
+            }
+        }
+
+        Ok(DflaBox {
+            version,
+            flags,
+            stream_info: stream_info.ok_or(BoxError::InvalidData("dfLa: missing STREAMINFO"))?,
+            metadata_blocks,
+        })
+    }
+
+    fn size_hint() -> usize {
+        4 + 4 + FlacStreamInfo::SIZE
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for DflaBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+
+        let block_header = |is_last: bool, block_type: u8, length: usize| {
+            (is_last as u32) << 31 | (block_type as u32 & 0x7F) << 24 | length as u32
+        };
+
+        writer.write_u32::<BigEndian>(block_header(
+            self.metadata_blocks.is_empty(),
+            FlacStreamInfo::BLOCK_TYPE,
+            FlacStreamInfo::SIZE,
+        ))?;
+        self.stream_info.write(writer)?;
+
+        for (idx, block) in self.metadata_blocks.iter().enumerate() {
+            if block.data.len() > 0xFF_FFFF {
+                return Err(BoxError::InvalidData("dfLa: metadata block too large"));
+            }
+
+            writer.write_u32::<BigEndian>(block_header(
+                idx == self.metadata_blocks.len() - 1,
+                block.block_type,
+                block.data.len(),
+            ))?;
+            writer.write_all(&block.data)?;
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_flac() {
+        let src_box = FlacBox::new(&FlacConfig {
+            stream_info: FlacStreamInfo {
+                min_block_size: 4096,
+                max_block_size: 4096,
+                min_frame_size: 14,
+                max_frame_size: 12345,
+                sample_rate: 96000,
+                channels: 2,
+                bits_per_sample: 24,
+                total_samples: 0x1_2345_6789,
+                md5: [0xAB; 16],
+            },
+            metadata_blocks: vec![
+                // VORBIS_COMMENT and PADDING
+                FlacMetadataBlock {
+                    block_type: 4,
+                    data: b"\x08\x00\x00\x00ref libFLAC\x00\x00\x00\x00".to_vec(),
+                },
+                FlacMetadataBlock {
+                    block_type: 1,
+                    data: vec![0; 16],
+                },
+            ],
+        });
+        assert_eq!(src_box.samplerate.value(), 0);
+
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::FlacBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = FlacBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[tokio::test]
+    async fn test_dfla_stream_info_only() {
+        let src_box = DflaBox {
+            stream_info: FlacStreamInfo {
+                sample_rate: 44100,
+                channels: 1,
+                bits_per_sample: 16,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        // the STREAMINFO block carries the last-metadata-block flag
+        assert_eq!(&buf[12..16], &[0x80, 0x00, 0x00, 34]);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::DflaBox);
+
+        let dst_box = DflaBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+}
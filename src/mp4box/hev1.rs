@@ -4,8 +4,13 @@ use std::io::Write;
 
 use crate::mp4box::*;
 
+/// HEVC sample entry. The `hev1` and `hvc1` layouts are the same, see
+/// [`HevcBoxType`] for how they differ.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Hev1Box {
+    /// `Hev1Box` or `Hvc1Box`.
+    #[serde(skip)]
+    pub kind: BoxType,
     pub data_reference_index: u16,
     pub width: u16,
     pub height: u16,
@@ -23,6 +28,7 @@ pub struct Hev1Box {
 impl Default for Hev1Box {
     fn default() -> Self {
         Hev1Box {
+            kind: BoxType::Hev1Box,
             data_reference_index: 0,
             width: 0,
             height: 0,
@@ -38,6 +44,10 @@ impl Default for Hev1Box {
 impl Hev1Box {
     pub fn new(config: &HevcConfig) -> Self {
         Hev1Box {
+            kind: match config.box_type {
+                HevcBoxType::Hev1 => BoxType::Hev1Box,
+                HevcBoxType::Hvc1 => BoxType::Hvc1Box,
+            },
             data_reference_index: 1,
             width: config.width,
             height: config.height,
@@ -45,12 +55,12 @@ impl Hev1Box {
             vertresolution: FixedPointU16::new(0x48),
             frame_count: 1,
             depth: 0x0018,
-            hvcc: HvcCBox::new(),
+            hvcc: HvcCBox::new(config),
         }
     }
 
     pub fn get_type(&self) -> BoxType {
-        BoxType::Hev1Box
+        self.kind
     }
 
     pub fn get_size(&self) -> u64 {
@@ -60,6 +70,7 @@ impl Hev1Box {
 
 impl Mp4Box for Hev1Box {
     const TYPE: BoxType = BoxType::Hev1Box;
+    const ALIASES: &'static [BoxType] = &[BoxType::Hvc1Box];
 
     fn box_size(&self) -> u64 {
         self.get_size()
@@ -106,6 +117,7 @@ impl BlockReader for Hev1Box {
         reader.get_i16(); // pre-defined
 
         Ok(Hev1Box {
+            kind: Self::TYPE,
             data_reference_index,
             width,
             height,
@@ -117,6 +129,13 @@ impl BlockReader for Hev1Box {
         })
     }
 
+    fn read_block_as<'a>(kind: BoxType, reader: &mut impl Reader<'a>) -> Result<Self> {
+        Ok(Hev1Box {
+            kind,
+            ..Self::read_block(reader)?
+        })
+    }
+
     fn size_hint() -> usize {
         78
     }
@@ -125,7 +144,7 @@ impl BlockReader for Hev1Box {
 impl<W: Write> WriteBox<&mut W> for Hev1Box {
     fn write_box(&self, writer: &mut W) -> Result<u64> {
         let size = self.box_size();
-        BoxHeader::new(Self::TYPE, size).write(writer)?;
+        BoxHeader::new(self.kind, size).write(writer)?;
 
         writer.write_u32::<BigEndian>(0)?; // reserved
         writer.write_u16::<BigEndian>(0)?; // reserved
@@ -174,11 +193,101 @@ pub struct HvcCBox {
 }
 
 impl HvcCBox {
-    pub fn new() -> Self {
-        Self {
+    pub const NAL_UNIT_VPS: u8 = 32;
+    pub const NAL_UNIT_SPS: u8 = 33;
+    pub const NAL_UNIT_PPS: u8 = 34;
+    pub const NAL_UNIT_PREFIX_SEI: u8 = 39;
+    pub const NAL_UNIT_SUFFIX_SEI: u8 = 40;
+
+    /// Builds the record from the parameter sets of `config`. Profile, level,
+    /// chroma format and bit depth are taken from the first SPS.
+    pub fn new(config: &HevcConfig) -> Self {
+        // hvc1 requires every parameter set to be in the record
+        let completeness = config.box_type == HevcBoxType::Hvc1;
+
+        let mut arrays = Vec::new();
+        for (nal_unit_type, nalus) in [
+            (Self::NAL_UNIT_VPS, &config.video_param_sets),
+            (Self::NAL_UNIT_SPS, &config.seq_param_sets),
+            (Self::NAL_UNIT_PPS, &config.pic_param_sets),
+        ] {
+            if !nalus.is_empty() {
+                arrays.push(HvcCArray::new(completeness, nal_unit_type, nalus));
+            }
+        }
+
+        // SEI arrays are never complete
+        if !config.sei.is_empty() {
+            arrays.push(HvcCArray::new(
+                false,
+                Self::NAL_UNIT_PREFIX_SEI,
+                &config.sei,
+            ));
+        }
+
+        let mut hvcc = Self {
             configuration_version: 1,
+            chroma_format_idc: 1,
+            num_temporal_layers: 1,
+            length_size_minus_one: 3,
+            arrays,
             ..Default::default()
+        };
+
+        if let Some(sps) = config.seq_param_sets.first().and_then(|x| Sps::parse(x)) {
+            hvcc.general_profile_space = sps.profile_space;
+            hvcc.general_tier_flag = sps.tier_flag;
+            hvcc.general_profile_idc = sps.profile_idc;
+            hvcc.general_profile_compatibility_flags = sps.profile_compatibility_flags;
+            hvcc.general_constraint_indicator_flag = sps.constraint_indicator_flags;
+            hvcc.general_level_idc = sps.level_idc;
+            hvcc.chroma_format_idc = sps.chroma_format_idc;
+            hvcc.bit_depth_luma_minus8 = sps.bit_depth_luma_minus8;
+            hvcc.bit_depth_chroma_minus8 = sps.bit_depth_chroma_minus8;
+            hvcc.num_temporal_layers = sps.max_sub_layers_minus1 + 1;
+            hvcc.temporal_id_nested = sps.temporal_id_nesting;
         }
+
+        hvcc
+    }
+
+    pub fn bit_depth_luma(&self) -> u8 {
+        self.bit_depth_luma_minus8 + 8
+    }
+
+    pub fn bit_depth_chroma(&self) -> u8 {
+        self.bit_depth_chroma_minus8 + 8
+    }
+
+    /// Size in bytes of the NAL unit length prefix in samples.
+    pub fn length_size(&self) -> u8 {
+        self.length_size_minus_one + 1
+    }
+
+    /// All NAL units of the given type, in record order.
+    pub fn nal_units(&self, nal_unit_type: u8) -> impl Iterator<Item = &[u8]> {
+        self.arrays
+            .iter()
+            .filter(move |x| x.nal_unit_type == nal_unit_type)
+            .flat_map(|x| x.nalus.iter().map(|x| x.data.as_slice()))
+    }
+
+    pub fn video_param_sets(&self) -> impl Iterator<Item = &[u8]> {
+        self.nal_units(Self::NAL_UNIT_VPS)
+    }
+
+    pub fn seq_param_sets(&self) -> impl Iterator<Item = &[u8]> {
+        self.nal_units(Self::NAL_UNIT_SPS)
+    }
+
+    pub fn pic_param_sets(&self) -> impl Iterator<Item = &[u8]> {
+        self.nal_units(Self::NAL_UNIT_PPS)
+    }
+
+    /// Prefix and suffix SEI NAL units.
+    pub fn sei(&self) -> impl Iterator<Item = &[u8]> {
+        self.nal_units(Self::NAL_UNIT_PREFIX_SEI)
+            .chain(self.nal_units(Self::NAL_UNIT_SUFFIX_SEI))
     }
 }
 
@@ -235,12 +344,152 @@ pub struct HvcCArray {
     pub nalus: Vec<HvcCArrayNalu>,
 }
 
+impl HvcCArray {
+    fn new(completeness: bool, nal_unit_type: u8, nalus: &[Vec<u8>]) -> Self {
+        Self {
+            completeness,
+            nal_unit_type,
+            nalus: nalus
+                .iter()
+                .map(|x| HvcCArrayNalu {
+                    size: x.len() as u16,
+                    data: x.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The leading fields of an H.265 SPS (7.3.2.2) that go into `hvcC`.
+struct Sps {
+    max_sub_layers_minus1: u8,
+    temporal_id_nesting: bool,
+    profile_space: u8,
+    tier_flag: bool,
+    profile_idc: u8,
+    profile_compatibility_flags: u32,
+    constraint_indicator_flags: u64,
+    level_idc: u8,
+    chroma_format_idc: u8,
+    bit_depth_luma_minus8: u8,
+    bit_depth_chroma_minus8: u8,
+}
+
+impl Sps {
+    fn parse(nal: &[u8]) -> Option<Self> {
+        // drop the NAL unit header and the emulation prevention bytes
+        let mut rbsp = Vec::with_capacity(nal.len());
+        for &b in nal.get(2..)? {
+            if b == 3 && rbsp.ends_with(&[0, 0]) {
+                continue;
+            }
+            rbsp.push(b);
+        }
+
+        let mut r = BitReader::new(&rbsp);
+
+        r.read_bits(4)?; // sps_video_parameter_set_id
+        let max_sub_layers_minus1 = r.read_bits(3)? as u8;
+        let temporal_id_nesting = r.read_bits(1)? == 1;
+
+        let profile_space = r.read_bits(2)? as u8;
+        let tier_flag = r.read_bits(1)? == 1;
+        let profile_idc = r.read_bits(5)? as u8;
+        let profile_compatibility_flags = r.read_bits(32)? as u32;
+        let constraint_indicator_flags = r.read_bits(48)?;
+        let level_idc = r.read_bits(8)? as u8;
+
+        let mut sub_layers = Vec::with_capacity(max_sub_layers_minus1 as usize);
+        for _ in 0..max_sub_layers_minus1 {
+            // sub_layer_profile_present_flag, sub_layer_level_present_flag
+            sub_layers.push((r.read_bits(1)? == 1, r.read_bits(1)? == 1));
+        }
+        if max_sub_layers_minus1 > 0 {
+            r.read_bits(2 * (8 - max_sub_layers_minus1 as u32))?; // reserved_zero_2bits
+        }
+        for (profile_present, level_present) in sub_layers {
+            if profile_present {
+                r.read_bits(44)?;
+                r.read_bits(44)?;
+            }
+            if level_present {
+                r.read_bits(8)?;
+            }
+        }
+
+        r.read_ue()?; // sps_seq_parameter_set_id
+        let chroma_format_idc = r.read_ue()? as u8;
+        if chroma_format_idc == 3 {
+            r.read_bits(1)?; // separate_colour_plane_flag
+        }
+
+        r.read_ue()?; // pic_width_in_luma_samples
+        r.read_ue()?; // pic_height_in_luma_samples
+        if r.read_bits(1)? == 1 {
+            // conformance window offsets
+            for _ in 0..4 {
+                r.read_ue()?;
+            }
+        }
+
+        let bit_depth_luma_minus8 = r.read_ue()? as u8;
+        let bit_depth_chroma_minus8 = r.read_ue()? as u8;
+
+        Some(Sps {
+            max_sub_layers_minus1,
+            temporal_id_nesting,
+            profile_space,
+            tier_flag,
+            profile_idc,
+            profile_compatibility_flags,
+            constraint_indicator_flags,
+            level_idc,
+            chroma_format_idc,
+            bit_depth_luma_minus8,
+            bit_depth_chroma_minus8,
+        })
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn read_bits(&mut self, count: u32) -> Option<u64> {
+        let mut value = 0;
+        for _ in 0..count {
+            let byte = self.data.get(self.pos / 8)?;
+            value = (value << 1) | ((byte >> (7 - self.pos % 8)) & 1) as u64;
+            self.pos += 1;
+        }
+        Some(value)
+    }
+
+    /// Unsigned Exp-Golomb code.
+    fn read_ue(&mut self) -> Option<u64> {
+        let mut zeros = 0;
+        while self.read_bits(1)? == 0 {
+            zeros += 1;
+            if zeros > 32 {
+                return None;
+            }
+        }
+        Some((1 << zeros) - 1 + self.read_bits(zeros)?)
+    }
+}
+
 impl BlockReader for HvcCBox {
     fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
         let configuration_version = reader.get_u8();
         let params = reader.get_u8();
-        let general_profile_space = params & 0b11000000 >> 6;
-        let general_tier_flag = (params & 0b00100000 >> 5) > 0;
+        let general_profile_space = (params & 0b11000000) >> 6;
+        let general_tier_flag = (params & 0b00100000) > 0;
         let general_profile_idc = params & 0b00011111;
 
         let general_profile_compatibility_flags = reader.get_u32();
@@ -255,10 +504,10 @@ impl BlockReader for HvcCBox {
         let avg_frame_rate = reader.get_u16();
 
         let params = reader.get_u8();
-        let constant_frame_rate = params & 0b11000000 >> 6;
-        let num_temporal_layers = params & 0b00111000 >> 3;
-        let temporal_id_nested = (params & 0b00000100 >> 2) > 0;
-        let length_size_minus_one = params & 0b000011;
+        let constant_frame_rate = (params & 0b11000000) >> 6;
+        let num_temporal_layers = (params & 0b00111000) >> 3;
+        let temporal_id_nested = (params & 0b00000100) > 0;
+        let length_size_minus_one = params & 0b00000011;
 
         let num_of_arrays = reader.get_u8();
 
@@ -336,11 +585,12 @@ impl<W: Write> WriteBox<&mut W> for HvcCBox {
         writer.write_u48::<BigEndian>(self.general_constraint_indicator_flag)?;
         writer.write_u8(self.general_level_idc)?;
 
-        writer.write_u16::<BigEndian>(self.min_spatial_segmentation_idc & 0x0FFF)?;
-        writer.write_u8(self.parallelism_type & 0b11)?;
-        writer.write_u8(self.chroma_format_idc & 0b11)?;
-        writer.write_u8(self.bit_depth_luma_minus8 & 0b111)?;
-        writer.write_u8(self.bit_depth_chroma_minus8 & 0b111)?;
+        // reserved bits are all set
+        writer.write_u16::<BigEndian>(0xF000 | (self.min_spatial_segmentation_idc & 0x0FFF))?;
+        writer.write_u8(0b11111100 | (self.parallelism_type & 0b11))?;
+        writer.write_u8(0b11111100 | (self.chroma_format_idc & 0b11))?;
+        writer.write_u8(0b11111000 | (self.bit_depth_luma_minus8 & 0b111))?;
+        writer.write_u8(0b11111000 | (self.bit_depth_chroma_minus8 & 0b111))?;
         writer.write_u16::<BigEndian>(self.avg_frame_rate)?;
 
         let constant_frame_rate = (self.constant_frame_rate & 0b11) << 6;
@@ -373,6 +623,7 @@ mod tests {
     #[tokio::test]
     async fn test_hev1() {
         let src_box = Hev1Box {
+            kind: BoxType::Hev1Box,
             data_reference_index: 1,
             width: 320,
             height: 240,
@@ -397,4 +648,98 @@ mod tests {
         let dst_box = Hev1Box::read_block(&mut reader).unwrap();
         assert_eq!(src_box, dst_box);
     }
+
+    #[tokio::test]
+    async fn test_hvc1() {
+        let src_box = Hev1Box::new(&HevcConfig {
+            width: 1280,
+            height: 720,
+            box_type: HevcBoxType::Hvc1,
+            video_param_sets: vec![vec![0x40, 0x01, 0x0c, 0x01]],
+            seq_param_sets: vec![vec![0x42, 0x01, 0x01]],
+            pic_param_sets: vec![vec![0x44, 0x01, 0xc1, 0x72]],
+            sei: vec![],
+        });
+        assert_eq!(src_box.kind, BoxType::Hvc1Box);
+        assert!(src_box.hvcc.arrays.iter().all(|x| x.completeness));
+
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::Hvc1Box);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = Hev1Box::read_block_as(header.kind, &mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+
+        let dst_box: Hev1Box = buf.as_slice().find_box().unwrap();
+        assert_eq!(dst_box.kind, BoxType::Hvc1Box);
+
+        let mut out = Vec::new();
+        dst_box.write_box(&mut out).unwrap();
+        assert_eq!(buf, out);
+    }
+
+    #[tokio::test]
+    async fn test_hvcc_from_config() {
+        let config = HevcConfig {
+            width: 1280,
+            height: 720,
+            box_type: HevcBoxType::Hev1,
+            video_param_sets: vec![vec![
+                0x40, 0x01, 0x0c, 0x01, 0xff, 0xff, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0x90, 0x00,
+                0x00, 0x03, 0x00, 0x00, 0x03, 0x00, 0x5d, 0x95, 0x98, 0x09,
+            ]],
+            seq_param_sets: vec![vec![
+                0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0x90, 0x00, 0x00, 0x03, 0x00,
+                0x00, 0x03, 0x00, 0x5d, 0xa0, 0x02, 0x80, 0x80, 0x2d, 0x16, 0x59, 0x59, 0xa4, 0x93,
+                0x2b, 0xc0, 0x5a, 0x02, 0x00, 0x00, 0x03, 0x00, 0x02, 0x00, 0x00, 0x03, 0x00, 0x3c,
+                0x10,
+            ]],
+            pic_param_sets: vec![vec![0x44, 0x01, 0xc1, 0x72, 0xb4, 0x62, 0x40]],
+            sei: vec![vec![0x4e, 0x01, 0x05, 0x01, 0x00, 0x80]],
+        };
+        let src_box = HvcCBox::new(&config);
+        assert_eq!(src_box.general_profile_space, 0);
+        assert!(!src_box.general_tier_flag);
+        assert_eq!(src_box.general_profile_idc, 1);
+        assert_eq!(src_box.general_profile_compatibility_flags, 0x6000_0000);
+        assert_eq!(src_box.general_constraint_indicator_flag, 0x9000_0000_0000);
+        assert_eq!(src_box.general_level_idc, 93);
+        assert_eq!(src_box.chroma_format_idc, 1);
+        assert_eq!(src_box.bit_depth_luma(), 8);
+        assert_eq!(src_box.bit_depth_chroma(), 8);
+        assert_eq!(src_box.length_size(), 4);
+        assert!(src_box.temporal_id_nested);
+
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::HvcCBox);
+
+        let dst_box = HvcCBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert_eq!(
+            dst_box.video_param_sets().collect::<Vec<_>>(),
+            vec![config.video_param_sets[0].as_slice()]
+        );
+        assert_eq!(
+            dst_box.seq_param_sets().collect::<Vec<_>>(),
+            vec![config.seq_param_sets[0].as_slice()]
+        );
+        assert_eq!(
+            dst_box.pic_param_sets().collect::<Vec<_>>(),
+            vec![config.pic_param_sets[0].as_slice()]
+        );
+        assert_eq!(
+            dst_box.sei().collect::<Vec<_>>(),
+            vec![config.sei[0].as_slice()]
+        );
+    }
 }
@@ -109,7 +109,7 @@ impl<W: Write> WriteBox<&mut W> for IlstBox {
 }
 
 impl<'a> Metadata<'a> for IlstBox {
-    fn title(&self) -> Option<Cow<str>> {
+    fn title(&self) -> Option<Cow<'_, str>> {
         self.items.get(&MetadataKey::Title).map(item_to_str)
     }
 
@@ -121,7 +121,7 @@ impl<'a> Metadata<'a> for IlstBox {
         self.items.get(&MetadataKey::Poster).map(item_to_bytes)
     }
 
-    fn summary(&self) -> Option<Cow<str>> {
+    fn summary(&self) -> Option<Cow<'_, str>> {
         self.items.get(&MetadataKey::Summary).map(item_to_str)
     }
 }
@@ -130,7 +130,7 @@ fn item_to_bytes(item: &DataBox) -> &[u8] {
     &item.data
 }
 
-fn item_to_str(item: &DataBox) -> Cow<str> {
+fn item_to_str(item: &DataBox) -> Cow<'_, str> {
     String::from_utf8_lossy(&item.data)
 }
 
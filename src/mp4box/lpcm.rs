@@ -0,0 +1,216 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// QuickTime `lpcm` sample entry, a version 2 sound description carrying an
+/// AudioStreamBasicDescription (QTFF, "Sound Sample Description (Version 2)").
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LpcmBox {
+    pub data_reference_index: u16,
+
+    /// Stored as a 64-bit float, fractional rates are truncated.
+    pub sample_rate: u32,
+    pub channel_count: u32,
+    pub bits_per_channel: u32,
+
+    /// `FLAG_*` format specific flags.
+    pub format_flags: u32,
+    pub bytes_per_packet: u32,
+    pub frames_per_packet: u32,
+
+    /// Child atoms, e.g. `chan`, kept as is.
+    #[serde(skip)]
+    pub extensions: Vec<u8>,
+}
+
+impl Default for LpcmBox {
+    fn default() -> Self {
+        Self {
+            data_reference_index: 0,
+            sample_rate: 48000,
+            channel_count: 2,
+            bits_per_channel: 16,
+            format_flags: Self::FLAG_SIGNED_INTEGER | Self::FLAG_PACKED,
+            bytes_per_packet: 4,
+            frames_per_packet: 1,
+            extensions: Vec::new(),
+        }
+    }
+}
+
+impl LpcmBox {
+    pub const FLAG_FLOAT: u32 = 1 << 0;
+    pub const FLAG_BIG_ENDIAN: u32 = 1 << 1;
+    pub const FLAG_SIGNED_INTEGER: u32 = 1 << 2;
+    pub const FLAG_PACKED: u32 = 1 << 3;
+
+    const STRUCT_SIZE: u32 = 72;
+
+    pub fn get_type(&self) -> BoxType {
+        BoxType::LpcmBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        HEADER_SIZE + Self::STRUCT_SIZE as u64 - 8 + self.extensions.len() as u64
+    }
+
+    pub fn config(&self) -> PcmConfig {
+        PcmConfig {
+            sample_rate: self.sample_rate,
+            channel_count: self.channel_count as u16,
+            sample_size: self.bits_per_channel as u16,
+            endianness: if self.format_flags & Self::FLAG_BIG_ENDIAN != 0 {
+                Endianness::Big
+            } else {
+                Endianness::Little
+            },
+            float: self.format_flags & Self::FLAG_FLOAT != 0,
+        }
+    }
+}
+
+impl Mp4Box for LpcmBox {
+    const TYPE: BoxType = BoxType::LpcmBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "channel_count={} bits_per_channel={} sample_rate={} format_flags={:#x}",
+            self.channel_count, self.bits_per_channel, self.sample_rate, self.format_flags
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for LpcmBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        reader.get_u32(); // reserved
+        reader.get_u16(); // reserved
+
+        let data_reference_index = reader.get_u16();
+
+        let version = reader.get_u16();
+        if version != 2 {
+            return Err(BoxError::UnsupportedBoxVersion(Self::TYPE, version as u8));
+        }
+
+        reader.get_u16(); // revision level
+        reader.get_u32(); // vendor
+        reader.get_u16(); // always 3
+        reader.get_u16(); // always 16
+        reader.get_i16(); // always -2
+        reader.get_u16(); // always 0
+        reader.get_u32(); // always 65536
+
+        let struct_size = reader.get_u32();
+        if struct_size < Self::STRUCT_SIZE {
+            return Err(BoxError::InvalidData("lpcm: sound description too short"));
+        }
+
+        let sample_rate = f64::from_bits(reader.get_u64()) as u32;
+        let channel_count = reader.get_u32();
+
+        reader.get_u32(); // always 0x7F000000
+
+        let bits_per_channel = reader.get_u32();
+        let format_flags = reader.get_u32();
+        let bytes_per_packet = reader.get_u32();
+        let frames_per_packet = reader.get_u32();
+
+        // skip any fields past the documented structure
+        reader.skip((struct_size - Self::STRUCT_SIZE) as usize);
+
+        Ok(LpcmBox {
+            data_reference_index,
+            sample_rate,
+            channel_count,
+            bits_per_channel,
+            format_flags,
+            bytes_per_packet,
+            frames_per_packet,
+            extensions: reader.collect_remaining(),
+        })
+    }
+
+    fn size_hint() -> usize {
+        Self::STRUCT_SIZE as usize - 8
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for LpcmBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u16::<BigEndian>(2)?; // version
+        writer.write_u16::<BigEndian>(0)?; // revision level
+        writer.write_u32::<BigEndian>(0)?; // vendor
+        writer.write_u16::<BigEndian>(3)?;
+        writer.write_u16::<BigEndian>(16)?;
+        writer.write_i16::<BigEndian>(-2)?;
+        writer.write_u16::<BigEndian>(0)?;
+        writer.write_u32::<BigEndian>(65536)?;
+        writer.write_u32::<BigEndian>(Self::STRUCT_SIZE)?;
+        writer.write_u64::<BigEndian>((self.sample_rate as f64).to_bits())?;
+        writer.write_u32::<BigEndian>(self.channel_count)?;
+        writer.write_u32::<BigEndian>(0x7F000000)?;
+        writer.write_u32::<BigEndian>(self.bits_per_channel)?;
+        writer.write_u32::<BigEndian>(self.format_flags)?;
+        writer.write_u32::<BigEndian>(self.bytes_per_packet)?;
+        writer.write_u32::<BigEndian>(self.frames_per_packet)?;
+
+        writer.write_all(&self.extensions)?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_lpcm() {
+        let src_box = LpcmBox {
+            data_reference_index: 1,
+            sample_rate: 96000,
+            channel_count: 2,
+            bits_per_channel: 32,
+            format_flags: LpcmBox::FLAG_FLOAT | LpcmBox::FLAG_PACKED,
+            bytes_per_packet: 8,
+            frames_per_packet: 1,
+            extensions: Vec::new(),
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+        assert_eq!(buf.len(), 72);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::LpcmBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = LpcmBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+
+        let config = dst_box.config();
+        assert_eq!(config.sample_rate, 96000);
+        assert_eq!(config.endianness, Endianness::Little);
+        assert!(config.float);
+    }
+}
@@ -26,7 +26,6 @@ pub enum MetaBox {
 }
 
 const MDIR: FourCC = FourCC { value: *b"mdir" };
-const MDTA: FourCC = FourCC { value: *b"mdta" };
 
 impl MetaBox {
     pub fn get_type(&self) -> BoxType {
@@ -110,10 +109,7 @@ impl BlockReader for MetaBox {
             },
             _ => MetaBox::Unknown {
                 hdlr: hdlr.clone(),
-                data: boxes
-                    .into_iter()
-                    .map(|(k, v)| (k, v))
-                    .collect::<Vec<(BoxType, Vec<u8>)>>(),
+                data: boxes.into_iter().collect::<Vec<(BoxType, Vec<u8>)>>(),
             },
         })
     }
@@ -0,0 +1,140 @@
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::*;
+use crate::mp4box::{mfro::MfroBox, tfra::TfraBox};
+
+/// Movie fragment random access box, ISO/IEC 14496-12 section 8.8.9.
+/// Usually the last box of a fragmented file.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct MfraBox {
+    #[serde(rename = "tfra")]
+    pub tfras: Vec<TfraBox>,
+
+    /// Its size is kept in sync with the `mfra` when writing.
+    pub mfro: MfroBox,
+}
+
+impl MfraBox {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::MfraBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + self.mfro.box_size();
+        for tfra in self.tfras.iter() {
+            size += tfra.box_size();
+        }
+        size
+    }
+
+    pub fn tfra(&self, track_id: u32) -> Option<&TfraBox> {
+        self.tfras.iter().find(|x| x.track_id == track_id)
+    }
+}
+
+impl Mp4Box for MfraBox {
+    const TYPE: BoxType = BoxType::MfraBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!("tfras={}", self.tfras.len());
+        Ok(s)
+    }
+}
+
+impl BlockReader for MfraBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let mut tfras = Vec::new();
+        let mut mfro = None;
+
+        while let Some(mut bx) = reader.get_box()? {
+            match bx.kind {
+                BoxType::TfraBox => {
+                    tfras.push(bx.read()?);
+                }
+
+                BoxType::MfroBox => {
+                    mfro = Some(bx.read()?);
+                }
+
+                _ => continue,
+            }
+        }
+
+        let Some(mfro) = mfro else {
+            return Err(BoxError::BoxNotFound(BoxType::MfroBox));
+        };
+
+        Ok(MfraBox { tfras, mfro })
+    }
+
+    fn size_hint() -> usize {
+        MfroBox::size_hint()
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for MfraBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        for tfra in self.tfras.iter() {
+            tfra.write_box(writer)?;
+        }
+        MfroBox {
+            size: size as u32,
+            ..self.mfro
+        }
+        .write_box(writer)?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+    use crate::TfraEntry;
+
+    #[tokio::test]
+    async fn test_mfra() {
+        let mut src_box = MfraBox {
+            tfras: vec![TfraBox {
+                track_id: 1,
+                entries: vec![TfraEntry {
+                    time: 0,
+                    moof_offset: 800,
+                    traf_number: 1,
+                    trun_number: 1,
+                    sample_number: 1,
+                }],
+                ..Default::default()
+            }],
+            mfro: MfroBox::default(),
+        };
+        src_box.mfro.size = src_box.box_size() as u32;
+
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+        assert_eq!(buf[buf.len() - 4..], (buf.len() as u32).to_be_bytes());
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::MfraBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = MfraBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert!(dst_box.tfra(1).is_some());
+    }
+}
@@ -13,6 +13,7 @@
 //! ftyp
 //! moov
 //!     mvhd
+//!     pssh
 //!     udta
 //!         meta
 //!             ilst
@@ -26,9 +27,40 @@
 //!                 stbl
 //!                     stsd
 //!                         avc1
+//!                         av01
 //!                         hev1
+//!                         hvc1
 //!                         mp4a
+//!                         Opus
+//!                             dOps
+//!                         fLaC
+//!                             dfLa
+//!                         ac-3
+//!                             dac3
+//!                         ec-3
+//!                             dec3
+//!                         ipcm
+//!                         fpcm
+//!                             srat
+//!                             pcmC
+//!                         lpcm
+//!                         sowt
+//!                         twos
+//!                         alac
+//!                             alac
 //!                         tx3g
+//!                         stpp
+//!                         c608
+//!                         wvtt
+//!                             vttC
+//!                             vlab
+//!                         encv
+//!                         enca
+//!                             sinf
+//!                                 frma
+//!                                 schm
+//!                                 schi
+//!                                     tenc
 //!                     stts
 //!                     stsc
 //!                     stsz
@@ -36,6 +68,9 @@
 //!                     stco
 //!                     co64
 //!                     ctts
+//!                     subs
+//!                     saiz
+//!                     saio
 //!                 dinf
 //!                     dref
 //!                 smhd
@@ -45,14 +80,25 @@
 //!     mvex
 //!         mehd
 //!         trex
+//! styp
+//! sidx
+//! prft
 //! emsg
 //! moof
 //!     mfhd
+//!     pssh
 //!     traf
 //!         tfhd
 //!         tfdt
 //!         trun
+//!         subs
+//!         senc
+//!         saiz
+//!         saio
 //! mdat
+//! mfra
+//!     tfra
+//!     mfro
 //! free
 //!
 
@@ -64,93 +110,152 @@ use tokio::io::{AsyncRead, AsyncReadExt};
 
 use crate::*;
 
+pub(crate) mod ac3;
+pub(crate) mod alac;
+pub(crate) mod av01;
 pub(crate) mod avc1;
+pub(crate) mod c608;
 pub(crate) mod co64;
 pub(crate) mod ctts;
 pub(crate) mod data;
 pub(crate) mod dinf;
+pub(crate) mod ec3;
 pub(crate) mod edts;
 pub(crate) mod elst;
 pub(crate) mod emsg;
+pub(crate) mod encv;
+pub(crate) mod flac;
 pub(crate) mod ftyp;
 pub(crate) mod hdlr;
 pub(crate) mod hev1;
 pub(crate) mod ilst;
+pub(crate) mod lpcm;
 pub(crate) mod mdhd;
 pub(crate) mod mdia;
 pub(crate) mod mehd;
 pub(crate) mod meta;
 pub(crate) mod mfhd;
+pub(crate) mod mfra;
+pub(crate) mod mfro;
 pub(crate) mod minf;
 pub(crate) mod moof;
 pub(crate) mod moov;
 pub(crate) mod mp4a;
 pub(crate) mod mvex;
 pub(crate) mod mvhd;
+pub(crate) mod opus;
+pub(crate) mod pcm;
+pub(crate) mod prft;
+pub(crate) mod pssh;
+pub(crate) mod saio;
+pub(crate) mod saiz;
+pub(crate) mod senc;
+pub(crate) mod sidx;
+pub(crate) mod sinf;
 pub(crate) mod smhd;
 pub(crate) mod stbl;
 pub(crate) mod stco;
+pub(crate) mod stpp;
 pub(crate) mod stsc;
 pub(crate) mod stsd;
 pub(crate) mod stss;
 pub(crate) mod stsz;
 pub(crate) mod stts;
+pub(crate) mod styp;
+pub(crate) mod subs;
+pub(crate) mod tenc;
+pub(crate) mod text;
 pub(crate) mod tfdt;
 pub(crate) mod tfhd;
+pub(crate) mod tfra;
 pub(crate) mod tkhd;
 pub(crate) mod traf;
 pub(crate) mod trak;
 pub(crate) mod trex;
 pub(crate) mod trun;
+pub(crate) mod twos;
 pub(crate) mod tx3g;
 pub(crate) mod udta;
 pub(crate) mod vmhd;
 pub(crate) mod vp09;
 pub(crate) mod vpcc;
+pub(crate) mod wvtt;
 
+pub use ac3::{Ac3Box, Ac3ChannelMode, Dac3Box};
+pub use alac::{AlacBox, AlacSpecificBox};
+pub use av01::{Av01Box, Av1CBox};
 pub use avc1::Avc1Box;
+pub use c608::C608Box;
 pub use co64::Co64Box;
 pub use ctts::CttsBox;
 pub use data::DataBox;
 pub use dinf::DinfBox;
+pub use ec3::{Dec3Box, Ec3Box, Ec3IndependentSubstream};
 pub use edts::EdtsBox;
 pub use elst::ElstBox;
 pub use emsg::EmsgBox;
+pub use encv::EncvBox;
+pub use flac::{DflaBox, FlacBox, FlacMetadataBlock, FlacStreamInfo};
 pub use ftyp::FtypBox;
 pub use hdlr::HdlrBox;
-pub use hev1::Hev1Box;
+pub use hev1::{Hev1Box, HvcCArray, HvcCArrayNalu, HvcCBox};
 pub use ilst::IlstBox;
+pub use lpcm::LpcmBox;
 pub use mdhd::MdhdBox;
 pub use mdia::MdiaBox;
 pub use mehd::MehdBox;
 pub use meta::MetaBox;
 pub use mfhd::MfhdBox;
+pub use mfra::MfraBox;
+pub use mfro::MfroBox;
 pub use minf::MinfBox;
 pub use moof::MoofBox;
 pub use moov::MoovBox;
 pub use mp4a::Mp4aBox;
 pub use mvex::MvexBox;
 pub use mvhd::MvhdBox;
+pub use opus::{DopsBox, OpusBox, OpusChannelMapping};
+pub use pcm::{PcmBox, PcmCBox, SratBox};
+pub use prft::PrftBox;
+pub use pssh::PsshBox;
+pub use saio::SaioBox;
+pub use saiz::SaizBox;
+pub use senc::{SampleEncryption, SencBox, SubsampleEncryption};
+pub use sidx::{SidxBox, SidxReference};
+pub use sinf::{FrmaBox, SchiBox, SchmBox, SinfBox};
 pub use smhd::SmhdBox;
 pub use stbl::StblBox;
 pub use stco::StcoBox;
+pub use stpp::{StppBox, StppSample};
 pub use stsc::StscBox;
-pub use stsd::StsdBox;
+pub use stsd::{SampleEntry, StsdBox};
 pub use stss::StssBox;
 pub use stsz::StszBox;
 pub use stts::SttsBox;
+pub use styp::StypBox;
+pub use subs::{SubsBox, SubsEntry, Subsample};
+pub use tenc::TencBox;
+pub use text::{
+    HyperText, Karaoke, KaraokeEntry, StyleRecord, TextBoxRecord, TextCue, TextRange, TextSample,
+};
 pub use tfdt::TfdtBox;
 pub use tfhd::TfhdBox;
+pub use tfra::{TfraBox, TfraEntry};
 pub use tkhd::TkhdBox;
 pub use traf::TrafBox;
 pub use trak::TrakBox;
 pub use trex::TrexBox;
 pub use trun::TrunBox;
-pub use tx3g::Tx3gBox;
+pub use twos::{SoundPacketInfo, TwosBox};
+pub use tx3g::{RgbaColor, Tx3gBox};
 pub use udta::UdtaBox;
 pub use vmhd::VmhdBox;
 pub use vp09::Vp09Box;
 pub use vpcc::VpccBox;
+pub use wvtt::{
+    CtimBox, IdenBox, PaylBox, SttgBox, VlabBox, VttCBox, VttcBox, VtteBox, WebVttCue,
+    WebVttTimedCue, WvttBox,
+};
 
 pub const HEADER_SIZE: u64 = 8;
 // const HEADER_LARGE_SIZE: u64 = 16;
@@ -236,12 +341,53 @@ boxtype! {
     Avc1Box => 0x61766331,
     AvcCBox => 0x61766343,
     Hev1Box => 0x68657631,
+    Hvc1Box => 0x68766331,
     HvcCBox => 0x68766343,
     Mp4aBox => 0x6d703461,
     EsdsBox => 0x65736473,
+    OpusBox => 0x4f707573,
+    DopsBox => 0x644f7073,
+    FlacBox => 0x664c6143,
+    DflaBox => 0x64664c61,
+    Ac3Box => 0x61632d33,
+    Dac3Box => 0x64616333,
+    Ec3Box => 0x65632d33,
+    Dec3Box => 0x64656333,
+    IpcmBox => 0x6970636d,
+    FpcmBox => 0x6670636d,
+    PcmCBox => 0x70636d43,
+    SratBox => 0x73726174,
+    LpcmBox => 0x6c70636d,
+    SowtBox => 0x736f7774,
+    TwosBox => 0x74776f73,
+    AlacBox => 0x616c6163,
     Tx3gBox => 0x74783367,
+    StylBox => 0x7374796c,
+    HlitBox => 0x686c6974,
+    HclrBox => 0x68636c72,
+    KrokBox => 0x6b726f6b,
+    DlayBox => 0x646c6179,
+    HrefBox => 0x68726566,
+    TboxBox => 0x74626f78,
+    BlnkBox => 0x626c6e6b,
+    WvttBox => 0x77767474,
+    StppBox => 0x73747070,
+    SubsBox => 0x73756273,
+    C608Box => 0x63363038,
+    CdatBox => 0x63646174,
+    Cdt2Box => 0x63647432,
+    VttCBox => 0x76747443,
+    VlabBox => 0x766c6162,
+    VttcBox => 0x76747463,
+    VtteBox => 0x76747465,
+    IdenBox => 0x6964656e,
+    SttgBox => 0x73747467,
+    CtimBox => 0x6374696d,
+    PaylBox => 0x7061796c,
     VpccBox => 0x76706343,
     Vp09Box => 0x76703039,
+    Av01Box => 0x61763031,
+    Av1CBox => 0x61763143,
     DataBox => 0x64617461,
     IlstBox => 0x696c7374,
     NameBox => 0xa96e616d,
@@ -249,12 +395,32 @@ boxtype! {
     CovrBox => 0x636f7672,
     DescBox => 0x64657363,
     WideBox => 0x77696465,
-    WaveBox => 0x77617665
+    WaveBox => 0x77617665,
+    EncvBox => 0x656e6376,
+    EncaBox => 0x656e6361,
+    SinfBox => 0x73696e66,
+    FrmaBox => 0x66726d61,
+    SchmBox => 0x7363686d,
+    SchiBox => 0x73636869,
+    TencBox => 0x74656e63,
+    PsshBox => 0x70737368,
+    SencBox => 0x73656e63,
+    SaizBox => 0x7361697a,
+    SaioBox => 0x7361696f,
+    SidxBox => 0x73696478,
+    MfraBox => 0x6d667261,
+    TfraBox => 0x74667261,
+    MfroBox => 0x6d66726f,
+    StypBox => 0x73747970,
+    PrftBox => 0x70726674
 }
 
 pub trait Mp4Box: Sized {
     const TYPE: BoxType;
 
+    /// Other types sharing the layout, e.g. `sowt` for `twos`.
+    const ALIASES: &'static [BoxType] = &[];
+
     fn box_size(&self) -> u64;
     fn to_json(&self) -> Result<String>;
     fn summary(&self) -> Result<String>;
@@ -269,8 +435,9 @@ pub struct BoxReader<'a, R: Reader<'a>> {
 impl<'a, R: Reader<'a>> BoxReader<'a, R> {
     #[inline]
     pub fn try_read<T: Mp4Box + BlockReader>(&mut self) -> Result<Option<T>> {
-        if T::TYPE == self.kind {
-            Ok(Some(T::read_block(&mut self.inner)?))
+        if T::TYPE == self.kind || T::ALIASES.contains(&self.kind) {
+            let _span = box_span!(self.kind, self.inner.remaining());
+            Ok(Some(T::read_block_as(self.kind, &mut self.inner)?))
         } else {
             Ok(None)
         }
@@ -278,8 +445,9 @@ impl<'a, R: Reader<'a>> BoxReader<'a, R> {
 
     #[inline]
     pub fn read<T: Mp4Box + BlockReader>(&mut self) -> Result<T> {
-        if T::TYPE == self.kind {
-            T::read_block(&mut self.inner)
+        if T::TYPE == self.kind || T::ALIASES.contains(&self.kind) {
+            let _span = box_span!(self.kind, self.inner.remaining());
+            T::read_block_as(self.kind, &mut self.inner)
         } else {
             Err(BoxError::BoxNotFound(T::TYPE))
         }
@@ -422,7 +590,7 @@ pub trait Reader<'a> {
 
     fn find_box<B: Mp4Box + BlockReader>(&mut self) -> Result<B> {
         self.try_find_box()
-            .and_then(|x| x.ok_or_else(|| BoxError::InvalidData("expected box")))
+            .and_then(|x| x.ok_or(BoxError::InvalidData("expected box")))
     }
 
     fn try_find_box2<A: Mp4Box + BlockReader, B: Mp4Box + BlockReader>(
@@ -446,7 +614,7 @@ pub trait Reader<'a> {
                 }
             }
 
-            println!(" 1 unknown box {}", bx.kind);
+            debug!(r#type = %bx.kind, size = bx.inner.remaining(), "unknown box");
         }
 
         Ok((a, b))
@@ -483,7 +651,7 @@ pub trait Reader<'a> {
                 }
             }
 
-            println!(" 2 unknown box {}", bx.kind);
+            debug!(r#type = %bx.kind, size = bx.inner.remaining(), "unknown box");
         }
 
         Ok((a, b, c))
@@ -513,6 +681,7 @@ pub trait Reader<'a> {
         Ok((a, b, c))
     }
 
+    #[allow(clippy::type_complexity)]
     fn try_find_box4<A, B, C, D>(&mut self) -> Result<(Option<A>, Option<B>, Option<C>, Option<D>)>
     where
         A: Mp4Box + BlockReader,
@@ -554,7 +723,7 @@ pub trait Reader<'a> {
                 }
             }
 
-            println!(" 3 unknown box {}", bx.kind);
+            debug!(r#type = %bx.kind, size = bx.inner.remaining(), "unknown box");
         }
 
         Ok((a, b, c, d))
@@ -567,7 +736,7 @@ pub trait Reader<'a> {
                 return Ok(Some(inner));
             }
 
-            println!(" 4 unknown box {}", bx.kind);
+            debug!(r#type = %bx.kind, size = bx.inner.remaining(), "unknown box");
         }
 
         Ok(None)
@@ -714,6 +883,13 @@ impl<'a> Reader<'a> for &'a [u8] {
 
 pub trait BlockReader: Sized {
     fn read_block<'a>(block: &mut impl Reader<'a>) -> Result<Self>;
+
+    /// Reads a box whose header type is `kind`, either `Mp4Box::TYPE` or one
+    /// of `Mp4Box::ALIASES`. Boxes that keep their type override this.
+    fn read_block_as<'a>(_kind: BoxType, block: &mut impl Reader<'a>) -> Result<Self> {
+        Self::read_block(block)
+    }
+
     fn size_hint() -> usize;
 }
 
@@ -763,11 +939,11 @@ impl BoxHeader {
             sz as _
         };
 
-        println!(
-            "{} box {} {}",
-            if sz == 1 { "big" } else { "small" },
-            BoxType::from(typ).as_str(),
-            size
+        trace!(
+            r#type = %BoxType::from(typ),
+            size,
+            large = sz == 1,
+            "box header"
         );
 
         Ok(Some(BoxHeader {
@@ -825,11 +1001,12 @@ impl BoxHeader {
             sz as _
         };
 
-        println!(
-            "{} box {} {}",
-            if sz == 1 { "big" } else { "small" },
-            BoxType::from(typ).as_str(),
-            size
+        trace!(
+            r#type = %BoxType::from(typ),
+            size,
+            offset = *offset - if sz == 1 { 16 } else { 8 },
+            large = sz == 1,
+            "box header"
         );
 
         Ok(Some(BoxHeader {
@@ -2,7 +2,7 @@ use serde::Serialize;
 use std::io::Write;
 
 use crate::mp4box::*;
-use crate::mp4box::{mfhd::MfhdBox, traf::TrafBox};
+use crate::mp4box::{mfhd::MfhdBox, pssh::PsshBox, traf::TrafBox};
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct MoofBox {
@@ -10,6 +10,10 @@ pub struct MoofBox {
 
     #[serde(rename = "traf")]
     pub trafs: Vec<TrafBox>,
+
+    /// Protection system headers for key rotation.
+    #[serde(rename = "pssh")]
+    pub psshs: Vec<PsshBox>,
 }
 
 impl MoofBox {
@@ -22,6 +26,9 @@ impl MoofBox {
         for traf in self.trafs.iter() {
             size += traf.box_size();
         }
+        for pssh in self.psshs.iter() {
+            size += pssh.box_size();
+        }
         size
     }
 }
@@ -47,6 +54,7 @@ impl BlockReader for MoofBox {
     fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
         let mut mfhd = None;
         let mut trafs = Vec::new();
+        let mut psshs = Vec::new();
 
         while let Some(mut bx) = reader.get_box()? {
             match bx.kind {
@@ -58,6 +66,10 @@ impl BlockReader for MoofBox {
                     trafs.push(bx.read()?);
                 }
 
+                BoxType::PsshBox => {
+                    psshs.push(bx.read()?);
+                }
+
                 _ => continue,
             }
         }
@@ -69,6 +81,7 @@ impl BlockReader for MoofBox {
         Ok(MoofBox {
             mfhd: mfhd.unwrap(),
             trafs,
+            psshs,
         })
     }
 
@@ -83,9 +96,12 @@ impl<W: Write> WriteBox<&mut W> for MoofBox {
         BoxHeader::new(Self::TYPE, size).write(writer)?;
 
         self.mfhd.write_box(writer)?;
+        for pssh in self.psshs.iter() {
+            pssh.write_box(writer)?;
+        }
         for traf in self.trafs.iter() {
             traf.write_box(writer)?;
         }
-        Ok(0)
+        Ok(size)
     }
 }
@@ -3,7 +3,7 @@ use std::io::Write;
 
 use crate::meta::MetaBox;
 use crate::mp4box::*;
-use crate::mp4box::{mvex::MvexBox, mvhd::MvhdBox, trak::TrakBox, udta::UdtaBox};
+use crate::mp4box::{mvex::MvexBox, mvhd::MvhdBox, pssh::PsshBox, trak::TrakBox, udta::UdtaBox};
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct MoovBox {
@@ -18,6 +18,9 @@ pub struct MoovBox {
     #[serde(rename = "trak")]
     pub traks: Vec<TrakBox>,
 
+    #[serde(rename = "pssh")]
+    pub psshs: Vec<PsshBox>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub udta: Option<UdtaBox>,
 }
@@ -32,6 +35,12 @@ impl MoovBox {
         for trak in self.traks.iter() {
             size += trak.box_size();
         }
+        for pssh in self.psshs.iter() {
+            size += pssh.box_size();
+        }
+        if let Some(mvex) = &self.mvex {
+            size += mvex.box_size();
+        }
         if let Some(meta) = &self.meta {
             size += meta.box_size();
         }
@@ -66,6 +75,7 @@ impl BlockReader for MoovBox {
         let mut udta = None;
         let mut mvex = None;
         let mut traks = Vec::new();
+        let mut psshs = Vec::new();
 
         while let Some(mut bx) = reader.get_box()? {
             match bx.kind {
@@ -89,6 +99,10 @@ impl BlockReader for MoovBox {
                     udta = Some(bx.read()?);
                 }
 
+                BoxType::PsshBox => {
+                    psshs.push(bx.read()?);
+                }
+
                 _ => continue,
             }
         }
@@ -103,6 +117,7 @@ impl BlockReader for MoovBox {
             udta,
             mvex,
             traks,
+            psshs,
         })
     }
 
@@ -117,16 +132,22 @@ impl<W: Write> WriteBox<&mut W> for MoovBox {
         BoxHeader::new(Self::TYPE, size).write(writer)?;
 
         self.mvhd.write_box(writer)?;
+        for pssh in self.psshs.iter() {
+            pssh.write_box(writer)?;
+        }
         for trak in self.traks.iter() {
             trak.write_box(writer)?;
         }
+        if let Some(mvex) = &self.mvex {
+            mvex.write_box(writer)?;
+        }
         if let Some(meta) = &self.meta {
             meta.write_box(writer)?;
         }
         if let Some(udta) = &self.udta {
             udta.write_box(writer)?;
         }
-        Ok(0)
+        Ok(size)
     }
 }
 
@@ -134,13 +155,22 @@ impl<W: Write> WriteBox<&mut W> for MoovBox {
 mod tests {
     use super::*;
     use crate::mp4box::BoxHeader;
+    use crate::mp4box::TrexBox;
 
     #[tokio::test]
     async fn test_moov() {
         let src_box = MoovBox {
             mvhd: MvhdBox::default(),
-            mvex: None, // XXX mvex is not written currently
+            mvex: Some(MvexBox {
+                mehd: None,
+                trexs: vec![TrexBox::default()],
+            }),
             traks: vec![],
+            psshs: vec![PsshBox {
+                system_id: PsshBox::SYSTEM_ID_WIDEVINE,
+                data: vec![1, 2, 3],
+                ..Default::default()
+            }],
             meta: Some(MetaBox::default()),
             udta: Some(UdtaBox::default()),
         };
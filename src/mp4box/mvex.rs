@@ -7,16 +7,19 @@ use crate::mp4box::{mehd::MehdBox, trex::TrexBox};
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct MvexBox {
     pub mehd: Option<MehdBox>,
-    pub trex: TrexBox,
+    #[serde(rename = "trex")]
+    pub trexs: Vec<TrexBox>,
 }
 
 impl MvexBox {
     pub fn get_type(&self) -> BoxType {
-        BoxType::MdiaBox
+        BoxType::MvexBox
     }
 
     pub fn get_size(&self) -> u64 {
-        HEADER_SIZE + self.mehd.as_ref().map(|x| x.box_size()).unwrap_or(0) + self.trex.box_size()
+        HEADER_SIZE
+            + self.mehd.as_ref().map(|x| x.box_size()).unwrap_or(0)
+            + self.trexs.iter().map(|x| x.box_size()).sum::<u64>()
     }
 }
 
@@ -32,23 +35,35 @@ impl Mp4Box for MvexBox {
     }
 
     fn summary(&self) -> Result<String> {
-        let s = String::new();
+        let s = format!("trexs={}", self.trexs.len());
         Ok(s)
     }
 }
 
 impl BlockReader for MvexBox {
     fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
-        let (mehd, trex) = reader.try_find_box2::<MehdBox, TrexBox>()?;
+        let mut mehd = None;
+        let mut trexs = Vec::new();
 
-        if trex.is_none() {
+        while let Some(mut bx) = reader.get_box()? {
+            match bx.kind {
+                BoxType::MehdBox => {
+                    mehd = Some(bx.read()?);
+                }
+
+                BoxType::TrexBox => {
+                    trexs.push(bx.read()?);
+                }
+
+                _ => continue,
+            }
+        }
+
+        if trexs.is_empty() {
             return Err(BoxError::BoxNotFound(BoxType::TrexBox));
         }
 
-        Ok(MvexBox {
-            mehd,
-            trex: trex.unwrap(),
-        })
+        Ok(MvexBox { mehd, trexs })
     }
 
     fn size_hint() -> usize {
@@ -65,7 +80,9 @@ impl<W: Write> WriteBox<&mut W> for MvexBox {
             mehd.write_box(writer)?;
         }
 
-        self.trex.write_box(writer)?;
+        for trex in self.trexs.iter() {
+            trex.write_box(writer)?;
+        }
 
         Ok(size)
     }
@@ -0,0 +1,335 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// Opus sample entry, see "Encapsulation of Opus in ISO Base Media File
+/// Format", section 4.3.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OpusBox {
+    pub data_reference_index: u16,
+    pub channelcount: u16,
+    pub samplesize: u16,
+
+    #[serde(with = "value_u32")]
+    pub samplerate: FixedPointU16,
+    pub dops: DopsBox,
+}
+
+impl Default for OpusBox {
+    fn default() -> Self {
+        Self {
+            data_reference_index: 0,
+            channelcount: 2,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(48000),
+            dops: DopsBox::default(),
+        }
+    }
+}
+
+impl OpusBox {
+    pub fn new(config: &OpusConfig) -> Self {
+        Self {
+            data_reference_index: 1,
+            channelcount: config.channel_count as u16,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(48000),
+            dops: DopsBox::new(config),
+        }
+    }
+
+    pub fn get_type(&self) -> BoxType {
+        BoxType::OpusBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        HEADER_SIZE + 8 + 20 + self.dops.box_size()
+    }
+}
+
+impl Mp4Box for OpusBox {
+    const TYPE: BoxType = BoxType::OpusBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "channel_count={} sample_size={} sample_rate={}",
+            self.channelcount,
+            self.samplesize,
+            self.samplerate.value()
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for OpusBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        reader.get_u32(); // reserved
+        reader.get_u16(); // reserved
+
+        let data_reference_index = reader.get_u16();
+
+        reader.get_u64(); // reserved
+
+        let channelcount = reader.get_u16();
+        let samplesize = reader.get_u16();
+
+        reader.get_u32(); // pre-defined, reserved
+
+        let samplerate = FixedPointU16::new_raw(reader.get_u32());
+
+        Ok(OpusBox {
+            data_reference_index,
+            channelcount,
+            samplesize,
+            samplerate,
+            dops: reader.find_box::<DopsBox>()?,
+        })
+    }
+
+    fn size_hint() -> usize {
+        28
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for OpusBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u64::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.channelcount)?;
+        writer.write_u16::<BigEndian>(self.samplesize)?;
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u32::<BigEndian>(self.samplerate.raw_value())?;
+
+        self.dops.write_box(writer)?;
+
+        Ok(size)
+    }
+}
+
+/// Opus specific box, section 4.3.2.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DopsBox {
+    pub version: u8,
+    pub output_channel_count: u8,
+
+    /// Samples at 48 kHz to discard from the decoder output at the start.
+    pub pre_skip: u16,
+    pub input_sample_rate: u32,
+
+    /// Q7.8 gain in dB to apply to the decoder output.
+    pub output_gain: i16,
+    pub channel_mapping_family: u8,
+
+    /// Present when `channel_mapping_family` is not 0.
+    pub channel_mapping: Option<OpusChannelMapping>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct OpusChannelMapping {
+    pub stream_count: u8,
+    pub coupled_count: u8,
+
+    /// Decoded stream channel for each output channel.
+    pub channel_mapping: Vec<u8>,
+}
+
+impl Default for DopsBox {
+    fn default() -> Self {
+        Self::new(&OpusConfig::default())
+    }
+}
+
+impl DopsBox {
+    pub fn new(config: &OpusConfig) -> Self {
+        Self {
+            version: 0,
+            output_channel_count: config.channel_count,
+            pre_skip: config.pre_skip,
+            input_sample_rate: config.input_sample_rate,
+            output_gain: config.output_gain,
+            channel_mapping_family: config.channel_mapping_family,
+            channel_mapping: config.channel_mapping.clone(),
+        }
+    }
+}
+
+impl Mp4Box for DopsBox {
+    const TYPE: BoxType = BoxType::DopsBox;
+
+    fn box_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + 11;
+        if let Some(ref mapping) = self.channel_mapping {
+            size += 2 + mapping.channel_mapping.len() as u64;
+        }
+        size
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "output_channel_count={} pre_skip={} input_sample_rate={} channel_mapping_family={}",
+            self.output_channel_count,
+            self.pre_skip,
+            self.input_sample_rate,
+            self.channel_mapping_family
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for DopsBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let version = reader.get_u8();
+        if version != 0 {
+            return Err(BoxError::UnsupportedBoxVersion(BoxType::DopsBox, version));
+        }
+
+        let output_channel_count = reader.get_u8();
+        let pre_skip = reader.get_u16();
+        let input_sample_rate = reader.get_u32();
+        let output_gain = reader.get_i16();
+        let channel_mapping_family = reader.get_u8();
+
+        let channel_mapping = if channel_mapping_family != 0 {
+            if reader.remaining() < 2 + output_channel_count as usize {
+                return Err(BoxError::InvalidData(
+                    "dOps: channel mapping table too short",
+                ));
+            }
+
+            Some(OpusChannelMapping {
+                stream_count: reader.get_u8(),
+                coupled_count: reader.get_u8(),
+                channel_mapping: reader.collect(output_channel_count as _)?,
+            })
+        } else {
+            None
+        };
+
+        Ok(DopsBox {
+            version,
+            output_channel_count,
+            pre_skip,
+            input_sample_rate,
+            output_gain,
+            channel_mapping_family,
+            channel_mapping,
+        })
+    }
+
+    fn size_hint() -> usize {
+        11
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for DopsBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        match (self.channel_mapping_family, &self.channel_mapping) {
+            (0, None) => {}
+            (0, Some(_)) | (_, None) => {
+                return Err(BoxError::InvalidData(
+                    "dOps: channel mapping table must be present iff family is not 0",
+                ));
+            }
+            (_, Some(mapping)) => {
+                if mapping.channel_mapping.len() != self.output_channel_count as usize {
+                    return Err(BoxError::InvalidData(
+                        "dOps: channel mapping size mismatch with output_channel_count",
+                    ));
+                }
+            }
+        }
+
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        writer.write_u8(self.version)?;
+        writer.write_u8(self.output_channel_count)?;
+        writer.write_u16::<BigEndian>(self.pre_skip)?;
+        writer.write_u32::<BigEndian>(self.input_sample_rate)?;
+        writer.write_i16::<BigEndian>(self.output_gain)?;
+        writer.write_u8(self.channel_mapping_family)?;
+
+        if let Some(ref mapping) = self.channel_mapping {
+            writer.write_u8(mapping.stream_count)?;
+            writer.write_u8(mapping.coupled_count)?;
+            writer.write_all(&mapping.channel_mapping)?;
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_opus() {
+        let src_box = OpusBox::new(&OpusConfig {
+            channel_count: 6,
+            pre_skip: 312,
+            input_sample_rate: 44100,
+            output_gain: -256,
+            channel_mapping_family: 1,
+            channel_mapping: Some(OpusChannelMapping {
+                stream_count: 4,
+                coupled_count: 2,
+                channel_mapping: vec![0, 4, 1, 2, 3, 5],
+            }),
+        });
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::OpusBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = OpusBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[tokio::test]
+    async fn test_dops_stereo() {
+        let src_box = DopsBox::default();
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+        assert_eq!(buf.len(), 19);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::DopsBox);
+
+        let dst_box = DopsBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+
+        let invalid = DopsBox {
+            channel_mapping_family: 1,
+            ..DopsBox::default()
+        };
+        assert!(invalid.write_box(&mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,366 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// Uncompressed audio sample entry, `ipcm` for integer and `fpcm` for
+/// floating point samples, see ISO/IEC 23003-5.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PcmBox {
+    /// `IpcmBox` or `FpcmBox`.
+    #[serde(skip)]
+    pub kind: BoxType,
+    pub data_reference_index: u16,
+    pub channelcount: u16,
+    pub samplesize: u16,
+
+    #[serde(with = "value_u32")]
+    pub samplerate: FixedPointU16,
+
+    /// Sample rate for rates not representable in `samplerate`, which makes
+    /// this a version 1 entry.
+    pub srat: Option<SratBox>,
+    pub pcmc: PcmCBox,
+}
+
+impl Default for PcmBox {
+    fn default() -> Self {
+        Self::new(&PcmConfig::default())
+    }
+}
+
+impl PcmBox {
+    pub fn new(config: &PcmConfig) -> Self {
+        let (samplerate, srat) = if config.sample_rate > 0xFFFF {
+            let srat = SratBox {
+                version: 0,
+                flags: 0,
+                sampling_rate: config.sample_rate,
+            };
+            (0, Some(srat))
+        } else {
+            (config.sample_rate as u16, None)
+        };
+
+        Self {
+            kind: if config.float {
+                BoxType::FpcmBox
+            } else {
+                BoxType::IpcmBox
+            },
+            data_reference_index: 1,
+            channelcount: config.channel_count,
+            samplesize: config.sample_size,
+            samplerate: FixedPointU16::new(samplerate),
+            srat,
+            pcmc: PcmCBox {
+                version: 0,
+                flags: 0,
+                format_flags: (config.endianness == Endianness::Little) as u8,
+                pcm_sample_size: config.sample_size as u8,
+            },
+        }
+    }
+
+    pub fn get_type(&self) -> BoxType {
+        self.kind
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + 8 + 20 + self.pcmc.box_size();
+        if let Some(ref srat) = self.srat {
+            size += srat.box_size();
+        }
+        size
+    }
+
+    pub fn config(&self) -> PcmConfig {
+        PcmConfig {
+            sample_rate: self
+                .srat
+                .as_ref()
+                .map(|x| x.sampling_rate)
+                .unwrap_or(self.samplerate.value() as u32),
+            channel_count: self.channelcount,
+            sample_size: self.pcmc.pcm_sample_size as u16,
+            endianness: self.pcmc.endianness(),
+            float: self.kind == BoxType::FpcmBox,
+        }
+    }
+}
+
+impl Mp4Box for PcmBox {
+    const TYPE: BoxType = BoxType::IpcmBox;
+    const ALIASES: &'static [BoxType] = &[BoxType::FpcmBox];
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let config = self.config();
+        let s = format!(
+            "channel_count={} sample_size={} sample_rate={} endianness={:?}",
+            config.channel_count, config.sample_size, config.sample_rate, config.endianness
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for PcmBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        reader.get_u32(); // reserved
+        reader.get_u16(); // reserved
+
+        let data_reference_index = reader.get_u16();
+
+        reader.get_u16(); // entry_version
+        reader.get_u16(); // reserved
+        reader.get_u32(); // reserved
+
+        let channelcount = reader.get_u16();
+        let samplesize = reader.get_u16();
+
+        reader.get_u32(); // pre-defined, reserved
+
+        let samplerate = FixedPointU16::new_raw(reader.get_u32());
+
+        let (pcmc, srat) = reader.try_find_box2::<PcmCBox, SratBox>()?;
+
+        Ok(PcmBox {
+            kind: Self::TYPE,
+            data_reference_index,
+            channelcount,
+            samplesize,
+            samplerate,
+            srat,
+            pcmc: pcmc.ok_or(BoxError::BoxNotFound(BoxType::PcmCBox))?,
+        })
+    }
+
+    fn read_block_as<'a>(kind: BoxType, reader: &mut impl Reader<'a>) -> Result<Self> {
+        Ok(PcmBox {
+            kind,
+            ..Self::read_block(reader)?
+        })
+    }
+
+    fn size_hint() -> usize {
+        28
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for PcmBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.kind, size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u16::<BigEndian>(self.srat.is_some() as u16)?; // entry_version
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.channelcount)?;
+        writer.write_u16::<BigEndian>(self.samplesize)?;
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u32::<BigEndian>(self.samplerate.raw_value())?;
+
+        if let Some(ref srat) = self.srat {
+            srat.write_box(writer)?;
+        }
+        self.pcmc.write_box(writer)?;
+
+        Ok(size)
+    }
+}
+
+/// PCM configuration box.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct PcmCBox {
+    pub version: u8,
+    pub flags: u32,
+
+    /// Bit 0 set for little endian samples.
+    pub format_flags: u8,
+    pub pcm_sample_size: u8,
+}
+
+impl PcmCBox {
+    pub fn endianness(&self) -> Endianness {
+        if self.format_flags & 1 == 1 {
+            Endianness::Little
+        } else {
+            Endianness::Big
+        }
+    }
+}
+
+impl Mp4Box for PcmCBox {
+    const TYPE: BoxType = BoxType::PcmCBox;
+
+    fn box_size(&self) -> u64 {
+        HEADER_SIZE + HEADER_EXT_SIZE + 2
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "format_flags={} pcm_sample_size={}",
+            self.format_flags, self.pcm_sample_size
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for PcmCBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let (version, flags) = read_box_header_ext(reader);
+
+        Ok(PcmCBox {
+            version,
+            flags,
+            format_flags: reader.get_u8(),
+            pcm_sample_size: reader.get_u8(),
+        })
+    }
+
+    fn size_hint() -> usize {
+        6
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for PcmCBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u8(self.format_flags)?;
+        writer.write_u8(self.pcm_sample_size)?;
+
+        Ok(size)
+    }
+}
+
+/// Sampling rate box of version 1 audio sample entries.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct SratBox {
+    pub version: u8,
+    pub flags: u32,
+    pub sampling_rate: u32,
+}
+
+impl Mp4Box for SratBox {
+    const TYPE: BoxType = BoxType::SratBox;
+
+    fn box_size(&self) -> u64 {
+        HEADER_SIZE + HEADER_EXT_SIZE + 4
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        Ok(format!("sampling_rate={}", self.sampling_rate))
+    }
+}
+
+impl BlockReader for SratBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let (version, flags) = read_box_header_ext(reader);
+
+        Ok(SratBox {
+            version,
+            flags,
+            sampling_rate: reader.get_u32(),
+        })
+    }
+
+    fn size_hint() -> usize {
+        8
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for SratBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+        writer.write_u32::<BigEndian>(self.sampling_rate)?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_ipcm() {
+        let config = PcmConfig {
+            sample_rate: 48000,
+            channel_count: 2,
+            sample_size: 24,
+            endianness: Endianness::Little,
+            float: false,
+        };
+        let src_box = PcmBox::new(&config);
+        assert!(src_box.srat.is_none());
+
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::IpcmBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = PcmBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert_eq!(dst_box.config(), config);
+    }
+
+    #[tokio::test]
+    async fn test_fpcm_high_rate() {
+        let config = PcmConfig {
+            sample_rate: 192000,
+            channel_count: 8,
+            sample_size: 32,
+            endianness: Endianness::Big,
+            float: true,
+        };
+        let src_box = PcmBox::new(&config);
+        assert_eq!(src_box.kind, BoxType::FpcmBox);
+
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::FpcmBox);
+
+        let dst_box = PcmBox {
+            kind: header.kind,
+            ..PcmBox::read_block(&mut reader).unwrap()
+        };
+        assert_eq!(src_box, dst_box);
+        assert_eq!(dst_box.config(), config);
+    }
+}
@@ -0,0 +1,187 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::mp4box::*;
+
+/// Producer reference time box, ISO/IEC 14496-12 section 8.16.5. Pairs a
+/// wall clock time with the media time of a track, for low latency
+/// streaming.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct PrftBox {
+    pub version: u8,
+
+    /// When the time was taken, one of the `TIME_*` constants.
+    pub flags: u32,
+    pub reference_track_id: u32,
+
+    /// NTP format: seconds since 1900 in the upper 32 bits, fraction in the
+    /// lower.
+    pub ntp_timestamp: u64,
+
+    /// In the timescale of the reference track. 32 bits in version 0.
+    pub media_time: u64,
+}
+
+impl PrftBox {
+    pub const TIME_ENCODER_INPUT: u32 = 0;
+    pub const TIME_ENCODER_OUTPUT: u32 = 1;
+    pub const TIME_MOOF_FINALIZED: u32 = 2;
+    pub const TIME_MOOF_WRITTEN: u32 = 4;
+    pub const TIME_ARBITRARY: u32 = 8;
+    pub const TIME_CAPTURED: u32 = 24;
+
+    /// Seconds between the NTP (1900) and Unix (1970) epochs.
+    const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+    pub fn get_type(&self) -> BoxType {
+        BoxType::PrftBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + HEADER_EXT_SIZE + 12;
+        if self.version == 1 {
+            size += 8;
+        } else {
+            size += 4;
+        }
+        size
+    }
+
+    /// The NTP timestamp as a system time, `None` if it is not representable.
+    pub fn system_time(&self) -> Option<SystemTime> {
+        let seconds = self.ntp_timestamp >> 32;
+        let nanos = ((self.ntp_timestamp & 0xFFFF_FFFF) * 1_000_000_000) >> 32;
+        let ntp = Duration::new(seconds, nanos as u32);
+        let offset = Duration::from_secs(Self::NTP_UNIX_OFFSET);
+
+        if ntp >= offset {
+            UNIX_EPOCH.checked_add(ntp - offset)
+        } else {
+            UNIX_EPOCH.checked_sub(offset - ntp)
+        }
+    }
+
+    /// Converts a system time to an NTP timestamp.
+    pub fn ntp_timestamp(time: SystemTime) -> u64 {
+        let ntp = match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => since + Duration::from_secs(Self::NTP_UNIX_OFFSET),
+            Err(err) => Duration::from_secs(Self::NTP_UNIX_OFFSET).saturating_sub(err.duration()),
+        };
+
+        let fraction = ((ntp.subsec_nanos() as u64) << 32) / 1_000_000_000;
+        ntp.as_secs() << 32 | fraction
+    }
+}
+
+impl Mp4Box for PrftBox {
+    const TYPE: BoxType = BoxType::PrftBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "reference_track_id={} ntp_timestamp={:#x} media_time={}",
+            self.reference_track_id, self.ntp_timestamp, self.media_time
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for PrftBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let (version, flags) = read_box_header_ext(reader);
+
+        let reference_track_id = reader.get_u32();
+        let ntp_timestamp = reader.get_u64();
+        let media_time = if version == 1 {
+            reader.try_get_u64()?
+        } else if version == 0 {
+            reader.try_get_u32()? as u64
+        } else {
+            return Err(BoxError::InvalidData("version must be 0 or 1"));
+        };
+
+        Ok(PrftBox {
+            version,
+            flags,
+            reference_track_id,
+            ntp_timestamp,
+            media_time,
+        })
+    }
+
+    fn size_hint() -> usize {
+        20
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for PrftBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.reference_track_id)?;
+        writer.write_u64::<BigEndian>(self.ntp_timestamp)?;
+        if self.version == 1 {
+            writer.write_u64::<BigEndian>(self.media_time)?;
+        } else if self.version == 0 {
+            writer.write_u32::<BigEndian>(self.media_time as u32)?;
+        } else {
+            return Err(BoxError::InvalidData("version must be 0 or 1"));
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_prft() {
+        for version in 0..2 {
+            let src_box = PrftBox {
+                version,
+                flags: PrftBox::TIME_ENCODER_OUTPUT,
+                reference_track_id: 1,
+                ntp_timestamp: 0xE9A1_B2C3_8000_0000,
+                media_time: 90000,
+            };
+            let mut buf = Vec::new();
+            src_box.write_box(&mut buf).unwrap();
+            assert_eq!(buf.len(), src_box.box_size() as usize);
+
+            let mut reader = buf.as_slice();
+            let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+            assert_eq!(header.kind, BoxType::PrftBox);
+            assert_eq!(src_box.box_size(), header.size);
+
+            let dst_box = PrftBox::read_block(&mut reader).unwrap();
+            assert_eq!(src_box, dst_box);
+        }
+    }
+
+    #[test]
+    fn test_prft_system_time() {
+        let time = UNIX_EPOCH + Duration::new(1_700_000_000, 500_000_000);
+        let prft = PrftBox {
+            ntp_timestamp: PrftBox::ntp_timestamp(time),
+            ..Default::default()
+        };
+        assert_eq!(prft.ntp_timestamp >> 32, 1_700_000_000 + 2_208_988_800);
+        assert_eq!(prft.ntp_timestamp & 0xFFFF_FFFF, 0x8000_0000);
+        assert_eq!(prft.system_time(), Some(time));
+    }
+}
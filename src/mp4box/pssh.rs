@@ -0,0 +1,165 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// Protection system specific header box, ISO/IEC 23001-7 section 8.1.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct PsshBox {
+    pub version: u8,
+    pub flags: u32,
+    pub system_id: [u8; 16],
+
+    /// Key IDs the data applies to, version 1 only.
+    pub kids: Vec<[u8; 16]>,
+
+    /// Opaque to the file format, e.g. a Widevine `WidevinePsshData`
+    /// protobuf or a PlayReady header object.
+    #[serde(skip_serializing)]
+    pub data: Vec<u8>,
+}
+
+impl PsshBox {
+    /// W3C "Common" system id used by clear key.
+    pub const SYSTEM_ID_COMMON: [u8; 16] = [
+        0x10, 0x77, 0xef, 0xec, 0xc0, 0xb2, 0x4d, 0x02, 0xac, 0xe3, 0x3c, 0x1e, 0x52, 0xe2, 0xfb,
+        0x4b,
+    ];
+    pub const SYSTEM_ID_WIDEVINE: [u8; 16] = [
+        0xed, 0xef, 0x8b, 0xa9, 0x79, 0xd6, 0x4a, 0xce, 0xa3, 0xc8, 0x27, 0xdc, 0xd5, 0x1d, 0x21,
+        0xed,
+    ];
+    pub const SYSTEM_ID_PLAYREADY: [u8; 16] = [
+        0x9a, 0x04, 0xf0, 0x79, 0x98, 0x40, 0x42, 0x86, 0xab, 0x92, 0xe6, 0x5b, 0xe0, 0x88, 0x5f,
+        0x95,
+    ];
+    pub const SYSTEM_ID_FAIRPLAY: [u8; 16] = [
+        0x94, 0xce, 0x86, 0xfb, 0x07, 0xff, 0x4f, 0x43, 0xad, 0xb8, 0x93, 0xd2, 0xfa, 0x96, 0x8c,
+        0xa2,
+    ];
+
+    pub fn get_type(&self) -> BoxType {
+        BoxType::PsshBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + HEADER_EXT_SIZE + 16 + 4 + self.data.len() as u64;
+        if self.version > 0 {
+            size += 4 + 16 * self.kids.len() as u64;
+        }
+        size
+    }
+}
+
+impl Mp4Box for PsshBox {
+    const TYPE: BoxType = BoxType::PsshBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let system_id: String = self.system_id.iter().map(|x| format!("{x:02x}")).collect();
+        let s = format!(
+            "system_id={} kids={} data_size={}",
+            system_id,
+            self.kids.len(),
+            self.data.len()
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for PsshBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let (version, flags) = read_box_header_ext(reader);
+
+        let mut system_id = [0; 16];
+        reader.copy_to_slice(&mut system_id)?;
+
+        let mut kids = Vec::new();
+        if version > 0 {
+            let kid_count = reader.try_get_u32()?;
+            if kid_count as usize > reader.remaining() / 16 {
+                return Err(BoxError::InvalidData(
+                    "pssh kid_count indicates more entries than could fit in the box",
+                ));
+            }
+
+            for _ in 0..kid_count {
+                let mut kid = [0; 16];
+                reader.copy_to_slice(&mut kid)?;
+                kids.push(kid);
+            }
+        }
+
+        let data_size = reader.try_get_u32()?;
+        let data = reader.collect(data_size as usize)?;
+
+        Ok(PsshBox {
+            version,
+            flags,
+            system_id,
+            kids,
+            data,
+        })
+    }
+
+    fn size_hint() -> usize {
+        24
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for PsshBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_all(&self.system_id)?;
+        if self.version > 0 {
+            writer.write_u32::<BigEndian>(self.kids.len() as u32)?;
+            for kid in self.kids.iter() {
+                writer.write_all(kid)?;
+            }
+        }
+        writer.write_u32::<BigEndian>(self.data.len() as u32)?;
+        writer.write_all(&self.data)?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_pssh() {
+        let src_box = PsshBox {
+            version: 1,
+            flags: 0,
+            system_id: PsshBox::SYSTEM_ID_COMMON,
+            kids: vec![[1; 16], [2; 16]],
+            data: b"opaque".to_vec(),
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::PsshBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = PsshBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+}
@@ -0,0 +1,149 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// Sample auxiliary information offsets box, ISO/IEC 14496-12 section
+/// 8.7.9.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct SaioBox {
+    pub version: u8,
+    pub flags: u32,
+
+    /// Read when [`SaioBox::FLAG_AUX_INFO_TYPE`] is set.
+    pub aux_info_type: Option<FourCC>,
+    pub aux_info_type_parameter: u32,
+
+    /// One offset for all samples, or one per chunk (`stbl`) or `trun`
+    /// (`traf`). In `traf` they are relative to the base data offset.
+    pub offsets: Vec<u64>,
+}
+
+impl SaioBox {
+    pub const FLAG_AUX_INFO_TYPE: u32 = 0x01;
+
+    pub fn get_type(&self) -> BoxType {
+        BoxType::SaioBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + HEADER_EXT_SIZE + 4;
+        if self.aux_info_type.is_some() {
+            size += 8;
+        }
+        let offset_size = if self.version == 0 { 4 } else { 8 };
+        size + offset_size * self.offsets.len() as u64
+    }
+}
+
+impl Mp4Box for SaioBox {
+    const TYPE: BoxType = BoxType::SaioBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!("entry_count={}", self.offsets.len());
+        Ok(s)
+    }
+}
+
+impl BlockReader for SaioBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let (version, flags) = read_box_header_ext(reader);
+
+        let (aux_info_type, aux_info_type_parameter) = if flags & Self::FLAG_AUX_INFO_TYPE != 0 {
+            (Some(reader.try_get_u32()?.into()), reader.try_get_u32()?)
+        } else {
+            (None, 0)
+        };
+
+        let entry_count = reader.try_get_u32()?;
+        let offset_size = if version == 0 { 4 } else { 8 };
+        if entry_count as usize > reader.remaining() / offset_size {
+            return Err(BoxError::InvalidData(
+                "saio entry_count indicates more entries than could fit in the box",
+            ));
+        }
+
+        let offsets = (0..entry_count)
+            .map(|_| {
+                if version == 0 {
+                    reader.get_u32() as u64
+                } else {
+                    reader.get_u64()
+                }
+            })
+            .collect();
+
+        Ok(SaioBox {
+            version,
+            flags,
+            aux_info_type,
+            aux_info_type_parameter,
+            offsets,
+        })
+    }
+
+    fn size_hint() -> usize {
+        8
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for SaioBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+
+        if let Some(aux_info_type) = self.aux_info_type {
+            writer.write_u32::<BigEndian>(aux_info_type.into())?;
+            writer.write_u32::<BigEndian>(self.aux_info_type_parameter)?;
+        }
+        writer.write_u32::<BigEndian>(self.offsets.len() as u32)?;
+        for &offset in self.offsets.iter() {
+            if self.version == 0 {
+                writer.write_u32::<BigEndian>(offset as u32)?;
+            } else {
+                writer.write_u64::<BigEndian>(offset)?;
+            }
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_saio() {
+        let src_box = SaioBox {
+            version: 1,
+            flags: 0,
+            aux_info_type: None,
+            aux_info_type_parameter: 0,
+            offsets: vec![0x1_0000_0000],
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::SaioBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = SaioBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+}
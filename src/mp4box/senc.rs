@@ -0,0 +1,274 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// Sample encryption box, ISO/IEC 23001-7 section 7.2.
+///
+/// The per-sample IV size is declared in `tenc` rather than in the box, so
+/// the entries are kept raw and parsed with [`SencBox::samples`] once the
+/// sample entry is known.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct SencBox {
+    pub version: u8,
+    pub flags: u32,
+    pub sample_count: u32,
+
+    #[serde(skip_serializing)]
+    pub data: Vec<u8>,
+}
+
+/// Encryption parameters of one sample, from `senc` or the auxiliary
+/// information referenced by `saiz`/`saio`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct SampleEncryption {
+    /// Empty with a constant IV from `tenc`.
+    pub iv: Vec<u8>,
+
+    /// Empty if the whole sample is encrypted.
+    pub subsamples: Vec<SubsampleEncryption>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct SubsampleEncryption {
+    pub clear_bytes: u16,
+    pub encrypted_bytes: u32,
+}
+
+impl SampleEncryption {
+    pub(crate) fn read<'a>(
+        reader: &mut impl Reader<'a>,
+        iv_size: usize,
+        use_subsamples: bool,
+    ) -> Result<Self> {
+        let iv = reader.collect(iv_size)?;
+
+        let mut subsamples = Vec::new();
+        if use_subsamples {
+            let subsample_count = reader.try_get_u16()?;
+            if subsample_count as usize * 6 > reader.remaining() {
+                return Err(BoxError::InvalidData(
+                    "subsample_count indicates more entries than could fit in the box",
+                ));
+            }
+
+            for _ in 0..subsample_count {
+                subsamples.push(SubsampleEncryption {
+                    clear_bytes: reader.get_u16(),
+                    encrypted_bytes: reader.get_u32(),
+                });
+            }
+        }
+
+        Ok(SampleEncryption { iv, subsamples })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W, use_subsamples: bool) -> Result<()> {
+        writer.write_all(&self.iv)?;
+        if use_subsamples {
+            writer.write_u16::<BigEndian>(self.subsamples.len() as u16)?;
+            for subsample in self.subsamples.iter() {
+                writer.write_u16::<BigEndian>(subsample.clear_bytes)?;
+                writer.write_u32::<BigEndian>(subsample.encrypted_bytes)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SencBox {
+    pub const FLAG_USE_SUBSAMPLE_ENCRYPTION: u32 = 0x02;
+
+    pub fn get_type(&self) -> BoxType {
+        BoxType::SencBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        HEADER_SIZE + HEADER_EXT_SIZE + 4 + self.data.len() as u64
+    }
+
+    /// Builds the box from parsed entries, flagging sub-samples if any
+    /// entry has them.
+    pub fn new(samples: &[SampleEncryption]) -> Self {
+        let use_subsamples = samples.iter().any(|x| !x.subsamples.is_empty());
+        let mut data = Vec::new();
+        for sample in samples.iter() {
+            sample.write(&mut data, use_subsamples).unwrap();
+        }
+
+        SencBox {
+            version: 0,
+            flags: if use_subsamples {
+                Self::FLAG_USE_SUBSAMPLE_ENCRYPTION
+            } else {
+                0
+            },
+            sample_count: samples.len() as u32,
+            data,
+        }
+    }
+
+    /// Parses the entries with the `default_per_sample_iv_size` of `tenc`.
+    pub fn samples(&self, iv_size: u8) -> Result<Vec<SampleEncryption>> {
+        let use_subsamples = self.flags & Self::FLAG_USE_SUBSAMPLE_ENCRYPTION != 0;
+        let min_size = iv_size as usize + if use_subsamples { 2 } else { 0 };
+        if self.sample_count as usize * min_size > self.data.len() {
+            return Err(BoxError::InvalidData(
+                "senc sample_count indicates more entries than could fit in the box",
+            ));
+        }
+
+        let mut data = self.data.as_slice();
+        let mut samples = Vec::with_capacity(self.sample_count as usize);
+        for _ in 0..self.sample_count {
+            samples.push(SampleEncryption::read(
+                &mut data,
+                iv_size as usize,
+                use_subsamples,
+            )?);
+        }
+
+        if !data.is_empty() {
+            return Err(BoxError::InvalidData(
+                "senc entries do not match the per-sample IV size",
+            ));
+        }
+        Ok(samples)
+    }
+}
+
+impl Mp4Box for SencBox {
+    const TYPE: BoxType = BoxType::SencBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!("sample_count={}", self.sample_count);
+        Ok(s)
+    }
+}
+
+impl BlockReader for SencBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let (version, flags) = read_box_header_ext(reader);
+
+        Ok(SencBox {
+            version,
+            flags,
+            sample_count: reader.get_u32(),
+            data: reader.collect_remaining(),
+        })
+    }
+
+    fn size_hint() -> usize {
+        8
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for SencBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.sample_count)?;
+        writer.write_all(&self.data)?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    async fn round_trip(samples: Vec<SampleEncryption>, iv_size: u8) {
+        let src_box = SencBox::new(&samples);
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::SencBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = SencBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert_eq!(dst_box.samples(iv_size).unwrap(), samples);
+    }
+
+    #[tokio::test]
+    async fn test_senc() {
+        round_trip(
+            vec![
+                SampleEncryption {
+                    iv: vec![1; 8],
+                    subsamples: vec![
+                        SubsampleEncryption {
+                            clear_bytes: 5,
+                            encrypted_bytes: 1024,
+                        },
+                        SubsampleEncryption {
+                            clear_bytes: 3,
+                            encrypted_bytes: 16,
+                        },
+                    ],
+                },
+                SampleEncryption {
+                    iv: vec![2; 8],
+                    subsamples: vec![],
+                },
+            ],
+            8,
+        )
+        .await;
+
+        round_trip(
+            vec![
+                SampleEncryption {
+                    iv: vec![3; 16],
+                    subsamples: vec![],
+                };
+                3
+            ],
+            16,
+        )
+        .await;
+
+        // constant IV, sub-samples only
+        round_trip(
+            vec![SampleEncryption {
+                iv: vec![],
+                subsamples: vec![SubsampleEncryption {
+                    clear_bytes: 32,
+                    encrypted_bytes: 160,
+                }],
+            }],
+            0,
+        )
+        .await;
+
+        // constant IV without sub-samples leaves the entries empty
+        round_trip(vec![SampleEncryption::default(); 4], 0).await;
+    }
+
+    #[test]
+    fn test_senc_iv_size_mismatch() {
+        let senc = SencBox::new(&[SampleEncryption {
+            iv: vec![7; 16],
+            subsamples: vec![],
+        }]);
+        assert!(senc.samples(8).is_err());
+        assert!(senc.samples(16).is_ok());
+    }
+}
@@ -0,0 +1,279 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+use std::ops::Range;
+
+use crate::mp4box::*;
+
+/// Segment index box, ISO/IEC 14496-12 section 8.16.3.
+///
+/// Reference offsets are relative to the first byte after the box (plus
+/// `first_offset`), so resolving them needs the position of the box.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct SidxBox {
+    pub version: u8,
+    pub flags: u32,
+    pub reference_id: u32,
+    pub timescale: u32,
+    pub earliest_presentation_time: u64,
+    pub first_offset: u64,
+
+    #[serde(skip_serializing)]
+    pub references: Vec<SidxReference>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct SidxReference {
+    /// Whether the reference points to another `sidx` rather than to media.
+    pub reference_type: bool,
+
+    /// 31 bits.
+    pub referenced_size: u32,
+    pub subsegment_duration: u32,
+    pub starts_with_sap: bool,
+
+    /// 3 bits, 1 to 6 per ISO/IEC 14496-12 annex I, 0 if unknown.
+    pub sap_type: u8,
+
+    /// 28 bits.
+    pub sap_delta_time: u32,
+}
+
+impl SidxBox {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::SidxBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + HEADER_EXT_SIZE + 12 + 12 * self.references.len() as u64;
+        if self.version == 1 {
+            size += 16;
+        } else {
+            size += 8;
+        }
+        size
+    }
+
+    /// Start time and byte range of each reference, with `anchor` the file
+    /// offset of the first byte after the box.
+    pub fn subsegments(
+        &self,
+        anchor: u64,
+    ) -> impl Iterator<Item = (u64, Range<u64>, &SidxReference)> + '_ {
+        let mut time = self.earliest_presentation_time;
+        let mut offset = anchor + self.first_offset;
+
+        self.references.iter().map(move |reference| {
+            let start_time = time;
+            let start = offset;
+            time += reference.subsegment_duration as u64;
+            offset += reference.referenced_size as u64;
+
+            (start_time, start..offset, reference)
+        })
+    }
+
+    /// The reference covering `time`, in `timescale` units: its start time,
+    /// byte range and the reference itself. Times before the first reference
+    /// map to it.
+    pub fn subsegment_at(
+        &self,
+        anchor: u64,
+        time: u64,
+    ) -> Option<(u64, Range<u64>, &SidxReference)> {
+        let time = time.max(self.earliest_presentation_time);
+
+        self.subsegments(anchor).find(|(start_time, _, reference)| {
+            time < start_time + reference.subsegment_duration as u64
+        })
+    }
+}
+
+impl Mp4Box for SidxBox {
+    const TYPE: BoxType = BoxType::SidxBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "reference_id={} timescale={} earliest_presentation_time={} references={}",
+            self.reference_id,
+            self.timescale,
+            self.earliest_presentation_time,
+            self.references.len()
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for SidxBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let (version, flags) = read_box_header_ext(reader);
+
+        let reference_id = reader.get_u32();
+        let timescale = reader.get_u32();
+        let (earliest_presentation_time, first_offset) = if version == 1 {
+            (reader.try_get_u64()?, reader.try_get_u64()?)
+        } else if version == 0 {
+            (reader.try_get_u32()? as u64, reader.try_get_u32()? as u64)
+        } else {
+            return Err(BoxError::InvalidData("version must be 0 or 1"));
+        };
+
+        reader.try_get_u16()?; // reserved
+        let reference_count = reader.try_get_u16()?;
+        if reference_count as usize * 12 > reader.remaining() {
+            return Err(BoxError::InvalidData(
+                "sidx reference_count indicates more entries than could fit in the box",
+            ));
+        }
+
+        let mut references = Vec::with_capacity(reference_count as usize);
+        for _ in 0..reference_count {
+            let size = reader.get_u32();
+            let subsegment_duration = reader.get_u32();
+            let sap = reader.get_u32();
+
+            references.push(SidxReference {
+                reference_type: size >> 31 != 0,
+                referenced_size: size & 0x7FFF_FFFF,
+                subsegment_duration,
+                starts_with_sap: sap >> 31 != 0,
+                sap_type: (sap >> 28 & 0x07) as u8,
+                sap_delta_time: sap & 0x0FFF_FFFF,
+            });
+        }
+
+        Ok(SidxBox {
+            version,
+            flags,
+            reference_id,
+            timescale,
+            earliest_presentation_time,
+            first_offset,
+            references,
+        })
+    }
+
+    fn size_hint() -> usize {
+        24
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for SidxBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.reference_id)?;
+        writer.write_u32::<BigEndian>(self.timescale)?;
+        if self.version == 1 {
+            writer.write_u64::<BigEndian>(self.earliest_presentation_time)?;
+            writer.write_u64::<BigEndian>(self.first_offset)?;
+        } else if self.version == 0 {
+            writer.write_u32::<BigEndian>(self.earliest_presentation_time as u32)?;
+            writer.write_u32::<BigEndian>(self.first_offset as u32)?;
+        } else {
+            return Err(BoxError::InvalidData("version must be 0 or 1"));
+        }
+
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.references.len() as u16)?;
+        for reference in self.references.iter() {
+            writer.write_u32::<BigEndian>(
+                (reference.reference_type as u32) << 31 | reference.referenced_size & 0x7FFF_FFFF,
+            )?;
+            writer.write_u32::<BigEndian>(reference.subsegment_duration)?;
+            writer.write_u32::<BigEndian>(
+                (reference.starts_with_sap as u32) << 31
+                    | (reference.sap_type as u32 & 0x07) << 28
+                    | reference.sap_delta_time & 0x0FFF_FFFF,
+            )?;
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    fn sidx(version: u8) -> SidxBox {
+        SidxBox {
+            version,
+            flags: 0,
+            reference_id: 1,
+            timescale: 90000,
+            earliest_presentation_time: 1800,
+            first_offset: 10,
+            references: vec![
+                SidxReference {
+                    reference_type: false,
+                    referenced_size: 1000,
+                    subsegment_duration: 180000,
+                    starts_with_sap: true,
+                    sap_type: 1,
+                    sap_delta_time: 0,
+                },
+                SidxReference {
+                    reference_type: false,
+                    referenced_size: 2000,
+                    subsegment_duration: 90000,
+                    starts_with_sap: false,
+                    sap_type: 0,
+                    sap_delta_time: 3000,
+                },
+            ],
+        }
+    }
+
+    async fn round_trip(src_box: SidxBox) {
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::SidxBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = SidxBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[tokio::test]
+    async fn test_sidx32() {
+        round_trip(sidx(0)).await;
+    }
+
+    #[tokio::test]
+    async fn test_sidx64() {
+        let mut src_box = sidx(1);
+        src_box.earliest_presentation_time = u32::MAX as u64 + 1;
+        round_trip(src_box).await;
+    }
+
+    #[test]
+    fn test_subsegment_at() {
+        let sidx = sidx(0);
+        let lookup = |time| {
+            sidx.subsegment_at(100, time)
+                .map(|(start_time, range, _)| (start_time, range))
+        };
+
+        assert_eq!(lookup(0), Some((1800, 110..1110)));
+        assert_eq!(lookup(181799), Some((1800, 110..1110)));
+        assert_eq!(lookup(181800), Some((181800, 1110..3110)));
+        assert_eq!(lookup(271800), None);
+    }
+}
@@ -0,0 +1,335 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::tenc::TencBox;
+use crate::mp4box::*;
+
+/// Protection scheme information box, ISO/IEC 14496-12 section 8.12.1.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct SinfBox {
+    pub frma: FrmaBox,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schm: Option<SchmBox>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schi: Option<SchiBox>,
+}
+
+impl SinfBox {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::SinfBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + self.frma.box_size();
+        if let Some(ref schm) = self.schm {
+            size += schm.box_size();
+        }
+        if let Some(ref schi) = self.schi {
+            size += schi.box_size();
+        }
+        size
+    }
+
+    /// Protection scheme, e.g. [`SchmBox::CENC`] or [`SchmBox::CBCS`].
+    pub fn scheme_type(&self) -> Option<FourCC> {
+        self.schm.as_ref().map(|x| x.scheme_type)
+    }
+
+    pub fn tenc(&self) -> Option<&TencBox> {
+        self.schi.as_ref()?.tenc.as_ref()
+    }
+}
+
+impl Mp4Box for SinfBox {
+    const TYPE: BoxType = BoxType::SinfBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = match self.scheme_type() {
+            Some(scheme_type) => format!(
+                "original_format={} scheme_type={}",
+                self.frma.data_format, scheme_type
+            ),
+            None => format!("original_format={}", self.frma.data_format),
+        };
+        Ok(s)
+    }
+}
+
+impl BlockReader for SinfBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let (frma, schm, schi) = reader.try_find_box3()?;
+
+        let Some(frma) = frma else {
+            return Err(BoxError::BoxNotFound(BoxType::FrmaBox));
+        };
+
+        Ok(SinfBox { frma, schm, schi })
+    }
+
+    fn size_hint() -> usize {
+        FrmaBox::size_hint()
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for SinfBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        self.frma.write_box(writer)?;
+        if let Some(ref schm) = self.schm {
+            schm.write_box(writer)?;
+        }
+        if let Some(ref schi) = self.schi {
+            schi.write_box(writer)?;
+        }
+
+        Ok(size)
+    }
+}
+
+/// Original format box: the type of the sample entry before it was renamed
+/// to `encv`/`enca`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct FrmaBox {
+    pub data_format: FourCC,
+}
+
+impl Mp4Box for FrmaBox {
+    const TYPE: BoxType = BoxType::FrmaBox;
+
+    fn box_size(&self) -> u64 {
+        HEADER_SIZE + 4
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        Ok(format!("data_format={}", self.data_format))
+    }
+}
+
+impl BlockReader for FrmaBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        Ok(FrmaBox {
+            data_format: reader.get_u32().into(),
+        })
+    }
+
+    fn size_hint() -> usize {
+        4
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for FrmaBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(self.data_format.into())?;
+
+        Ok(size)
+    }
+}
+
+/// Scheme type box.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct SchmBox {
+    pub version: u8,
+    pub flags: u32,
+    pub scheme_type: FourCC,
+
+    /// `0x00010000` for the ISO/IEC 23001-7 schemes.
+    pub scheme_version: u32,
+
+    /// Read when [`SchmBox::FLAG_SCHEME_URI`] is set.
+    pub scheme_uri: Option<String>,
+}
+
+impl SchmBox {
+    pub const FLAG_SCHEME_URI: u32 = 0x01;
+
+    pub const CENC: FourCC = FourCC { value: *b"cenc" };
+    pub const CBC1: FourCC = FourCC { value: *b"cbc1" };
+    pub const CENS: FourCC = FourCC { value: *b"cens" };
+    pub const CBCS: FourCC = FourCC { value: *b"cbcs" };
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + HEADER_EXT_SIZE + 8;
+        if let Some(ref scheme_uri) = self.scheme_uri {
+            size += scheme_uri.len() as u64 + 1;
+        }
+        size
+    }
+}
+
+impl Mp4Box for SchmBox {
+    const TYPE: BoxType = BoxType::SchmBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "scheme_type={} scheme_version={:#x}",
+            self.scheme_type, self.scheme_version
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for SchmBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let (version, flags) = read_box_header_ext(reader);
+
+        let scheme_type = reader.get_u32().into();
+        let scheme_version = reader.get_u32();
+        let scheme_uri = if flags & Self::FLAG_SCHEME_URI != 0 {
+            Some(reader.get_null_terminated_string())
+        } else {
+            None
+        };
+
+        Ok(SchmBox {
+            version,
+            flags,
+            scheme_type,
+            scheme_version,
+            scheme_uri,
+        })
+    }
+
+    fn size_hint() -> usize {
+        12
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for SchmBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.scheme_type.into())?;
+        writer.write_u32::<BigEndian>(self.scheme_version)?;
+        if let Some(ref scheme_uri) = self.scheme_uri {
+            writer.write_all(scheme_uri.as_bytes())?;
+            writer.write_u8(0)?;
+        }
+
+        Ok(size)
+    }
+}
+
+/// Scheme information box, holding the `tenc` of the ISO/IEC 23001-7
+/// schemes.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct SchiBox {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenc: Option<TencBox>,
+}
+
+impl Mp4Box for SchiBox {
+    const TYPE: BoxType = BoxType::SchiBox;
+
+    fn box_size(&self) -> u64 {
+        HEADER_SIZE + self.tenc.as_ref().map(|x| x.box_size()).unwrap_or(0)
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        Ok(String::new())
+    }
+}
+
+impl BlockReader for SchiBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        Ok(SchiBox {
+            tenc: reader.try_find_box()?,
+        })
+    }
+
+    fn size_hint() -> usize {
+        0
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for SchiBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        if let Some(ref tenc) = self.tenc {
+            tenc.write_box(writer)?;
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_sinf() {
+        let src_box = SinfBox {
+            frma: FrmaBox {
+                data_format: str::parse("avc1").unwrap(),
+            },
+            schm: Some(SchmBox {
+                version: 0,
+                flags: SchmBox::FLAG_SCHEME_URI,
+                scheme_type: SchmBox::CBCS,
+                scheme_version: 0x00010000,
+                scheme_uri: Some(String::from("urn:example")),
+            }),
+            schi: Some(SchiBox {
+                tenc: Some(TencBox {
+                    version: 1,
+                    default_crypt_byte_block: 1,
+                    default_skip_byte_block: 9,
+                    default_is_protected: 1,
+                    default_constant_iv: Some(vec![7; 16]),
+                    ..Default::default()
+                }),
+            }),
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::SinfBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = SinfBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert_eq!(dst_box.scheme_type(), Some(SchmBox::CBCS));
+        assert_eq!(dst_box.tenc().unwrap().default_skip_byte_block, 9);
+    }
+}
@@ -3,8 +3,8 @@ use std::io::Write;
 
 use crate::mp4box::*;
 use crate::mp4box::{
-    co64::Co64Box, ctts::CttsBox, stco::StcoBox, stsc::StscBox, stsd::StsdBox, stss::StssBox,
-    stsz::StszBox, stts::SttsBox,
+    co64::Co64Box, ctts::CttsBox, saio::SaioBox, saiz::SaizBox, stco::StcoBox, stsc::StscBox,
+    stsd::StsdBox, stss::StssBox, stsz::StszBox, stts::SttsBox, subs::SubsBox,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
@@ -25,6 +25,16 @@ pub struct StblBox {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub co64: Option<Co64Box>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subs: Option<SubsBox>,
+
+    /// Sample auxiliary information, e.g. the IVs of protected samples.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saiz: Option<SaizBox>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saio: Option<SaioBox>,
 }
 
 impl StblBox {
@@ -50,6 +60,15 @@ impl StblBox {
         if let Some(ref co64) = self.co64 {
             size += co64.box_size();
         }
+        if let Some(ref subs) = self.subs {
+            size += subs.box_size();
+        }
+        if let Some(ref saiz) = self.saiz {
+            size += saiz.box_size();
+        }
+        if let Some(ref saio) = self.saio {
+            size += saio.box_size();
+        }
         size
     }
 }
@@ -81,6 +100,9 @@ impl BlockReader for StblBox {
         let mut stsz = None;
         let mut stco = None;
         let mut co64 = None;
+        let mut subs = None;
+        let mut saiz = None;
+        let mut saio = None;
 
         while let Some(mut bx) = reader.get_box()? {
             match bx.kind {
@@ -116,6 +138,18 @@ impl BlockReader for StblBox {
                     co64 = Some(bx.read()?);
                 }
 
+                BoxType::SubsBox => {
+                    subs = Some(bx.read()?);
+                }
+
+                BoxType::SaizBox => {
+                    saiz = Some(bx.read()?);
+                }
+
+                BoxType::SaioBox => {
+                    saio = Some(bx.read()?);
+                }
+
                 _ => continue,
             }
         }
@@ -149,6 +183,9 @@ impl BlockReader for StblBox {
             stsz: stsz.unwrap(),
             stco,
             co64,
+            subs,
+            saiz,
+            saio,
         })
     }
 
@@ -178,6 +215,15 @@ impl<W: Write> WriteBox<&mut W> for StblBox {
         if let Some(ref co64) = self.co64 {
             co64.write_box(writer)?;
         }
+        if let Some(ref subs) = self.subs {
+            subs.write_box(writer)?;
+        }
+        if let Some(ref saiz) = self.saiz {
+            saiz.write_box(writer)?;
+        }
+        if let Some(ref saio) = self.saio {
+            saio.write_box(writer)?;
+        }
 
         Ok(size)
     }
@@ -0,0 +1,206 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use bytes::Bytes;
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// XML subtitle sample entry, e.g. TTML/IMSC1. See ISO/IEC 14496-12 section
+/// 12.6.3 and ISO/IEC 14496-30.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct StppBox {
+    pub data_reference_index: u16,
+
+    /// Space separated XML namespaces, e.g. `http://www.w3.org/ns/ttml`.
+    pub namespace: String,
+
+    /// Space separated URLs of the schemas for the namespaces, may be empty.
+    pub schema_location: String,
+
+    /// Space separated MIME types of the image subsamples, may be empty.
+    pub auxiliary_mime_types: String,
+}
+
+impl StppBox {
+    pub fn new(config: &TtmlConfig) -> Self {
+        Self {
+            data_reference_index: 1,
+            namespace: config.namespace.clone(),
+            schema_location: config.schema_location.clone(),
+            auxiliary_mime_types: config.auxiliary_mime_types.clone(),
+        }
+    }
+
+    pub fn get_type(&self) -> BoxType {
+        BoxType::StppBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        HEADER_SIZE
+            + 8
+            + self.namespace.len() as u64
+            + self.schema_location.len() as u64
+            + self.auxiliary_mime_types.len() as u64
+            + 3
+    }
+}
+
+impl Mp4Box for StppBox {
+    const TYPE: BoxType = BoxType::StppBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "namespace={:?} schema_location={:?} auxiliary_mime_types={:?}",
+            self.namespace, self.schema_location, self.auxiliary_mime_types
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for StppBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        reader.get_u32(); // reserved
+        reader.get_u16(); // reserved
+
+        let data_reference_index = reader.get_u16();
+
+        // child boxes such as `mime` and `btrt` follow the strings
+        Ok(StppBox {
+            data_reference_index,
+            namespace: reader.get_null_terminated_string(),
+            schema_location: reader.get_null_terminated_string(),
+            auxiliary_mime_types: reader.get_null_terminated_string(),
+        })
+    }
+
+    fn size_hint() -> usize {
+        9
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for StppBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        for s in [
+            &self.namespace,
+            &self.schema_location,
+            &self.auxiliary_mime_types,
+        ] {
+            writer.write_all(s.as_bytes())?;
+            writer.write_u8(0)?;
+        }
+
+        Ok(size)
+    }
+}
+
+/// Decoded `stpp` sample: the XML document followed by the resources it
+/// references, e.g. PNG images of IMSC1 image profile documents.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StppSample {
+    pub document: String,
+    pub resources: Vec<Bytes>,
+}
+
+impl StppSample {
+    /// Splits a sample along its `subs` entries. Without sub-samples the
+    /// whole sample is the document.
+    pub fn parse(bytes: Bytes, subsamples: &[Subsample]) -> Result<Self> {
+        let mut parts = Vec::with_capacity(subsamples.len().max(1));
+        let mut offset = 0usize;
+
+        for subsample in subsamples {
+            let end = offset + subsample.size as usize;
+            if end > bytes.len() {
+                return Err(BoxError::InvalidData(
+                    "stpp: subsamples exceed the sample size",
+                ));
+            }
+
+            parts.push(bytes.slice(offset..end));
+            offset = end;
+        }
+
+        if parts.is_empty() {
+            parts.push(bytes);
+        }
+
+        let mut parts = parts.into_iter();
+        let document = parts.next().unwrap_or_default();
+        let document = String::from_utf8(document.to_vec())
+            .map_err(|_| BoxError::InvalidData("stpp: document is not utf-8"))?;
+
+        Ok(StppSample {
+            document,
+            resources: parts.collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_stpp() {
+        let src_box = StppBox::new(&TtmlConfig {
+            namespace: String::from("http://www.w3.org/ns/ttml"),
+            schema_location: String::new(),
+            auxiliary_mime_types: String::from("image/png"),
+        });
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::StppBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = StppBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[test]
+    fn test_stpp_sample() {
+        let doc = r#"<tt xmlns="http://www.w3.org/ns/ttml"><body/></tt>"#;
+        let mut data = doc.as_bytes().to_vec();
+        data.extend_from_slice(b"\x89PNG");
+
+        let subsamples = [
+            Subsample {
+                size: doc.len() as u32,
+                ..Default::default()
+            },
+            Subsample {
+                size: 4,
+                ..Default::default()
+            },
+        ];
+
+        let sample = StppSample::parse(Bytes::from(data.clone()), &subsamples).unwrap();
+        assert_eq!(sample.document, doc);
+        assert_eq!(sample.resources, [Bytes::from_static(b"\x89PNG")]);
+
+        let sample = StppSample::parse(Bytes::from(doc), &[]).unwrap();
+        assert_eq!(sample.document, doc);
+        assert!(sample.resources.is_empty());
+
+        assert!(StppSample::parse(Bytes::from(doc), &subsamples).is_err());
+    }
+}
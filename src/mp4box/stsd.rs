@@ -4,27 +4,224 @@ use std::io::Write;
 
 use crate::mp4box::vp09::Vp09Box;
 use crate::mp4box::*;
-use crate::mp4box::{avc1::Avc1Box, hev1::Hev1Box, mp4a::Mp4aBox, tx3g::Tx3gBox};
+use crate::mp4box::{
+    ac3::Ac3Box, alac::AlacBox, av01::Av01Box, avc1::Avc1Box, c608::C608Box, ec3::Ec3Box,
+    encv::EncvBox, flac::FlacBox, hev1::Hev1Box, lpcm::LpcmBox, mp4a::Mp4aBox, opus::OpusBox,
+    pcm::PcmBox, sinf::SinfBox, stpp::StppBox, twos::TwosBox, tx3g::Tx3gBox, wvtt::WvttBox,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct StsdBox {
     pub version: u8,
     pub flags: u32,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub avc1: Option<Avc1Box>,
+    /// Sample entries in file order; `sample_description_index` in `stsc`
+    /// and `tfhd` is a 1-based index into this list.
+    #[serde(rename = "entry")]
+    pub entries: Vec<SampleEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SampleEntry {
+    Avc1(Avc1Box),
+    Hev1(Hev1Box),
+    Vp09(Vp09Box),
+    Av01(Av01Box),
+    Mp4a(Mp4aBox),
+    Opus(OpusBox),
+    Flac(FlacBox),
+    #[serde(rename = "ac-3")]
+    Ac3(Ac3Box),
+    #[serde(rename = "ec-3")]
+    Ec3(Ec3Box),
+
+    /// `ipcm` or `fpcm`.
+    Pcm(PcmBox),
+
+    /// `twos` or `sowt`.
+    Twos(TwosBox),
+    Lpcm(LpcmBox),
+    Alac(AlacBox),
+    Tx3g(Tx3gBox),
+    Wvtt(WvttBox),
+    Stpp(StppBox),
+    C608(C608Box),
+
+    /// `encv` or `enca`.
+    Encv(EncvBox),
+
+    /// Kept as is so the indices of the following entries stay valid.
+    #[serde(skip)]
+    Unknown {
+        kind: BoxType,
+        data: Vec<u8>,
+    },
+}
+
+impl SampleEntry {
+    pub fn get_type(&self) -> BoxType {
+        match self {
+            SampleEntry::Avc1(_) => BoxType::Avc1Box,
+            SampleEntry::Hev1(hev1) => hev1.kind,
+            SampleEntry::Vp09(_) => BoxType::Vp09Box,
+            SampleEntry::Av01(_) => BoxType::Av01Box,
+            SampleEntry::Mp4a(_) => BoxType::Mp4aBox,
+            SampleEntry::Opus(_) => BoxType::OpusBox,
+            SampleEntry::Flac(_) => BoxType::FlacBox,
+            SampleEntry::Ac3(_) => BoxType::Ac3Box,
+            SampleEntry::Ec3(_) => BoxType::Ec3Box,
+            SampleEntry::Pcm(pcm) => pcm.kind,
+            SampleEntry::Twos(twos) => twos.kind,
+            SampleEntry::Lpcm(_) => BoxType::LpcmBox,
+            SampleEntry::Alac(_) => BoxType::AlacBox,
+            SampleEntry::Tx3g(_) => BoxType::Tx3gBox,
+            SampleEntry::Wvtt(_) => BoxType::WvttBox,
+            SampleEntry::Stpp(_) => BoxType::StppBox,
+            SampleEntry::C608(_) => BoxType::C608Box,
+            SampleEntry::Encv(encv) => encv.kind,
+            SampleEntry::Unknown { kind, .. } => *kind,
+        }
+    }
+
+    /// Decoded sample format of uncompressed and lossless audio entries.
+    pub fn pcm_config(&self) -> Option<PcmConfig> {
+        match self {
+            SampleEntry::Pcm(pcm) => Some(pcm.config()),
+            SampleEntry::Twos(twos) => Some(twos.config()),
+            SampleEntry::Lpcm(lpcm) => Some(lpcm.config()),
+            SampleEntry::Alac(alac) => Some(alac.config()),
+            SampleEntry::Encv(encv) => encv.entry.pcm_config(),
+            _ => None,
+        }
+    }
+
+    /// Codec of the entry, `None` for entries kept as raw bytes.
+    pub fn media_type(&self) -> Option<MediaType> {
+        match self {
+            SampleEntry::Avc1(_) => Some(MediaType::H264),
+            SampleEntry::Hev1(_) => Some(MediaType::H265),
+            SampleEntry::Vp09(_) => Some(MediaType::VP9),
+            SampleEntry::Av01(_) => Some(MediaType::AV1),
+            SampleEntry::Mp4a(_) => Some(MediaType::AAC),
+            SampleEntry::Opus(_) => Some(MediaType::OPUS),
+            SampleEntry::Flac(_) => Some(MediaType::FLAC),
+            SampleEntry::Ac3(_) => Some(MediaType::AC3),
+            SampleEntry::Ec3(_) => Some(MediaType::EAC3),
+            SampleEntry::Pcm(_) | SampleEntry::Twos(_) | SampleEntry::Lpcm(_) => {
+                Some(MediaType::PCM)
+            }
+            SampleEntry::Alac(_) => Some(MediaType::ALAC),
+            SampleEntry::Tx3g(_) => Some(MediaType::TTXT),
+            SampleEntry::Wvtt(_) => Some(MediaType::WEBVTT),
+            SampleEntry::Stpp(_) => Some(MediaType::TTML),
+            SampleEntry::C608(_) => Some(MediaType::CEA608),
+            SampleEntry::Encv(encv) => encv.entry.media_type(),
+            SampleEntry::Unknown { .. } => None,
+        }
+    }
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub hev1: Option<Hev1Box>,
+    pub fn box_size(&self) -> u64 {
+        match self {
+            SampleEntry::Avc1(avc1) => avc1.box_size(),
+            SampleEntry::Hev1(hev1) => hev1.box_size(),
+            SampleEntry::Vp09(vp09) => vp09.box_size(),
+            SampleEntry::Av01(av01) => av01.box_size(),
+            SampleEntry::Mp4a(mp4a) => mp4a.box_size(),
+            SampleEntry::Opus(opus) => opus.box_size(),
+            SampleEntry::Flac(flac) => flac.box_size(),
+            SampleEntry::Ac3(ac3) => ac3.box_size(),
+            SampleEntry::Ec3(ec3) => ec3.box_size(),
+            SampleEntry::Pcm(pcm) => pcm.box_size(),
+            SampleEntry::Twos(twos) => twos.box_size(),
+            SampleEntry::Lpcm(lpcm) => lpcm.box_size(),
+            SampleEntry::Alac(alac) => alac.box_size(),
+            SampleEntry::Tx3g(tx3g) => tx3g.box_size(),
+            SampleEntry::Wvtt(wvtt) => wvtt.box_size(),
+            SampleEntry::Stpp(stpp) => stpp.box_size(),
+            SampleEntry::C608(c608) => c608.box_size(),
+            SampleEntry::Encv(encv) => encv.box_size(),
+            SampleEntry::Unknown { data, .. } => HEADER_SIZE + data.len() as u64,
+        }
+    }
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub vp09: Option<Vp09Box>,
+    /// Protection scheme of an `encv`/`enca` entry.
+    pub fn sinf(&self) -> Option<&SinfBox> {
+        match self {
+            SampleEntry::Encv(encv) => Some(&encv.sinf),
+            _ => None,
+        }
+    }
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub mp4a: Option<Mp4aBox>,
+    pub(crate) fn read<'a>(bx: &mut BoxReader<'a, impl Reader<'a>>) -> Result<Self> {
+        Ok(match bx.kind {
+            BoxType::Avc1Box => SampleEntry::Avc1(read_entry(bx)?),
+            BoxType::Hev1Box | BoxType::Hvc1Box => SampleEntry::Hev1(read_entry(bx)?),
+            BoxType::Vp09Box => SampleEntry::Vp09(read_entry(bx)?),
+            BoxType::Av01Box => SampleEntry::Av01(read_entry(bx)?),
+            BoxType::Mp4aBox => SampleEntry::Mp4a(read_entry(bx)?),
+            BoxType::OpusBox => SampleEntry::Opus(read_entry(bx)?),
+            BoxType::FlacBox => SampleEntry::Flac(read_entry(bx)?),
+            BoxType::Ac3Box => SampleEntry::Ac3(read_entry(bx)?),
+            BoxType::Ec3Box => SampleEntry::Ec3(read_entry(bx)?),
+            BoxType::IpcmBox | BoxType::FpcmBox => SampleEntry::Pcm(read_entry(bx)?),
+            BoxType::TwosBox | BoxType::SowtBox => SampleEntry::Twos(read_entry(bx)?),
+            BoxType::LpcmBox => SampleEntry::Lpcm(read_entry(bx)?),
+            BoxType::AlacBox => SampleEntry::Alac(read_entry(bx)?),
+            BoxType::Tx3gBox => SampleEntry::Tx3g(read_entry(bx)?),
+            BoxType::WvttBox => SampleEntry::Wvtt(read_entry(bx)?),
+            BoxType::StppBox => SampleEntry::Stpp(read_entry(bx)?),
+            BoxType::C608Box => SampleEntry::C608(read_entry(bx)?),
+            kind @ (BoxType::EncvBox | BoxType::EncaBox) => {
+                SampleEntry::Encv(EncvBox::read_entry(kind, &mut bx.inner)?)
+            }
+            kind => SampleEntry::Unknown {
+                kind,
+                data: bx.inner.collect_remaining(),
+            },
+        })
+    }
+}
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub tx3g: Option<Tx3gBox>,
+/// Like [`BoxReader::read`], but first checks that the fixed fields of the
+/// entry are there, as the entries read those without bounds checks.
+fn read_entry<'a, T: Mp4Box + BlockReader>(bx: &mut BoxReader<'a, impl Reader<'a>>) -> Result<T> {
+    if bx.inner.remaining() < T::size_hint() {
+        return Err(BoxError::InvalidData("sample entry is too short"));
+    }
+
+    bx.read()
+}
+
+impl<W: Write> WriteBox<&mut W> for SampleEntry {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        match self {
+            SampleEntry::Avc1(avc1) => avc1.write_box(writer),
+            SampleEntry::Hev1(hev1) => hev1.write_box(writer),
+            SampleEntry::Vp09(vp09) => vp09.write_box(writer),
+            SampleEntry::Av01(av01) => av01.write_box(writer),
+            SampleEntry::Mp4a(mp4a) => mp4a.write_box(writer),
+            SampleEntry::Opus(opus) => opus.write_box(writer),
+            SampleEntry::Flac(flac) => flac.write_box(writer),
+            SampleEntry::Ac3(ac3) => ac3.write_box(writer),
+            SampleEntry::Ec3(ec3) => ec3.write_box(writer),
+            SampleEntry::Pcm(pcm) => pcm.write_box(writer),
+            SampleEntry::Twos(twos) => twos.write_box(writer),
+            SampleEntry::Lpcm(lpcm) => lpcm.write_box(writer),
+            SampleEntry::Alac(alac) => alac.write_box(writer),
+            SampleEntry::Tx3g(tx3g) => tx3g.write_box(writer),
+            SampleEntry::Wvtt(wvtt) => wvtt.write_box(writer),
+            SampleEntry::Stpp(stpp) => stpp.write_box(writer),
+            SampleEntry::C608(c608) => c608.write_box(writer),
+            SampleEntry::Encv(encv) => encv.write_box(writer),
+            SampleEntry::Unknown { kind, data } => {
+                let size = self.box_size();
+                BoxHeader::new(*kind, size).write(writer)?;
+                writer.write_all(data)?;
+                Ok(size)
+            }
+        }
+    }
 }
 
 impl StsdBox {
@@ -33,20 +230,179 @@ impl StsdBox {
     }
 
     pub fn get_size(&self) -> u64 {
-        let mut size = HEADER_SIZE + HEADER_EXT_SIZE + 4;
-        if let Some(ref avc1) = self.avc1 {
-            size += avc1.box_size();
-        } else if let Some(ref hev1) = self.hev1 {
-            size += hev1.box_size();
-        } else if let Some(ref vp09) = self.vp09 {
-            size += vp09.box_size();
-        } else if let Some(ref mp4a) = self.mp4a {
-            size += mp4a.box_size();
-        } else if let Some(ref tx3g) = self.tx3g {
-            size += tx3g.box_size();
-        }
+        HEADER_SIZE + HEADER_EXT_SIZE + 4 + self.entries.iter().map(|x| x.box_size()).sum::<u64>()
+    }
 
-        size
+    /// Sample entry for a 1-based `sample_description_index`.
+    pub fn entry(&self, sample_description_index: u32) -> Option<&SampleEntry> {
+        self.entries
+            .get((sample_description_index as usize).checked_sub(1)?)
+    }
+
+    /// First `avc1` entry, in place of the `avc1` field before 0.15.
+    pub fn avc1(&self) -> Option<&Avc1Box> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::Avc1(avc1) => Some(avc1),
+            _ => None,
+        })
+    }
+
+    pub fn avc1_mut(&mut self) -> Option<&mut Avc1Box> {
+        self.entries.iter_mut().find_map(|x| match x {
+            SampleEntry::Avc1(avc1) => Some(avc1),
+            _ => None,
+        })
+    }
+
+    /// First `hev1` entry, in place of the `hev1` field before 0.15.
+    pub fn hev1(&self) -> Option<&Hev1Box> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::Hev1(hev1) => Some(hev1),
+            _ => None,
+        })
+    }
+
+    pub fn hev1_mut(&mut self) -> Option<&mut Hev1Box> {
+        self.entries.iter_mut().find_map(|x| match x {
+            SampleEntry::Hev1(hev1) => Some(hev1),
+            _ => None,
+        })
+    }
+
+    /// First `vp09` entry, in place of the `vp09` field before 0.15.
+    pub fn vp09(&self) -> Option<&Vp09Box> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::Vp09(vp09) => Some(vp09),
+            _ => None,
+        })
+    }
+
+    pub fn vp09_mut(&mut self) -> Option<&mut Vp09Box> {
+        self.entries.iter_mut().find_map(|x| match x {
+            SampleEntry::Vp09(vp09) => Some(vp09),
+            _ => None,
+        })
+    }
+
+    pub fn av01(&self) -> Option<&Av01Box> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::Av01(av01) => Some(av01),
+            _ => None,
+        })
+    }
+
+    /// First `mp4a` entry, in place of the `mp4a` field before 0.15.
+    pub fn mp4a(&self) -> Option<&Mp4aBox> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::Mp4a(mp4a) => Some(mp4a),
+            _ => None,
+        })
+    }
+
+    pub fn mp4a_mut(&mut self) -> Option<&mut Mp4aBox> {
+        self.entries.iter_mut().find_map(|x| match x {
+            SampleEntry::Mp4a(mp4a) => Some(mp4a),
+            _ => None,
+        })
+    }
+
+    pub fn opus(&self) -> Option<&OpusBox> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::Opus(opus) => Some(opus),
+            _ => None,
+        })
+    }
+
+    pub fn flac(&self) -> Option<&FlacBox> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::Flac(flac) => Some(flac),
+            _ => None,
+        })
+    }
+
+    pub fn ac3(&self) -> Option<&Ac3Box> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::Ac3(ac3) => Some(ac3),
+            _ => None,
+        })
+    }
+
+    pub fn ec3(&self) -> Option<&Ec3Box> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::Ec3(ec3) => Some(ec3),
+            _ => None,
+        })
+    }
+
+    pub fn pcm(&self) -> Option<&PcmBox> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::Pcm(pcm) => Some(pcm),
+            _ => None,
+        })
+    }
+
+    pub fn twos(&self) -> Option<&TwosBox> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::Twos(twos) => Some(twos),
+            _ => None,
+        })
+    }
+
+    pub fn lpcm(&self) -> Option<&LpcmBox> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::Lpcm(lpcm) => Some(lpcm),
+            _ => None,
+        })
+    }
+
+    pub fn alac(&self) -> Option<&AlacBox> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::Alac(alac) => Some(alac),
+            _ => None,
+        })
+    }
+
+    /// First `tx3g` entry, in place of the `tx3g` field before 0.15.
+    pub fn tx3g(&self) -> Option<&Tx3gBox> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::Tx3g(tx3g) => Some(tx3g),
+            _ => None,
+        })
+    }
+
+    pub fn tx3g_mut(&mut self) -> Option<&mut Tx3gBox> {
+        self.entries.iter_mut().find_map(|x| match x {
+            SampleEntry::Tx3g(tx3g) => Some(tx3g),
+            _ => None,
+        })
+    }
+
+    pub fn wvtt(&self) -> Option<&WvttBox> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::Wvtt(wvtt) => Some(wvtt),
+            _ => None,
+        })
+    }
+
+    pub fn stpp(&self) -> Option<&StppBox> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::Stpp(stpp) => Some(stpp),
+            _ => None,
+        })
+    }
+
+    pub fn c608(&self) -> Option<&C608Box> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::C608(c608) => Some(c608),
+            _ => None,
+        })
+    }
+
+    pub fn encv(&self) -> Option<&EncvBox> {
+        self.entries.iter().find_map(|x| match x {
+            SampleEntry::Encv(encv) => Some(encv),
+            _ => None,
+        })
     }
 }
 
@@ -62,7 +418,7 @@ impl Mp4Box for StsdBox {
     }
 
     fn summary(&self) -> Result<String> {
-        let s = String::new();
+        let s = format!("entry_count={}", self.entries.len());
         Ok(s)
     }
 }
@@ -71,48 +427,37 @@ impl BlockReader for StsdBox {
     fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
         let (version, flags) = read_box_header_ext(reader);
 
-        reader.get_u32(); // XXX entry_count
+        let entry_count = reader.get_u32();
 
-        let mut avc1 = None;
-        let mut hev1 = None;
-        let mut vp09 = None;
-        let mut mp4a = None;
-        let mut tx3g = None;
+        let mut entries = Vec::new();
+        while entries.len() < entry_count as usize {
+            let Some(mut bx) = reader.get_box()? else {
+                break;
+            };
 
-        while let Some(mut bx) = reader.get_box()? {
-            match bx.kind {
-                BoxType::Avc1Box => {
-                    avc1 = Some(bx.read()?);
-                }
-
-                BoxType::Hev1Box => {
-                    hev1 = Some(bx.read()?);
-                }
+            // keep the entry as is if it cannot be parsed, e.g. a version 2
+            // sound description, rather than failing the whole file
+            let kind = bx.kind;
+            let data = bx.inner.collect_remaining();
+            let mut entry = BoxReader {
+                kind,
+                inner: data.as_slice(),
+                m: PhantomData,
+            };
 
-                BoxType::Vp09Box => {
-                    vp09 = Some(bx.read()?);
+            entries.push(match SampleEntry::read(&mut entry) {
+                Ok(entry) => entry,
+                Err(_err) => {
+                    debug!(r#type = %kind, error = %_err, "unparsed sample entry");
+                    SampleEntry::Unknown { kind, data }
                 }
-
-                BoxType::Mp4aBox => {
-                    mp4a = Some(bx.read()?);
-                }
-
-                BoxType::Tx3gBox => {
-                    tx3g = Some(bx.read()?);
-                }
-
-                _ => {}
-            }
+            });
         }
 
         Ok(StsdBox {
             version,
             flags,
-            avc1,
-            hev1,
-            vp09,
-            mp4a,
-            tx3g,
+            entries,
         })
     }
 
@@ -128,20 +473,131 @@ impl<W: Write> WriteBox<&mut W> for StsdBox {
 
         write_box_header_ext(writer, self.version, self.flags)?;
 
-        writer.write_u32::<BigEndian>(1)?; // entry_count
-
-        if let Some(ref avc1) = self.avc1 {
-            avc1.write_box(writer)?;
-        } else if let Some(ref hev1) = self.hev1 {
-            hev1.write_box(writer)?;
-        } else if let Some(ref vp09) = self.vp09 {
-            vp09.write_box(writer)?;
-        } else if let Some(ref mp4a) = self.mp4a {
-            mp4a.write_box(writer)?;
-        } else if let Some(ref tx3g) = self.tx3g {
-            tx3g.write_box(writer)?;
+        writer.write_u32::<BigEndian>(self.entries.len() as u32)?;
+        for entry in self.entries.iter() {
+            entry.write_box(writer)?;
         }
 
         Ok(size)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_stsd_entries() {
+        let src_box = StsdBox {
+            version: 0,
+            flags: 0,
+            entries: vec![
+                SampleEntry::Mp4a(Mp4aBox::default()),
+                SampleEntry::Unknown {
+                    kind: BoxType::UnknownBox(0x61626364),
+                    data: b"1234".to_vec(),
+                },
+                SampleEntry::Mp4a(Mp4aBox {
+                    samplerate: FixedPointU16::new(44100),
+                    ..Default::default()
+                }),
+            ],
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::StsdBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let mut dst_box = StsdBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert_eq!(dst_box.entry(3), src_box.entries.get(2));
+        assert_eq!(dst_box.entry(0), None);
+        assert_eq!(dst_box.mp4a(), Some(&Mp4aBox::default()));
+        assert_eq!(dst_box.avc1(), None);
+
+        dst_box.mp4a_mut().unwrap().channelcount = 6;
+        assert_eq!(dst_box.mp4a().unwrap().channelcount, 6);
+    }
+
+    #[test]
+    fn test_stsd_unsupported_version() {
+        let mut data = Vec::new();
+        TwosBox {
+            kind: BoxType::SowtBox,
+            ..Default::default()
+        }
+        .write_box(&mut data)
+        .unwrap();
+        data.drain(..HEADER_SIZE as usize);
+        // sound description version 2
+        data[8..10].copy_from_slice(&2u16.to_be_bytes());
+
+        let src_box = StsdBox {
+            version: 0,
+            flags: 0,
+            entries: vec![SampleEntry::Unknown {
+                kind: BoxType::SowtBox,
+                data,
+            }],
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+
+        let dst_box = StsdBox::read_block(&mut &buf[HEADER_SIZE as usize..]).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[test]
+    fn test_stsd_invalid_entry() {
+        // an `avc1` too short to hold its `avcC`
+        let src_box = StsdBox {
+            version: 0,
+            flags: 0,
+            entries: vec![
+                SampleEntry::Unknown {
+                    kind: BoxType::Avc1Box,
+                    data: vec![0; 78],
+                },
+                SampleEntry::Mp4a(Mp4aBox::default()),
+            ],
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+
+        let dst_box = StsdBox::read_block(&mut &buf[HEADER_SIZE as usize..]).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[test]
+    fn test_stsd_truncated_entry() {
+        for kind in [
+            BoxType::Avc1Box,
+            BoxType::Hvc1Box,
+            BoxType::Mp4aBox,
+            BoxType::SowtBox,
+            BoxType::LpcmBox,
+            BoxType::Tx3gBox,
+            BoxType::StppBox,
+            BoxType::EncaBox,
+        ] {
+            let src_box = StsdBox {
+                version: 0,
+                flags: 0,
+                entries: vec![SampleEntry::Unknown {
+                    kind,
+                    data: vec![0; 6],
+                }],
+            };
+            let mut buf = Vec::new();
+            src_box.write_box(&mut buf).unwrap();
+
+            let dst_box = StsdBox::read_block(&mut &buf[HEADER_SIZE as usize..]).unwrap();
+            assert_eq!(src_box, dst_box);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// Segment type box, ISO/IEC 14496-12 section 8.16.2. Laid out like `ftyp`,
+/// at the start of a media segment.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct StypBox {
+    pub major_brand: FourCC,
+    pub minor_version: u32,
+    pub compatible_brands: Vec<FourCC>,
+}
+
+impl StypBox {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::StypBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        HEADER_SIZE + 8 + (4 * self.compatible_brands.len() as u64)
+    }
+}
+
+impl Mp4Box for StypBox {
+    const TYPE: BoxType = BoxType::StypBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let compatible_brands: Vec<String> = self
+            .compatible_brands
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        let s = format!(
+            "major_brand={} minor_version={} compatible_brands={}",
+            self.major_brand,
+            self.minor_version,
+            compatible_brands.join("-")
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for StypBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let major_brand = reader.get_u32().into();
+        let minor_version = reader.get_u32();
+
+        let brand_count = reader.remaining() / 4;
+        let mut compatible_brands = Vec::with_capacity(brand_count);
+        for _ in 0..brand_count {
+            compatible_brands.push(reader.get_u32().into());
+        }
+
+        Ok(StypBox {
+            major_brand,
+            minor_version,
+            compatible_brands,
+        })
+    }
+
+    fn size_hint() -> usize {
+        8
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for StypBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        writer.write_u32::<BigEndian>((&self.major_brand).into())?;
+        writer.write_u32::<BigEndian>(self.minor_version)?;
+        for brand in self.compatible_brands.iter() {
+            writer.write_u32::<BigEndian>(brand.into())?;
+        }
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_styp() {
+        let src_box = StypBox {
+            major_brand: str::parse("msdh").unwrap(),
+            minor_version: 0,
+            compatible_brands: vec![
+                str::parse("msdh").unwrap(),
+                str::parse("msix").unwrap(),
+                str::parse("cmfs").unwrap(),
+            ],
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::StypBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = StypBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+}
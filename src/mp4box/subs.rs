@@ -0,0 +1,243 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// Sub-sample information box, ISO/IEC 14496-12 section 8.7.7.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct SubsBox {
+    pub version: u8,
+    pub flags: u32,
+
+    #[serde(skip_serializing)]
+    pub entries: Vec<SubsEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct SubsEntry {
+    /// Difference to the sample number of the previous entry, the first
+    /// entry counts from the first sample of the track or fragment.
+    pub sample_delta: u32,
+    pub subsamples: Vec<Subsample>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct Subsample {
+    pub size: u32,
+    pub priority: u8,
+    pub discardable: bool,
+    pub codec_specific_parameters: u32,
+}
+
+impl SubsBox {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::SubsBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let subsample_size = if self.version == 1 { 10 } else { 8 };
+        let mut size = HEADER_SIZE + HEADER_EXT_SIZE + 4;
+        for entry in self.entries.iter() {
+            size += 6 + subsample_size * entry.subsamples.len() as u64;
+        }
+        size
+    }
+
+    /// Sub-samples keyed by 0-based sample index, relative to the first
+    /// sample the box applies to.
+    pub fn samples(&self) -> impl Iterator<Item = (usize, &[Subsample])> + '_ {
+        self.entries.iter().scan(0usize, |sample_number, entry| {
+            *sample_number += entry.sample_delta as usize;
+            Some((sample_number.saturating_sub(1), entry.subsamples.as_slice()))
+        })
+    }
+}
+
+impl Mp4Box for SubsBox {
+    const TYPE: BoxType = BoxType::SubsBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!("entries={}", self.entries.len());
+        Ok(s)
+    }
+}
+
+impl BlockReader for SubsBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let (version, flags) = read_box_header_ext(reader);
+
+        let entry_count = reader.get_u32();
+        if entry_count as usize > reader.remaining() / 6 {
+            return Err(BoxError::InvalidData(
+                "subs entry_count indicates more entries than could fit in the box",
+            ));
+        }
+
+        let subsample_size = if version == 1 { 10 } else { 8 };
+        let mut entries = Vec::with_capacity(entry_count as usize);
+        for _ in 0..entry_count {
+            let sample_delta = reader.try_get_u32()?;
+            let subsample_count = reader.try_get_u16()?;
+            if subsample_count as usize * subsample_size > reader.remaining() {
+                return Err(BoxError::InvalidData(
+                    "subs subsample_count indicates more entries than could fit in the box",
+                ));
+            }
+
+            let mut subsamples = Vec::with_capacity(subsample_count as usize);
+            for _ in 0..subsample_count {
+                subsamples.push(Subsample {
+                    size: if version == 1 {
+                        reader.get_u32()
+                    } else {
+                        reader.get_u16() as u32
+                    },
+                    priority: reader.get_u8(),
+                    discardable: reader.get_u8() != 0,
+                    codec_specific_parameters: reader.get_u32(),
+                });
+            }
+
+            entries.push(SubsEntry {
+                sample_delta,
+                subsamples,
+            });
+        }
+
+        Ok(SubsBox {
+            version,
+            flags,
+            entries,
+        })
+    }
+
+    fn size_hint() -> usize {
+        8
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for SubsBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        for entry in self.entries.iter() {
+            if entry.subsamples.len() > u16::MAX as usize {
+                return Err(BoxError::InvalidData("subs: too many subsamples"));
+            }
+
+            if self.version == 0 && entry.subsamples.iter().any(|x| x.size > u16::MAX as u32) {
+                return Err(BoxError::InvalidData(
+                    "subs: subsample size does not fit in version 0",
+                ));
+            }
+        }
+
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.entries.len() as u32)?;
+        for entry in self.entries.iter() {
+            writer.write_u32::<BigEndian>(entry.sample_delta)?;
+            writer.write_u16::<BigEndian>(entry.subsamples.len() as u16)?;
+            for subsample in entry.subsamples.iter() {
+                if self.version == 1 {
+                    writer.write_u32::<BigEndian>(subsample.size)?;
+                } else {
+                    writer.write_u16::<BigEndian>(subsample.size as u16)?;
+                }
+                writer.write_u8(subsample.priority)?;
+                writer.write_u8(subsample.discardable as u8)?;
+                writer.write_u32::<BigEndian>(subsample.codec_specific_parameters)?;
+            }
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_subs() {
+        let src_box = SubsBox {
+            version: 1,
+            flags: 0,
+            entries: vec![
+                SubsEntry {
+                    sample_delta: 1,
+                    subsamples: vec![
+                        Subsample {
+                            size: 1200,
+                            ..Default::default()
+                        },
+                        Subsample {
+                            size: 70000,
+                            discardable: true,
+                            ..Default::default()
+                        },
+                    ],
+                },
+                SubsEntry {
+                    sample_delta: 2,
+                    subsamples: vec![Subsample {
+                        size: 800,
+                        priority: 1,
+                        ..Default::default()
+                    }],
+                },
+            ],
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::SubsBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = SubsBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+
+        let samples: Vec<_> = dst_box.samples().map(|(idx, x)| (idx, x.len())).collect();
+        assert_eq!(samples, [(0, 2), (2, 1)]);
+    }
+
+    #[test]
+    fn test_subs_v0_size_overflow() {
+        let mut src_box = SubsBox {
+            version: 0,
+            flags: 0,
+            entries: vec![SubsEntry {
+                sample_delta: 1,
+                subsamples: vec![Subsample {
+                    size: 70000,
+                    ..Default::default()
+                }],
+            }],
+        };
+
+        let mut buf = Vec::new();
+        assert!(matches!(
+            src_box.write_box(&mut buf),
+            Err(BoxError::InvalidData(_))
+        ));
+        assert!(buf.is_empty());
+
+        src_box.version = 1;
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+    }
+}
@@ -0,0 +1,161 @@
+use byteorder::WriteBytesExt;
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// Track encryption box, ISO/IEC 23001-7 section 8.2. Holds the defaults for
+/// the samples described by the sample entry.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct TencBox {
+    pub version: u8,
+    pub flags: u32,
+
+    /// Pattern encryption (`cens`/`cbcs`), version 1 only: encrypted and
+    /// skipped 16 byte blocks of each pattern.
+    pub default_crypt_byte_block: u8,
+    pub default_skip_byte_block: u8,
+    pub default_is_protected: u8,
+
+    /// 0, 8 or 16. Zero with a protected track means a constant IV.
+    pub default_per_sample_iv_size: u8,
+    pub default_kid: [u8; 16],
+    pub default_constant_iv: Option<Vec<u8>>,
+}
+
+impl TencBox {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::TencBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + HEADER_EXT_SIZE + 20;
+        if let Some(ref iv) = self.default_constant_iv {
+            size += 1 + iv.len() as u64;
+        }
+        size
+    }
+}
+
+impl Mp4Box for TencBox {
+    const TYPE: BoxType = BoxType::TencBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let kid: String = self
+            .default_kid
+            .iter()
+            .map(|x| format!("{x:02x}"))
+            .collect();
+        let s = format!(
+            "is_protected={} per_sample_iv_size={} kid={}",
+            self.default_is_protected, self.default_per_sample_iv_size, kid
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for TencBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let (version, flags) = read_box_header_ext(reader);
+
+        reader.get_u8(); // reserved
+        let pattern = reader.get_u8();
+        let (default_crypt_byte_block, default_skip_byte_block) = if version > 0 {
+            (pattern >> 4, pattern & 0x0F)
+        } else {
+            (0, 0)
+        };
+
+        let default_is_protected = reader.get_u8();
+        let default_per_sample_iv_size = reader.get_u8();
+
+        let mut default_kid = [0; 16];
+        reader.copy_to_slice(&mut default_kid)?;
+
+        let default_constant_iv = if default_is_protected == 1 && default_per_sample_iv_size == 0 {
+            let size = reader.try_get_u8()?;
+            Some(reader.collect(size as usize)?)
+        } else {
+            None
+        };
+
+        Ok(TencBox {
+            version,
+            flags,
+            default_crypt_byte_block,
+            default_skip_byte_block,
+            default_is_protected,
+            default_per_sample_iv_size,
+            default_kid,
+            default_constant_iv,
+        })
+    }
+
+    fn size_hint() -> usize {
+        24
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for TencBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u8(0)?; // reserved
+        if self.version > 0 {
+            writer.write_u8(
+                self.default_crypt_byte_block << 4 | self.default_skip_byte_block & 0x0F,
+            )?;
+        } else {
+            writer.write_u8(0)?; // reserved
+        }
+        writer.write_u8(self.default_is_protected)?;
+        writer.write_u8(self.default_per_sample_iv_size)?;
+        writer.write_all(&self.default_kid)?;
+        if let Some(ref iv) = self.default_constant_iv {
+            writer.write_u8(iv.len() as u8)?;
+            writer.write_all(iv)?;
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_tenc() {
+        let src_box = TencBox {
+            version: 0,
+            flags: 0,
+            default_is_protected: 1,
+            default_per_sample_iv_size: 8,
+            default_kid: *b"0123456789abcdef",
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::TencBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = TencBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+}
@@ -0,0 +1,633 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use bytes::Bytes;
+use serde::Serialize;
+use std::convert::TryFrom;
+use std::fmt::Write as _;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// Decoded `tx3g` sample, 3GPP TS 26.245 section 5.17: the text followed by
+/// its modifier boxes. Character offsets count characters, not bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct TextSample {
+    pub text: String,
+
+    /// `styl`, non-overlapping and ordered by `start_char`.
+    pub styles: Vec<StyleRecord>,
+
+    /// `hlit`
+    pub highlight: Option<TextRange>,
+
+    /// `hclr`
+    pub highlight_color: Option<RgbaColor>,
+
+    /// `krok`
+    pub karaoke: Option<Karaoke>,
+
+    /// `dlay`, in track timescale units.
+    pub scroll_delay: Option<u32>,
+
+    /// `href`
+    pub hyper_texts: Vec<HyperText>,
+
+    /// `tbox`
+    pub text_box: Option<TextBoxRecord>,
+
+    /// `blnk`
+    pub blinks: Vec<TextRange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct TextRange {
+    pub start_char: u16,
+    pub end_char: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct StyleRecord {
+    pub start_char: u16,
+    pub end_char: u16,
+    pub font_id: u16,
+
+    /// `FACE_*` flags.
+    pub face_style_flags: u8,
+    pub font_size: u8,
+    pub text_color_rgba: RgbaColor,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct Karaoke {
+    pub highlight_start_time: u32,
+    pub entries: Vec<KaraokeEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct KaraokeEntry {
+    pub highlight_end_time: u32,
+    pub start_char: u16,
+    pub end_char: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct HyperText {
+    pub start_char: u16,
+    pub end_char: u16,
+    pub url: String,
+    pub alt: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct TextBoxRecord {
+    pub top: i16,
+    pub left: i16,
+    pub bottom: i16,
+    pub right: i16,
+}
+
+impl StyleRecord {
+    pub const FACE_BOLD: u8 = 1;
+    pub const FACE_ITALIC: u8 = 2;
+    pub const FACE_UNDERLINE: u8 = 4;
+
+    const SIZE: usize = 12;
+
+    fn read<'a>(reader: &mut impl Reader<'a>) -> Self {
+        Self {
+            start_char: reader.get_u16(),
+            end_char: reader.get_u16(),
+            font_id: reader.get_u16(),
+            face_style_flags: reader.get_u8(),
+            font_size: reader.get_u8(),
+            text_color_rgba: RgbaColor {
+                red: reader.get_u8(),
+                green: reader.get_u8(),
+                blue: reader.get_u8(),
+                alpha: reader.get_u8(),
+            },
+        }
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_u16::<BigEndian>(self.start_char)?;
+        writer.write_u16::<BigEndian>(self.end_char)?;
+        writer.write_u16::<BigEndian>(self.font_id)?;
+        writer.write_u8(self.face_style_flags)?;
+        writer.write_u8(self.font_size)?;
+        write_rgba(writer, &self.text_color_rgba)
+    }
+
+    fn tags(&self) -> impl Iterator<Item = &'static str> + '_ {
+        [
+            (Self::FACE_BOLD, "b"),
+            (Self::FACE_ITALIC, "i"),
+            (Self::FACE_UNDERLINE, "u"),
+        ]
+        .iter()
+        .filter(move |(flag, _)| self.face_style_flags & flag != 0)
+        .map(|&(_, tag)| tag)
+    }
+}
+
+fn read_range<'a>(reader: &mut impl Reader<'a>) -> TextRange {
+    TextRange {
+        start_char: reader.get_u16(),
+        end_char: reader.get_u16(),
+    }
+}
+
+fn write_range<W: Write>(writer: &mut W, range: &TextRange) -> Result<()> {
+    writer.write_u16::<BigEndian>(range.start_char)?;
+    writer.write_u16::<BigEndian>(range.end_char)?;
+    Ok(())
+}
+
+fn write_rgba<W: Write>(writer: &mut W, color: &RgbaColor) -> Result<()> {
+    writer.write_u8(color.red)?;
+    writer.write_u8(color.green)?;
+    writer.write_u8(color.blue)?;
+    writer.write_u8(color.alpha)?;
+    Ok(())
+}
+
+fn expect<'a>(reader: &impl Reader<'a>, size: usize) -> Result<()> {
+    if reader.remaining() < size {
+        Err(BoxError::InvalidData("tx3g: truncated modifier box"))
+    } else {
+        Ok(())
+    }
+}
+
+impl TextSample {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Parses a sample. The text is UTF-16 when it starts with a byte order
+    /// mark, UTF-8 otherwise. Unknown modifier boxes are skipped.
+    pub fn decode(mut data: &[u8]) -> Result<Self> {
+        let len = Reader::try_get_u16(&mut data)? as usize;
+        let raw = data.collect(len)?;
+
+        let text = if let [0xFE, 0xFF, rest @ ..] = raw.as_slice() {
+            if rest.len() % 2 != 0 {
+                return Err(BoxError::InvalidData("tx3g: truncated utf-16 text"));
+            }
+
+            let units: Vec<u16> = rest
+                .chunks_exact(2)
+                .map(|x| u16::from_be_bytes([x[0], x[1]]))
+                .collect();
+            String::from_utf16(&units)
+                .map_err(|_| BoxError::InvalidData("tx3g: invalid utf-16 text"))?
+        } else {
+            String::from_utf8(raw).map_err(|_| BoxError::InvalidData("tx3g: invalid utf-8 text"))?
+        };
+
+        let mut sample = TextSample::new(text);
+
+        while let Some(mut bx) = data.get_box()? {
+            let reader = &mut bx.inner;
+
+            match bx.kind {
+                BoxType::StylBox => {
+                    let count = reader.try_get_u16()? as usize;
+                    expect(reader, count * StyleRecord::SIZE)?;
+                    sample.styles = (0..count).map(|_| StyleRecord::read(reader)).collect();
+                }
+
+                BoxType::HlitBox => {
+                    expect(reader, 4)?;
+                    sample.highlight = Some(read_range(reader));
+                }
+
+                BoxType::HclrBox => {
+                    expect(reader, 4)?;
+                    sample.highlight_color = Some(RgbaColor {
+                        red: reader.get_u8(),
+                        green: reader.get_u8(),
+                        blue: reader.get_u8(),
+                        alpha: reader.get_u8(),
+                    });
+                }
+
+                BoxType::KrokBox => {
+                    expect(reader, 6)?;
+                    let highlight_start_time = reader.get_u32();
+                    let count = reader.get_u16() as usize;
+                    expect(reader, count * 8)?;
+
+                    let entries = (0..count)
+                        .map(|_| KaraokeEntry {
+                            highlight_end_time: reader.get_u32(),
+                            start_char: reader.get_u16(),
+                            end_char: reader.get_u16(),
+                        })
+                        .collect();
+
+                    sample.karaoke = Some(Karaoke {
+                        highlight_start_time,
+                        entries,
+                    });
+                }
+
+                BoxType::DlayBox => {
+                    sample.scroll_delay = Some(reader.try_get_u32()?);
+                }
+
+                BoxType::HrefBox => {
+                    expect(reader, 5)?;
+                    let range = read_range(reader);
+                    let url_len = reader.get_u8() as usize;
+                    let url = reader.collect(url_len)?;
+                    let alt_len = reader.try_get_u8()? as usize;
+                    let alt = reader.collect(alt_len)?;
+
+                    sample.hyper_texts.push(HyperText {
+                        start_char: range.start_char,
+                        end_char: range.end_char,
+                        url: String::from_utf8_lossy(&url).into_owned(),
+                        alt: String::from_utf8_lossy(&alt).into_owned(),
+                    });
+                }
+
+                BoxType::TboxBox => {
+                    expect(reader, 8)?;
+                    sample.text_box = Some(TextBoxRecord {
+                        top: reader.get_i16(),
+                        left: reader.get_i16(),
+                        bottom: reader.get_i16(),
+                        right: reader.get_i16(),
+                    });
+                }
+
+                BoxType::BlnkBox => {
+                    expect(reader, 4)?;
+                    sample.blinks.push(read_range(reader));
+                }
+
+                _ => {}
+            }
+        }
+
+        Ok(sample)
+    }
+
+    /// Builds the sample payload, always writing the text as UTF-8.
+    pub fn encode(&self) -> Result<Bytes> {
+        let text_len = u16::try_from(self.text.len())
+            .map_err(|_| BoxError::InvalidData("tx3g: text too long"))?;
+
+        let mut buf = Vec::new();
+        buf.write_u16::<BigEndian>(text_len)?;
+        buf.write_all(self.text.as_bytes())?;
+
+        if !self.styles.is_empty() {
+            let size = HEADER_SIZE + 2 + (StyleRecord::SIZE * self.styles.len()) as u64;
+            BoxHeader::new(BoxType::StylBox, size).write(&mut buf)?;
+            buf.write_u16::<BigEndian>(self.styles.len() as u16)?;
+            for style in self.styles.iter() {
+                style.write(&mut buf)?;
+            }
+        }
+
+        if let Some(ref highlight) = self.highlight {
+            BoxHeader::new(BoxType::HlitBox, HEADER_SIZE + 4).write(&mut buf)?;
+            write_range(&mut buf, highlight)?;
+        }
+
+        if let Some(ref color) = self.highlight_color {
+            BoxHeader::new(BoxType::HclrBox, HEADER_SIZE + 4).write(&mut buf)?;
+            write_rgba(&mut buf, color)?;
+        }
+
+        if let Some(ref karaoke) = self.karaoke {
+            let size = HEADER_SIZE + 6 + 8 * karaoke.entries.len() as u64;
+            BoxHeader::new(BoxType::KrokBox, size).write(&mut buf)?;
+            buf.write_u32::<BigEndian>(karaoke.highlight_start_time)?;
+            buf.write_u16::<BigEndian>(karaoke.entries.len() as u16)?;
+            for entry in karaoke.entries.iter() {
+                buf.write_u32::<BigEndian>(entry.highlight_end_time)?;
+                buf.write_u16::<BigEndian>(entry.start_char)?;
+                buf.write_u16::<BigEndian>(entry.end_char)?;
+            }
+        }
+
+        if let Some(scroll_delay) = self.scroll_delay {
+            BoxHeader::new(BoxType::DlayBox, HEADER_SIZE + 4).write(&mut buf)?;
+            buf.write_u32::<BigEndian>(scroll_delay)?;
+        }
+
+        for href in self.hyper_texts.iter() {
+            let url_len = u8::try_from(href.url.len())
+                .map_err(|_| BoxError::InvalidData("tx3g: href url too long"))?;
+            let alt_len = u8::try_from(href.alt.len())
+                .map_err(|_| BoxError::InvalidData("tx3g: href alt too long"))?;
+
+            let size = HEADER_SIZE + 6 + url_len as u64 + alt_len as u64;
+            BoxHeader::new(BoxType::HrefBox, size).write(&mut buf)?;
+            buf.write_u16::<BigEndian>(href.start_char)?;
+            buf.write_u16::<BigEndian>(href.end_char)?;
+            buf.write_u8(url_len)?;
+            buf.write_all(href.url.as_bytes())?;
+            buf.write_u8(alt_len)?;
+            buf.write_all(href.alt.as_bytes())?;
+        }
+
+        if let Some(ref text_box) = self.text_box {
+            BoxHeader::new(BoxType::TboxBox, HEADER_SIZE + 8).write(&mut buf)?;
+            buf.write_i16::<BigEndian>(text_box.top)?;
+            buf.write_i16::<BigEndian>(text_box.left)?;
+            buf.write_i16::<BigEndian>(text_box.bottom)?;
+            buf.write_i16::<BigEndian>(text_box.right)?;
+        }
+
+        for blink in self.blinks.iter() {
+            BoxHeader::new(BoxType::BlnkBox, HEADER_SIZE + 4).write(&mut buf)?;
+            write_range(&mut buf, blink)?;
+        }
+
+        Ok(buf.into())
+    }
+
+    /// The text with bold, italic and underline styles as `<b>`, `<i>` and
+    /// `<u>` tags, which both SRT and WebVTT understand. Blank lines are
+    /// dropped, as either format would end the cue there.
+    fn markup(&self, escape: bool) -> String {
+        let chars: Vec<char> = self.text.chars().collect();
+        let len = chars.len();
+        let mut out = String::with_capacity(self.text.len());
+
+        for idx in 0..=len {
+            for style in self.styles.iter().rev() {
+                let end = (style.end_char as usize).min(len);
+                if end == idx && (style.start_char as usize) < end {
+                    for tag in style.tags().collect::<Vec<_>>().into_iter().rev() {
+                        let _ = write!(out, "</{tag}>");
+                    }
+                }
+            }
+
+            let Some(&c) = chars.get(idx) else {
+                break;
+            };
+
+            for style in self.styles.iter() {
+                if style.start_char as usize == idx && idx < (style.end_char as usize).min(len) {
+                    for tag in style.tags() {
+                        let _ = write!(out, "<{tag}>");
+                    }
+                }
+            }
+
+            match c {
+                '&' if escape => out.push_str("&amp;"),
+                '<' if escape => out.push_str("&lt;"),
+                '>' if escape => out.push_str("&gt;"),
+                '\r' => {}
+                c => out.push(c),
+            }
+        }
+
+        out.split('\n')
+            .filter(|x| !x.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// A text sample with its presentation interval in track timescale units.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TextCue {
+    pub start_time: u64,
+    pub end_time: u64,
+    pub sample: TextSample,
+}
+
+impl TextCue {
+    /// Decodes the samples of a `tx3g` track. Empty samples only clear the
+    /// screen and produce no cue.
+    pub fn from_samples<'a>(samples: impl IntoIterator<Item = &'a Mp4Sample>) -> Result<Vec<Self>> {
+        let mut cues = Vec::new();
+
+        for sample in samples {
+            let text = TextSample::decode(&sample.bytes)?;
+            if text.text.is_empty() {
+                continue;
+            }
+
+            cues.push(TextCue {
+                start_time: sample.start_time,
+                end_time: sample.start_time + sample.duration as u64,
+                sample: text,
+            });
+        }
+
+        Ok(cues)
+    }
+
+    /// Contiguous `tx3g` samples starting at zero, with empty samples for the
+    /// gaps between cues. Cues must not overlap.
+    pub fn to_samples(cues: &[TextCue]) -> Result<Vec<Mp4Sample>> {
+        let mut cues: Vec<&TextCue> = cues.iter().collect();
+        cues.sort_by_key(|x| x.start_time);
+
+        let mut samples = Vec::with_capacity(cues.len() * 2);
+        let mut time = 0;
+
+        let mut push = |start: u64, end: u64, bytes: Bytes| -> Result<()> {
+            samples.push(Mp4Sample {
+                start_time: start,
+                duration: u32::try_from(end - start)
+                    .map_err(|_| BoxError::InvalidData("tx3g: cue too long"))?,
+                rendering_offset: 0,
+                is_sync: true,
+                bytes,
+            });
+            Ok(())
+        };
+
+        for cue in cues {
+            if cue.start_time < time || cue.end_time < cue.start_time {
+                return Err(BoxError::InvalidData("tx3g: overlapping cues"));
+            }
+
+            if cue.start_time > time {
+                push(time, cue.start_time, TextSample::default().encode()?)?;
+            }
+
+            push(cue.start_time, cue.end_time, cue.sample.encode()?)?;
+            time = cue.end_time;
+        }
+
+        Ok(samples)
+    }
+
+    /// SubRip document of the cues.
+    pub fn to_srt(cues: &[TextCue], timescale: u32) -> String {
+        let mut out = String::new();
+
+        for (idx, cue) in cues.iter().enumerate() {
+            let _ = write!(
+                out,
+                "{}\n{} --> {}\n{}\n\n",
+                idx + 1,
+                format_time(cue.start_time, timescale, ','),
+                format_time(cue.end_time, timescale, ','),
+                cue.sample.markup(false)
+            );
+        }
+
+        out
+    }
+
+    /// WebVTT document of the cues.
+    pub fn to_webvtt(cues: &[TextCue], timescale: u32) -> String {
+        let mut out = String::from("WEBVTT\n\n");
+
+        for cue in cues {
+            let _ = write!(
+                out,
+                "{} --> {}\n{}\n\n",
+                format_time(cue.start_time, timescale, '.'),
+                format_time(cue.end_time, timescale, '.'),
+                cue.sample.markup(true)
+            );
+        }
+
+        out
+    }
+}
+
+fn format_time(time: u64, timescale: u32, separator: char) -> String {
+    let ms = time as u128 * 1000 / timescale.max(1) as u128;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        separator,
+        ms % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_sample() {
+        let sample = TextSample {
+            text: String::from("Hello <world>"),
+            styles: vec![StyleRecord {
+                start_char: 0,
+                end_char: 5,
+                font_id: 1,
+                face_style_flags: StyleRecord::FACE_BOLD | StyleRecord::FACE_ITALIC,
+                font_size: 18,
+                text_color_rgba: RgbaColor {
+                    red: 255,
+                    green: 255,
+                    blue: 255,
+                    alpha: 255,
+                },
+            }],
+            highlight: Some(TextRange {
+                start_char: 6,
+                end_char: 13,
+            }),
+            karaoke: Some(Karaoke {
+                highlight_start_time: 0,
+                entries: vec![KaraokeEntry {
+                    highlight_end_time: 500,
+                    start_char: 0,
+                    end_char: 5,
+                }],
+            }),
+            scroll_delay: Some(100),
+            hyper_texts: vec![HyperText {
+                start_char: 6,
+                end_char: 13,
+                url: String::from("https://example.com"),
+                alt: String::from("example"),
+            }],
+            text_box: Some(TextBoxRecord {
+                top: 0,
+                left: 0,
+                bottom: 60,
+                right: 320,
+            }),
+            blinks: vec![TextRange {
+                start_char: 0,
+                end_char: 1,
+            }],
+            ..Default::default()
+        };
+
+        let bytes = sample.encode().unwrap();
+        assert_eq!(TextSample::decode(&bytes).unwrap(), sample);
+        assert_eq!(sample.markup(true), "<b><i>Hello</i></b> &lt;world&gt;");
+    }
+
+    #[test]
+    fn test_text_sample_utf16() {
+        let data = [0, 6, 0xFE, 0xFF, 0, b'h', 0, b'i'];
+        assert_eq!(TextSample::decode(&data).unwrap(), TextSample::new("hi"));
+
+        let data = [0, 5, 0xFE, 0xFF, 0, b'h', 0];
+        assert!(TextSample::decode(&data).is_err());
+    }
+
+    #[test]
+    fn test_text_cues() {
+        let cues = vec![
+            TextCue {
+                start_time: 1000,
+                end_time: 2500,
+                sample: TextSample::new("first"),
+            },
+            TextCue {
+                start_time: 2500,
+                end_time: 4000,
+                sample: TextSample::new("second\nline"),
+            },
+        ];
+
+        let samples = TextCue::to_samples(&cues).unwrap();
+        let spans: Vec<_> = samples.iter().map(|x| (x.start_time, x.duration)).collect();
+        assert_eq!(spans, [(0, 1000), (1000, 1500), (2500, 1500)]);
+        assert_eq!(TextCue::from_samples(&samples).unwrap(), cues);
+
+        assert_eq!(
+            TextCue::to_srt(&cues, 1000),
+            "1\n00:00:01,000 --> 00:00:02,500\nfirst\n\n\
+             2\n00:00:02,500 --> 00:00:04,000\nsecond\nline\n\n"
+        );
+        assert_eq!(
+            TextCue::to_webvtt(&cues, 1000),
+            "WEBVTT\n\n\
+             00:00:01.000 --> 00:00:02.500\nfirst\n\n\
+             00:00:02.500 --> 00:00:04.000\nsecond\nline\n\n"
+        );
+
+        let blank = [TextCue {
+            start_time: 0,
+            end_time: 1000,
+            sample: TextSample::new("\nfirst\n\n \r\nsecond\n"),
+        }];
+        assert_eq!(
+            TextCue::to_srt(&blank, 1000),
+            "1\n00:00:00,000 --> 00:00:01,000\nfirst\nsecond\n\n"
+        );
+        assert_eq!(
+            TextCue::to_webvtt(&blank, 1000),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:01.000\nfirst\nsecond\n\n"
+        );
+
+        let overlapping = [cues[1].clone(), cues[0].clone(), cues[1].clone()];
+        assert!(TextCue::to_samples(&overlapping).is_err());
+    }
+}
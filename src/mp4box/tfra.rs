@@ -0,0 +1,226 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// Track fragment random access box, ISO/IEC 14496-12 section 8.8.10.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct TfraBox {
+    pub version: u8,
+    pub flags: u32,
+    pub track_id: u32,
+
+    /// Sizes in bytes, minus one, of the `traf_number`, `trun_number` and
+    /// `sample_number` of each entry.
+    pub length_size_of_traf_num: u8,
+    pub length_size_of_trun_num: u8,
+    pub length_size_of_sample_num: u8,
+
+    #[serde(skip_serializing)]
+    pub entries: Vec<TfraEntry>,
+}
+
+/// A random access sample. Numbers are 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct TfraEntry {
+    /// Presentation time of the sample, in the track timescale.
+    pub time: u64,
+
+    /// File offset of the `moof` holding the sample.
+    pub moof_offset: u64,
+    pub traf_number: u32,
+    pub trun_number: u32,
+    pub sample_number: u32,
+}
+
+impl TfraBox {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::TfraBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let entry_size = if self.version == 1 { 16 } else { 8 }
+            + self.length_size_of_traf_num as u64
+            + self.length_size_of_trun_num as u64
+            + self.length_size_of_sample_num as u64
+            + 3;
+        HEADER_SIZE + HEADER_EXT_SIZE + 12 + entry_size * self.entries.len() as u64
+    }
+
+    /// The last entry at or before `time`, in the track timescale.
+    pub fn entry_at(&self, time: u64) -> Option<&TfraEntry> {
+        self.entries.iter().take_while(|x| x.time <= time).last()
+    }
+}
+
+impl Mp4Box for TfraBox {
+    const TYPE: BoxType = BoxType::TfraBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!("track_id={} entries={}", self.track_id, self.entries.len());
+        Ok(s)
+    }
+}
+
+fn read_number<'a>(reader: &mut impl Reader<'a>, length_size: u8) -> u32 {
+    match length_size {
+        0 => reader.get_u8() as u32,
+        1 => reader.get_u16() as u32,
+        2 => reader.get_u24(),
+        _ => reader.get_u32(),
+    }
+}
+
+fn write_number<W: Write>(writer: &mut W, length_size: u8, value: u32) -> Result<()> {
+    match length_size {
+        0 => writer.write_u8(value as u8)?,
+        1 => writer.write_u16::<BigEndian>(value as u16)?,
+        2 => writer.write_u24::<BigEndian>(value)?,
+        _ => writer.write_u32::<BigEndian>(value)?,
+    }
+    Ok(())
+}
+
+impl BlockReader for TfraBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let (version, flags) = read_box_header_ext(reader);
+
+        let track_id = reader.get_u32();
+        let sizes = reader.get_u32();
+        let length_size_of_traf_num = (sizes >> 4 & 0x03) as u8;
+        let length_size_of_trun_num = (sizes >> 2 & 0x03) as u8;
+        let length_size_of_sample_num = (sizes & 0x03) as u8;
+
+        let entry_count = reader.get_u32();
+        let entry_size = if version == 1 { 16 } else { 8 }
+            + length_size_of_traf_num as usize
+            + length_size_of_trun_num as usize
+            + length_size_of_sample_num as usize
+            + 3;
+        if entry_count as usize > reader.remaining() / entry_size {
+            return Err(BoxError::InvalidData(
+                "tfra entry_count indicates more entries than could fit in the box",
+            ));
+        }
+
+        let mut entries = Vec::with_capacity(entry_count as usize);
+        for _ in 0..entry_count {
+            let (time, moof_offset) = if version == 1 {
+                (reader.get_u64(), reader.get_u64())
+            } else {
+                (reader.get_u32() as u64, reader.get_u32() as u64)
+            };
+
+            entries.push(TfraEntry {
+                time,
+                moof_offset,
+                traf_number: read_number(reader, length_size_of_traf_num),
+                trun_number: read_number(reader, length_size_of_trun_num),
+                sample_number: read_number(reader, length_size_of_sample_num),
+            });
+        }
+
+        Ok(TfraBox {
+            version,
+            flags,
+            track_id,
+            length_size_of_traf_num,
+            length_size_of_trun_num,
+            length_size_of_sample_num,
+            entries,
+        })
+    }
+
+    fn size_hint() -> usize {
+        16
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for TfraBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.track_id)?;
+        writer.write_u32::<BigEndian>(
+            ((self.length_size_of_traf_num & 0x03) as u32) << 4
+                | ((self.length_size_of_trun_num & 0x03) as u32) << 2
+                | (self.length_size_of_sample_num & 0x03) as u32,
+        )?;
+        writer.write_u32::<BigEndian>(self.entries.len() as u32)?;
+        for entry in self.entries.iter() {
+            if self.version == 1 {
+                writer.write_u64::<BigEndian>(entry.time)?;
+                writer.write_u64::<BigEndian>(entry.moof_offset)?;
+            } else {
+                writer.write_u32::<BigEndian>(entry.time as u32)?;
+                writer.write_u32::<BigEndian>(entry.moof_offset as u32)?;
+            }
+            write_number(writer, self.length_size_of_traf_num, entry.traf_number)?;
+            write_number(writer, self.length_size_of_trun_num, entry.trun_number)?;
+            write_number(writer, self.length_size_of_sample_num, entry.sample_number)?;
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_tfra() {
+        for version in 0..2 {
+            let src_box = TfraBox {
+                version,
+                flags: 0,
+                track_id: 2,
+                length_size_of_traf_num: 0,
+                length_size_of_trun_num: 1,
+                length_size_of_sample_num: 2,
+                entries: vec![
+                    TfraEntry {
+                        time: 0,
+                        moof_offset: 1000,
+                        traf_number: 1,
+                        trun_number: 1,
+                        sample_number: 1,
+                    },
+                    TfraEntry {
+                        time: 90000,
+                        moof_offset: 52000,
+                        traf_number: 2,
+                        trun_number: 300,
+                        sample_number: 70000,
+                    },
+                ],
+            };
+            let mut buf = Vec::new();
+            src_box.write_box(&mut buf).unwrap();
+            assert_eq!(buf.len(), src_box.box_size() as usize);
+
+            let mut reader = buf.as_slice();
+            let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+            assert_eq!(header.kind, BoxType::TfraBox);
+            assert_eq!(src_box.box_size(), header.size);
+
+            let dst_box = TfraBox::read_block(&mut reader).unwrap();
+            assert_eq!(src_box, dst_box);
+            assert_eq!(dst_box.entry_at(89999).unwrap().moof_offset, 1000);
+            assert_eq!(dst_box.entry_at(90000).unwrap().moof_offset, 52000);
+        }
+    }
+}
@@ -2,13 +2,22 @@ use serde::Serialize;
 use std::io::Write;
 
 use crate::mp4box::*;
-use crate::mp4box::{tfdt::TfdtBox, tfhd::TfhdBox, trun::TrunBox};
+use crate::mp4box::{
+    saio::SaioBox, saiz::SaizBox, senc::SencBox, subs::SubsBox, tfdt::TfdtBox, tfhd::TfhdBox,
+    trun::TrunBox,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct TrafBox {
     pub tfhd: TfhdBox,
     pub tfdt: Option<TfdtBox>,
     pub trun: Option<TrunBox>,
+    pub subs: Option<SubsBox>,
+
+    /// Encryption parameters of the samples of the fragment.
+    pub senc: Option<SencBox>,
+    pub saiz: Option<SaizBox>,
+    pub saio: Option<SaioBox>,
 }
 
 impl TrafBox {
@@ -21,6 +30,18 @@ impl TrafBox {
         if let Some(ref trun) = self.trun {
             size += trun.box_size();
         }
+        if let Some(ref subs) = self.subs {
+            size += subs.box_size();
+        }
+        if let Some(ref senc) = self.senc {
+            size += senc.box_size();
+        }
+        if let Some(ref saiz) = self.saiz {
+            size += saiz.box_size();
+        }
+        if let Some(ref saio) = self.saio {
+            size += saio.box_size();
+        }
         size
     }
 }
@@ -44,7 +65,47 @@ impl Mp4Box for TrafBox {
 
 impl BlockReader for TrafBox {
     fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
-        let (tfhd, tfdt, trun) = reader.try_find_box3()?;
+        let mut tfhd = None;
+        let mut tfdt = None;
+        let mut trun = None;
+        let mut subs = None;
+        let mut senc = None;
+        let mut saiz = None;
+        let mut saio = None;
+
+        while let Some(mut bx) = reader.get_box()? {
+            match bx.kind {
+                BoxType::TfhdBox => {
+                    tfhd = Some(bx.read()?);
+                }
+
+                BoxType::TfdtBox => {
+                    tfdt = Some(bx.read()?);
+                }
+
+                BoxType::TrunBox => {
+                    trun = Some(bx.read()?);
+                }
+
+                BoxType::SubsBox => {
+                    subs = Some(bx.read()?);
+                }
+
+                BoxType::SencBox => {
+                    senc = Some(bx.read()?);
+                }
+
+                BoxType::SaizBox => {
+                    saiz = Some(bx.read()?);
+                }
+
+                BoxType::SaioBox => {
+                    saio = Some(bx.read()?);
+                }
+
+                _ => continue,
+            }
+        }
 
         if tfhd.is_none() {
             return Err(BoxError::BoxNotFound(BoxType::TfhdBox));
@@ -54,6 +115,10 @@ impl BlockReader for TrafBox {
             tfhd: tfhd.unwrap(),
             tfdt,
             trun,
+            subs,
+            senc,
+            saiz,
+            saio,
         })
     }
 
@@ -74,6 +139,18 @@ impl<W: Write> WriteBox<&mut W> for TrafBox {
         if let Some(ref trun) = self.trun {
             trun.write_box(writer)?;
         }
+        if let Some(ref subs) = self.subs {
+            subs.write_box(writer)?;
+        }
+        if let Some(ref saiz) = self.saiz {
+            saiz.write_box(writer)?;
+        }
+        if let Some(ref saio) = self.saio {
+            saio.write_box(writer)?;
+        }
+        if let Some(ref senc) = self.senc {
+            senc.write_box(writer)?;
+        }
 
         Ok(size)
     }
@@ -0,0 +1,238 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// QuickTime uncompressed audio sample entry, `twos` for big endian and
+/// `sowt` for little endian signed samples, using a version 0 or 1 sound
+/// description (QTFF, "Sound Sample Descriptions").
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TwosBox {
+    /// `TwosBox` or `SowtBox`.
+    #[serde(skip)]
+    pub kind: BoxType,
+    pub data_reference_index: u16,
+    pub channelcount: u16,
+    pub samplesize: u16,
+
+    #[serde(with = "value_u32")]
+    pub samplerate: FixedPointU16,
+
+    /// Version 1 packet layout.
+    pub packet: Option<SoundPacketInfo>,
+
+    /// Child atoms, e.g. `chan`, kept as is.
+    #[serde(skip)]
+    pub extensions: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct SoundPacketInfo {
+    pub samples_per_packet: u32,
+    pub bytes_per_packet: u32,
+    pub bytes_per_frame: u32,
+    pub bytes_per_sample: u32,
+}
+
+impl Default for TwosBox {
+    fn default() -> Self {
+        Self {
+            kind: BoxType::TwosBox,
+            data_reference_index: 0,
+            channelcount: 2,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(48000),
+            packet: None,
+            extensions: Vec::new(),
+        }
+    }
+}
+
+impl TwosBox {
+    pub fn get_type(&self) -> BoxType {
+        self.kind
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + 8 + 20 + self.extensions.len() as u64;
+        if self.packet.is_some() {
+            size += 16;
+        }
+        size
+    }
+
+    pub fn config(&self) -> PcmConfig {
+        PcmConfig {
+            sample_rate: self.samplerate.value() as u32,
+            channel_count: self.channelcount,
+            sample_size: match self.packet {
+                Some(ref packet) if packet.bytes_per_sample > 0 => {
+                    packet.bytes_per_sample as u16 * 8
+                }
+                _ => self.samplesize,
+            },
+            endianness: if self.kind == BoxType::SowtBox {
+                Endianness::Little
+            } else {
+                Endianness::Big
+            },
+            float: false,
+        }
+    }
+}
+
+impl Mp4Box for TwosBox {
+    const TYPE: BoxType = BoxType::TwosBox;
+    const ALIASES: &'static [BoxType] = &[BoxType::SowtBox];
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let config = self.config();
+        let s = format!(
+            "channel_count={} sample_size={} sample_rate={} endianness={:?}",
+            config.channel_count, config.sample_size, config.sample_rate, config.endianness
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for TwosBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        reader.get_u32(); // reserved
+        reader.get_u16(); // reserved
+
+        let data_reference_index = reader.get_u16();
+        let version = reader.get_u16();
+
+        reader.get_u16(); // revision level
+        reader.get_u32(); // vendor
+
+        let channelcount = reader.get_u16();
+        let samplesize = reader.get_u16();
+
+        reader.get_u16(); // compression id
+        reader.get_u16(); // packet size
+
+        let samplerate = FixedPointU16::new_raw(reader.get_u32());
+
+        let packet = match version {
+            0 => None,
+            1 => {
+                if reader.remaining() < 16 {
+                    return Err(BoxError::InvalidData("expected at least 16 bytes more"));
+                }
+
+                Some(SoundPacketInfo {
+                    samples_per_packet: reader.get_u32(),
+                    bytes_per_packet: reader.get_u32(),
+                    bytes_per_frame: reader.get_u32(),
+                    bytes_per_sample: reader.get_u32(),
+                })
+            }
+            _ => return Err(BoxError::UnsupportedBoxVersion(Self::TYPE, version as u8)),
+        };
+
+        Ok(TwosBox {
+            kind: Self::TYPE,
+            data_reference_index,
+            channelcount,
+            samplesize,
+            samplerate,
+            packet,
+            extensions: reader.collect_remaining(),
+        })
+    }
+
+    fn read_block_as<'a>(kind: BoxType, reader: &mut impl Reader<'a>) -> Result<Self> {
+        Ok(TwosBox {
+            kind,
+            ..Self::read_block(reader)?
+        })
+    }
+
+    fn size_hint() -> usize {
+        28
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for TwosBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.kind, size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u16::<BigEndian>(self.packet.is_some() as u16)?; // version
+        writer.write_u16::<BigEndian>(0)?; // revision level
+        writer.write_u32::<BigEndian>(0)?; // vendor
+        writer.write_u16::<BigEndian>(self.channelcount)?;
+        writer.write_u16::<BigEndian>(self.samplesize)?;
+        writer.write_u16::<BigEndian>(0)?; // compression id
+        writer.write_u16::<BigEndian>(0)?; // packet size
+        writer.write_u32::<BigEndian>(self.samplerate.raw_value())?;
+
+        if let Some(ref packet) = self.packet {
+            writer.write_u32::<BigEndian>(packet.samples_per_packet)?;
+            writer.write_u32::<BigEndian>(packet.bytes_per_packet)?;
+            writer.write_u32::<BigEndian>(packet.bytes_per_frame)?;
+            writer.write_u32::<BigEndian>(packet.bytes_per_sample)?;
+        }
+
+        writer.write_all(&self.extensions)?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_sowt_v1() {
+        let src_box = TwosBox {
+            kind: BoxType::SowtBox,
+            data_reference_index: 1,
+            channelcount: 2,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(44100),
+            packet: Some(SoundPacketInfo {
+                samples_per_packet: 1,
+                bytes_per_packet: 3,
+                bytes_per_frame: 6,
+                bytes_per_sample: 3,
+            }),
+            extensions: vec![0, 0, 0, 8, b'f', b'r', b'e', b'e'],
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::SowtBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = TwosBox {
+            kind: header.kind,
+            ..TwosBox::read_block(&mut reader).unwrap()
+        };
+        assert_eq!(src_box, dst_box);
+
+        let config = dst_box.config();
+        assert_eq!(config.sample_rate, 44100);
+        assert_eq!(config.sample_size, 24);
+        assert_eq!(config.endianness, Endianness::Little);
+    }
+}
@@ -0,0 +1,471 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use bytes::Bytes;
+use serde::Serialize;
+use std::convert::TryFrom;
+use std::io::Write;
+
+use crate::mp4box::*;
+
+/// WebVTT sample entry, see ISO/IEC 14496-30 section 7.5.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct WvttBox {
+    pub data_reference_index: u16,
+    pub config: VttCBox,
+    pub label: Option<VlabBox>,
+}
+
+impl WvttBox {
+    pub fn new(config: &WebVttConfig) -> Self {
+        Self {
+            data_reference_index: 1,
+            config: VttCBox {
+                config: config.config.clone(),
+            },
+            label: config.source_label.as_ref().map(|x| VlabBox {
+                source_label: x.clone(),
+            }),
+        }
+    }
+
+    pub fn get_type(&self) -> BoxType {
+        BoxType::WvttBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + 8 + self.config.box_size();
+        if let Some(ref label) = self.label {
+            size += label.box_size();
+        }
+        size
+    }
+}
+
+impl Mp4Box for WvttBox {
+    const TYPE: BoxType = BoxType::WvttBox;
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "data_reference_index={} config={:?}",
+            self.data_reference_index, self.config.config
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for WvttBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        reader.get_u32(); // reserved
+        reader.get_u16(); // reserved
+
+        let data_reference_index = reader.get_u16();
+        let (config, label) = reader.try_find_box2::<VttCBox, VlabBox>()?;
+
+        Ok(WvttBox {
+            data_reference_index,
+            config: config.ok_or(BoxError::BoxNotFound(BoxType::VttCBox))?,
+            label,
+        })
+    }
+
+    fn size_hint() -> usize {
+        8
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for WvttBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        self.config.write_box(writer)?;
+        if let Some(ref label) = self.label {
+            label.write_box(writer)?;
+        }
+
+        Ok(size)
+    }
+}
+
+/// Boxes whose whole payload is a single UTF-8 string without terminator.
+macro_rules! string_box {
+    ($(#[$meta:meta])* $name:ident, $field:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+        pub struct $name {
+            pub $field: String,
+        }
+
+        impl Mp4Box for $name {
+            const TYPE: BoxType = BoxType::$name;
+
+            fn box_size(&self) -> u64 {
+                HEADER_SIZE + self.$field.len() as u64
+            }
+
+            fn to_json(&self) -> Result<String> {
+                Ok(serde_json::to_string(&self).unwrap())
+            }
+
+            fn summary(&self) -> Result<String> {
+                Ok(format!("{}={:?}", stringify!($field), self.$field))
+            }
+        }
+
+        impl BlockReader for $name {
+            fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+                let $field = String::from_utf8(reader.collect_remaining())
+                    .map_err(|_| BoxError::InvalidData("webvtt: invalid utf-8"))?;
+
+                Ok($name { $field })
+            }
+
+            fn size_hint() -> usize {
+                0
+            }
+        }
+
+        impl<W: Write> WriteBox<&mut W> for $name {
+            fn write_box(&self, writer: &mut W) -> Result<u64> {
+                let size = self.box_size();
+                BoxHeader::new(Self::TYPE, size).write(writer)?;
+                writer.write_all(self.$field.as_bytes())?;
+
+                Ok(size)
+            }
+        }
+    };
+}
+
+string_box!(
+    /// WebVTT file header, i.e. everything before the first cue.
+    VttCBox,
+    config
+);
+string_box!(
+    /// Identifies the source of the WebVTT data, e.g. a URI.
+    VlabBox,
+    source_label
+);
+string_box!(
+    /// Cue identifier.
+    IdenBox,
+    cue_id
+);
+string_box!(
+    /// Cue settings, e.g. `line:0 align:start`.
+    SttgBox,
+    settings
+);
+string_box!(
+    /// Original cue start time, needed for cues with timestamp tags.
+    CtimBox,
+    cue_current_time
+);
+string_box!(
+    /// Cue text.
+    PaylBox,
+    cue_text
+);
+
+/// A single cue of a WebVTT sample.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct VttcBox {
+    pub iden: Option<IdenBox>,
+    pub sttg: Option<SttgBox>,
+    pub ctim: Option<CtimBox>,
+    pub payl: Option<PaylBox>,
+}
+
+impl Mp4Box for VttcBox {
+    const TYPE: BoxType = BoxType::VttcBox;
+
+    fn box_size(&self) -> u64 {
+        HEADER_SIZE
+            + self.iden.as_ref().map(|x| x.box_size()).unwrap_or(0)
+            + self.sttg.as_ref().map(|x| x.box_size()).unwrap_or(0)
+            + self.ctim.as_ref().map(|x| x.box_size()).unwrap_or(0)
+            + self.payl.as_ref().map(|x| x.box_size()).unwrap_or(0)
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "cue_id={:?} cue_text={:?}",
+            self.iden.as_ref().map(|x| x.cue_id.as_str()),
+            self.payl.as_ref().map(|x| x.cue_text.as_str())
+        );
+        Ok(s)
+    }
+}
+
+impl BlockReader for VttcBox {
+    fn read_block<'a>(reader: &mut impl Reader<'a>) -> Result<Self> {
+        let mut vttc = VttcBox::default();
+
+        while let Some(mut bx) = reader.get_box()? {
+            match bx.kind {
+                BoxType::IdenBox => vttc.iden = Some(bx.read()?),
+                BoxType::SttgBox => vttc.sttg = Some(bx.read()?),
+                BoxType::CtimBox => vttc.ctim = Some(bx.read()?),
+                BoxType::PaylBox => vttc.payl = Some(bx.read()?),
+                _ => {}
+            }
+        }
+
+        Ok(vttc)
+    }
+
+    fn size_hint() -> usize {
+        0
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for VttcBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+
+        if let Some(ref iden) = self.iden {
+            iden.write_box(writer)?;
+        }
+        if let Some(ref sttg) = self.sttg {
+            sttg.write_box(writer)?;
+        }
+        if let Some(ref ctim) = self.ctim {
+            ctim.write_box(writer)?;
+        }
+        if let Some(ref payl) = self.payl {
+            payl.write_box(writer)?;
+        }
+
+        Ok(size)
+    }
+}
+
+/// Sample content for a time span without active cues.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct VtteBox {}
+
+impl Mp4Box for VtteBox {
+    const TYPE: BoxType = BoxType::VtteBox;
+
+    fn box_size(&self) -> u64 {
+        HEADER_SIZE
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        Ok(String::new())
+    }
+}
+
+impl BlockReader for VtteBox {
+    fn read_block<'a>(_: &mut impl Reader<'a>) -> Result<Self> {
+        Ok(VtteBox {})
+    }
+
+    fn size_hint() -> usize {
+        0
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for VtteBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(Self::TYPE, size).write(writer)?;
+        Ok(size)
+    }
+}
+
+/// Decoded WebVTT cue. Timing comes from the sample carrying it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WebVttCue {
+    pub id: Option<String>,
+    pub settings: Option<String>,
+    pub current_time: Option<String>,
+    pub payload: String,
+}
+
+/// Cue with its presentation interval in track timescale units, the input of
+/// `WebVttCue::to_samples`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WebVttTimedCue {
+    pub start_time: u64,
+    pub end_time: u64,
+    pub cue: WebVttCue,
+}
+
+impl From<VttcBox> for WebVttCue {
+    fn from(vttc: VttcBox) -> Self {
+        Self {
+            id: vttc.iden.map(|x| x.cue_id),
+            settings: vttc.sttg.map(|x| x.settings),
+            current_time: vttc.ctim.map(|x| x.cue_current_time),
+            payload: vttc.payl.map(|x| x.cue_text).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<&WebVttCue> for VttcBox {
+    fn from(cue: &WebVttCue) -> Self {
+        Self {
+            iden: cue.id.as_ref().map(|x| IdenBox { cue_id: x.clone() }),
+            sttg: cue.settings.as_ref().map(|x| SttgBox {
+                settings: x.clone(),
+            }),
+            ctim: cue.current_time.as_ref().map(|x| CtimBox {
+                cue_current_time: x.clone(),
+            }),
+            payl: Some(PaylBox {
+                cue_text: cue.payload.clone(),
+            }),
+        }
+    }
+}
+
+impl WebVttCue {
+    /// Cues active during a sample, empty for `vtte` samples.
+    pub fn decode_sample(mut data: &[u8]) -> Result<Vec<WebVttCue>> {
+        let mut cues = Vec::new();
+
+        while let Some(mut bx) = data.get_box()? {
+            if let Some(vttc) = bx.try_read::<VttcBox>()? {
+                cues.push(vttc.into());
+            }
+        }
+
+        Ok(cues)
+    }
+
+    /// Sample payload for cues active over the same time span.
+    pub fn encode_sample(cues: &[WebVttCue]) -> Result<Bytes> {
+        let mut buf = Vec::new();
+
+        if cues.is_empty() {
+            VtteBox {}.write_box(&mut buf)?;
+        }
+        for cue in cues {
+            VttcBox::from(cue).write_box(&mut buf)?;
+        }
+
+        Ok(buf.into())
+    }
+
+    /// Splits possibly overlapping cues into contiguous samples starting at
+    /// zero, as required by ISO/IEC 14496-30. Gaps are filled with `vtte`.
+    pub fn to_samples(cues: &[WebVttTimedCue]) -> Result<Vec<Mp4Sample>> {
+        let mut bounds: Vec<u64> = cues
+            .iter()
+            .filter(|x| x.end_time > x.start_time)
+            .flat_map(|x| [x.start_time, x.end_time])
+            .chain([0])
+            .collect();
+
+        bounds.sort_unstable();
+        bounds.dedup();
+
+        let mut samples = Vec::with_capacity(bounds.len());
+        for span in bounds.windows(2) {
+            let (start, end) = (span[0], span[1]);
+            let active: Vec<WebVttCue> = cues
+                .iter()
+                .filter(|x| x.start_time <= start && x.end_time >= end)
+                .map(|x| x.cue.clone())
+                .collect();
+
+            samples.push(Mp4Sample {
+                start_time: start,
+                duration: u32::try_from(end - start)
+                    .map_err(|_| BoxError::InvalidData("webvtt: cue too long"))?,
+                rendering_offset: 0,
+                is_sync: true,
+                bytes: Self::encode_sample(&active)?,
+            });
+        }
+
+        Ok(samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+
+    #[tokio::test]
+    async fn test_wvtt() {
+        let src_box = WvttBox::new(&WebVttConfig {
+            config: String::from("WEBVTT\n\nSTYLE\n::cue { color: lime }"),
+            source_label: Some(String::from("urn:example:subs")),
+        });
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = buf.as_slice();
+        let header = BoxHeader::read(&mut reader, &mut 0).await.unwrap().unwrap();
+        assert_eq!(header.kind, BoxType::WvttBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = WvttBox::read_block(&mut reader).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[test]
+    fn test_webvtt_samples() {
+        let first = WebVttCue {
+            id: Some(String::from("1")),
+            payload: String::from("Hello"),
+            ..Default::default()
+        };
+        let second = WebVttCue {
+            settings: Some(String::from("line:0")),
+            payload: String::from("<v Bob>World"),
+            ..Default::default()
+        };
+        let cues = [
+            WebVttTimedCue {
+                start_time: 1000,
+                end_time: 3000,
+                cue: first.clone(),
+            },
+            WebVttTimedCue {
+                start_time: 2000,
+                end_time: 4000,
+                cue: second.clone(),
+            },
+        ];
+
+        let samples = WebVttCue::to_samples(&cues).unwrap();
+        let spans: Vec<_> = samples.iter().map(|x| (x.start_time, x.duration)).collect();
+        assert_eq!(spans, [(0, 1000), (1000, 1000), (2000, 1000), (3000, 1000)]);
+
+        let decoded: Vec<_> = samples
+            .iter()
+            .map(|x| WebVttCue::decode_sample(&x.bytes).unwrap())
+            .collect();
+        assert_eq!(decoded[0], []);
+        assert_eq!(decoded[1], std::slice::from_ref(&first));
+        assert_eq!(decoded[2], [first, second.clone()]);
+        assert_eq!(decoded[3], [second]);
+        assert_eq!(samples[0].bytes.as_ref(), b"\0\0\0\x08vtte");
+    }
+}
@@ -0,0 +1,51 @@
+//! Settings and samples shared by the writer and reader tests.
+
+use bytes::Bytes;
+
+use crate::{AvcConfig, MediaConfig, Mp4Config, Mp4Sample, TrackConfig};
+
+pub(crate) fn config() -> Mp4Config {
+    Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![
+            str::parse("isom").unwrap(),
+            str::parse("iso2").unwrap(),
+            str::parse("avc1").unwrap(),
+            str::parse("mp41").unwrap(),
+        ],
+        timescale: 1000,
+    }
+}
+
+/// 320x240 H.264.
+pub(crate) fn avc_config() -> TrackConfig {
+    TrackConfig::from(MediaConfig::AvcConfig(AvcConfig {
+        width: 320,
+        height: 240,
+        seq_param_set: vec![0x67, 0x64, 0x00, 0x0D, 0xAC, 0xD9, 0x41, 0x41],
+        pic_param_set: vec![0x68, 0xEB, 0xE3, 0xCB, 0x22, 0xC0],
+    }))
+}
+
+/// 25 fps with a keyframe every second.
+pub(crate) fn video_sample(idx: u32) -> Mp4Sample {
+    Mp4Sample {
+        start_time: idx as u64 * 40,
+        duration: 40,
+        rendering_offset: if idx % 25 == 0 { 0 } else { 80 },
+        is_sync: idx % 25 == 0,
+        bytes: Bytes::from(vec![idx as u8; 100 + idx as usize]),
+    }
+}
+
+/// AAC frames of 1024 samples.
+pub(crate) fn audio_sample(idx: u32) -> Mp4Sample {
+    Mp4Sample {
+        start_time: idx as u64 * 1024,
+        duration: 1024,
+        rendering_offset: 0,
+        is_sync: true,
+        bytes: Bytes::from(vec![!(idx as u8); 32]),
+    }
+}
@@ -0,0 +1,46 @@
+//! Parser diagnostics.
+//!
+//! With the `tracing` feature these forward to the `tracing` crate, otherwise
+//! they expand to nothing. Box reads are wrapped in `box` spans carrying the
+//! box type and size (and the file offset for top-level boxes), so the span
+//! stack of an event is the nesting path of the box it was emitted from.
+
+#[cfg(feature = "tracing")]
+macro_rules! trace {
+    ($($arg:tt)*) => { tracing::trace!($($arg)*) };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace {
+    ($($arg:tt)*) => {};
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! debug {
+    ($($arg:tt)*) => { tracing::debug!($($arg)*) };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! debug {
+    ($($arg:tt)*) => {};
+}
+
+/// Enters a `box` span for the rest of the enclosing scope.
+///
+/// The guard is `!Send`, so the scope must not contain an `.await`.
+#[cfg(feature = "tracing")]
+macro_rules! box_span {
+    ($kind:expr, $size:expr) => {
+        tracing::debug_span!("box", r#type = %$kind, size = $size).entered()
+    };
+    ($kind:expr, $size:expr, $offset:expr) => {
+        tracing::debug_span!("box", r#type = %$kind, size = $size, offset = $offset).entered()
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! box_span {
+    ($($arg:tt)*) => {
+        ()
+    };
+}
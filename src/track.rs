@@ -1,10 +1,52 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+use bytes::{Bytes, BytesMut};
 
 use crate::ctts::CttsEntry;
+use crate::elst::ElstEntry;
 use crate::error::BoxError;
 use crate::stsc::StscEntry;
 use crate::stts::SttsEntry;
-use crate::TrackType;
+use crate::types::{HANDLER_TYPE_SUBT_FOURCC, HANDLER_TYPE_TEXT_FOURCC};
+use crate::{
+    Av01Box, Avc1Box, Co64Box, CttsBox, EdtsBox, ElstBox, FlacBox, Hev1Box, MediaConfig, MediaType,
+    Mp4Sample, Mp4aBox, OpusBox, PcmBox, SampleEncryption, SampleEntry, SampleFlags, SmhdBox,
+    StcoBox, StppBox, StssBox, Subsample, TrackType, TrakBox, Tx3gBox, VmhdBox, Vp09Box, WvttBox,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackConfig {
+    pub track_type: TrackType,
+    pub timescale: u32,
+    pub language: String,
+    pub media_conf: MediaConfig,
+}
+
+impl From<MediaConfig> for TrackConfig {
+    fn from(media_conf: MediaConfig) -> Self {
+        let (track_type, timescale) = match &media_conf {
+            MediaConfig::AvcConfig(_)
+            | MediaConfig::HevcConfig(_)
+            | MediaConfig::Vp9Config(_)
+            | MediaConfig::Av1Config(_) => (TrackType::Video, 1000),
+            MediaConfig::AacConfig(aac) => (TrackType::Audio, aac.freq_index.freq()),
+            MediaConfig::OpusConfig(_) => (TrackType::Audio, 48000),
+            MediaConfig::FlacConfig(flac) => (TrackType::Audio, flac.stream_info.sample_rate),
+            MediaConfig::PcmConfig(pcm) => (TrackType::Audio, pcm.sample_rate),
+            MediaConfig::TtxtConfig(_)
+            | MediaConfig::WebVttConfig(_)
+            | MediaConfig::TtmlConfig(_) => (TrackType::Subtitle, 1000),
+        };
+
+        Self {
+            track_type,
+            timescale,
+            language: String::from("und"),
+            media_conf,
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct Mp4SampleOffset {
@@ -15,6 +57,53 @@ pub struct Mp4SampleOffset {
     pub rendering_offset: i32,
     pub is_sync: bool,
     pub chunk_id: u32,
+
+    /// 1-based index of the sample entry in `stsd` describing the sample.
+    pub sample_description_index: u32,
+
+    /// Sample flags of fragmented samples. Progressive tracks only carry the
+    /// sync flag from `stss`.
+    pub flags: SampleFlags,
+}
+
+impl Mp4SampleOffset {
+    /// Composition time of the sample, in track timescale.
+    #[inline]
+    pub fn presentation_time(&self) -> i64 {
+        self.start_time as i64 + self.rendering_offset as i64
+    }
+}
+
+/// Which sample [`Mp4Track::seek`] returns for a presentation time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekMode {
+    /// The closest sync sample presented at or before the time.
+    PreviousSync,
+
+    /// The closest sync sample presented at or after the time.
+    NextSync,
+
+    /// The sample being presented at the time. Decoding still has to start
+    /// from the preceding sync sample.
+    Exact,
+}
+
+/// One entry of the edit list, placed on the movie timeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EditSegment {
+    /// Start of the segment on the movie timeline, in movie timescale.
+    pub presentation_time: u64,
+
+    /// Length of the segment on the movie timeline, in movie timescale.
+    pub duration: u64,
+
+    /// Media time shown at the start of the segment, in track timescale, or
+    /// `None` for an empty edit.
+    pub media_time: Option<i64>,
+
+    /// Playback rate as signed 16.16 fixed point. Zero is a dwell:
+    /// `media_time` is held for the whole segment.
+    pub media_rate: i32,
 }
 
 pub struct Mp4Track {
@@ -22,7 +111,23 @@ pub struct Mp4Track {
     pub duration: u64,
     pub samples: Vec<Mp4SampleOffset>,
     pub tkhd: crate::TkhdBox,
+    pub edts: Option<crate::EdtsBox>,
     pub mdia: crate::MdiaBox,
+    pub movie_timescale: u32,
+
+    /// Fragment defaults from `mvex`, for fragmented tracks.
+    pub trex: Option<crate::TrexBox>,
+
+    /// Map presentation times through the edit list. When unset, the media
+    /// timeline is only rescaled to the movie timescale.
+    pub apply_edit_list: bool,
+
+    /// Sub-samples from `subs`, keyed by sample index.
+    pub subsamples: BTreeMap<usize, Vec<Subsample>>,
+
+    /// IVs and encrypted ranges of protected samples, from `senc` or the
+    /// `saiz`/`saio` auxiliary information, keyed by sample index.
+    pub encryption: BTreeMap<usize, SampleEncryption>,
 }
 
 impl Mp4Track {
@@ -122,6 +227,7 @@ impl Mp4Track {
 
             prev_size = size;
             total_duration = start_time + duration as u64;
+            let is_sync = sync_iter.next().unwrap_or(true);
             samples.push(Mp4SampleOffset {
                 chunk_id: chunk.index,
                 offset: chunk.offset + sample_offset as u64,
@@ -129,16 +235,36 @@ impl Mp4Track {
                 duration,
                 start_time,
                 rendering_offset: rend_offset_iter.next().unwrap_or(0),
-                is_sync: sync_iter.next().unwrap_or(true),
+                is_sync,
+                sample_description_index: chunk.sample_description_index,
+                flags: SampleFlags {
+                    is_non_sync: !is_sync,
+                    ..Default::default()
+                },
             })
         }
 
+        let subsamples = trak
+            .mdia
+            .minf
+            .stbl
+            .subs
+            .as_ref()
+            .map(|subs| subs.samples().map(|(idx, x)| (idx, x.to_vec())).collect())
+            .unwrap_or_default();
+
         Ok(Self {
             track_id: trak.tkhd.track_id,
+            movie_timescale: trak.mdia.mdhd.timescale,
             tkhd: trak.tkhd,
+            edts: trak.edts,
             mdia: trak.mdia,
             samples,
             duration: total_duration,
+            apply_edit_list: false,
+            trex: None,
+            subsamples,
+            encryption: BTreeMap::new(),
         })
     }
 
@@ -147,18 +273,311 @@ impl Mp4Track {
         TrackType::from(&self.mdia.hdlr.handler_type)
     }
 
+    /// Codec of the track, taken from its first sample entry.
+    pub fn media_type(&self) -> Result<MediaType, BoxError> {
+        self.mdia
+            .minf
+            .stbl
+            .stsd
+            .entries
+            .first()
+            .and_then(|x| x.media_type())
+            .ok_or(BoxError::InvalidData("unsupported media type"))
+    }
+
+    /// The `stsd` entry describing a sample, i.e. the decoder configuration
+    /// it needs.
+    pub fn sample_entry(&self, sample_idx: usize) -> Option<&SampleEntry> {
+        let sample = self.samples.get(sample_idx)?;
+        self.mdia
+            .minf
+            .stbl
+            .stsd
+            .entry(sample.sample_description_index)
+    }
+
+    /// Sub-samples of a sample, empty if it is not split.
+    pub fn subsamples(&self, sample_idx: usize) -> &[Subsample] {
+        self.subsamples
+            .get(&sample_idx)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Encryption parameters of a protected sample.
+    pub fn sample_encryption(&self, sample_idx: usize) -> Option<&SampleEncryption> {
+        self.encryption.get(&sample_idx)
+    }
+
+    /// The edit list of the track. A track without one is presented as a
+    /// single segment mapping the media timeline 1:1, except for Opus where
+    /// the segment starts after the pre-skip.
+    pub fn edit_segments(&self) -> Vec<EditSegment> {
+        let entries = self
+            .edts
+            .as_ref()
+            .and_then(|x| x.elst.as_ref())
+            .map(|x| &x.entries[..])
+            .unwrap_or(&[]);
+
+        if entries.is_empty() {
+            return vec![EditSegment {
+                presentation_time: 0,
+                duration: u64::MAX,
+                media_time: Some(self.pre_skip() as i64),
+                media_rate: 1 << 16,
+            }];
+        }
+
+        let mut presentation_time = 0u64;
+        entries
+            .iter()
+            .enumerate()
+            .map(|(idx, entry)| {
+                // a zero duration on the last edit extends it over the rest of
+                // the media, as written by fragmented muxers
+                let duration = if entry.segment_duration == 0 && idx == entries.len() - 1 {
+                    u64::MAX - presentation_time
+                } else {
+                    entry.segment_duration
+                };
+
+                let segment = EditSegment {
+                    presentation_time,
+                    duration,
+                    media_time: (!entry.is_empty()).then_some(entry.media_time),
+                    media_rate: (entry.media_rate as i16 as i32) << 16
+                        | entry.media_rate_fraction as i32,
+                };
+
+                presentation_time = presentation_time.saturating_add(duration);
+                segment
+            })
+            .collect()
+    }
+
+    /// Opus pre-skip in track timescale, 0 for other codecs.
+    fn pre_skip(&self) -> u64 {
+        self.mdia
+            .minf
+            .stbl
+            .stsd
+            .opus()
+            .map(|x| x.dops.pre_skip as u64 * self.mdia.mdhd.timescale as u64 / 48000)
+            .unwrap_or(0)
+    }
+
+    /// Presentation time of a sample in movie timescale.
+    ///
+    /// With [`Mp4Track::apply_edit_list`] the sample is placed by the first
+    /// edit showing it, and `None` is returned for samples edited out. A
+    /// sample cut by the start of an edit (e.g. AAC priming) starts before
+    /// the edit, so its time can be negative.
+    pub fn presentation_time(&self, sample_idx: usize) -> Option<i64> {
+        let sample = self.samples.get(sample_idx)?;
+        let track_timescale = self.mdia.mdhd.timescale.max(1) as i128;
+        let movie_timescale = self.movie_timescale as i128;
+        let pts = sample.presentation_time() as i128;
+
+        if !self.apply_edit_list {
+            return Some((pts * movie_timescale / track_timescale) as i64);
+        }
+
+        for segment in self.edit_segments() {
+            let Some(media_time) = segment.media_time else {
+                continue;
+            };
+            let media_time = media_time as i128;
+
+            if segment.media_rate == 0 {
+                if pts <= media_time && media_time < pts + sample.duration as i128 {
+                    return Some(segment.presentation_time as i64);
+                }
+
+                continue;
+            }
+
+            let rate = segment.media_rate as i128;
+            let media_end = media_time.saturating_add(
+                segment.duration as i128 * track_timescale * rate / (movie_timescale.max(1) << 16),
+            );
+
+            if pts + (sample.duration as i128) > media_time && pts < media_end {
+                let offset =
+                    (pts - media_time) * movie_timescale * (1 << 16) / (track_timescale * rate);
+                return Some((segment.presentation_time as i128 + offset) as i64);
+            }
+        }
+
+        None
+    }
+
+    /// Maps a time on the movie timeline (movie timescale) to media time
+    /// (track timescale), e.g. to feed [`Mp4Track::seek`]. Returns `None`
+    /// inside empty edits and past the end of the edit list.
+    pub fn media_time(&self, presentation_time: u64) -> Option<i64> {
+        let track_timescale = self.mdia.mdhd.timescale as i128;
+        let movie_timescale = self.movie_timescale.max(1) as i128;
+
+        if !self.apply_edit_list {
+            return Some((presentation_time as i128 * track_timescale / movie_timescale) as i64);
+        }
+
+        let segment = self.edit_segments().into_iter().find(|x| {
+            x.presentation_time <= presentation_time
+                && presentation_time - x.presentation_time < x.duration
+        })?;
+        let media_time = segment.media_time?;
+
+        let offset = (presentation_time - segment.presentation_time) as i128;
+        Some(
+            media_time
+                + (offset * track_timescale * segment.media_rate as i128 / (movie_timescale << 16))
+                    as i64,
+        )
+    }
+
+    /// Looks up the index of the sample presented at `time` (in track
+    /// timescale). Times outside the track are clamped to its first and last
+    /// samples; `None` is returned only if there is nothing to seek to.
+    pub fn seek(&self, time: u64, mode: SeekMode) -> Option<usize> {
+        if self.samples.is_empty() {
+            return None;
+        }
+
+        let exact = self.sample_at(time as i64);
+
+        match mode {
+            SeekMode::Exact => Some(exact),
+
+            SeekMode::PreviousSync => Some(
+                self.samples[..=exact]
+                    .iter()
+                    .rposition(|x| x.is_sync)
+                    .unwrap_or(0),
+            ),
+
+            SeekMode::NextSync => {
+                let sample = &self.samples[exact];
+                if sample.is_sync && sample.presentation_time() >= time as i64 {
+                    return Some(exact);
+                }
+
+                self.samples[exact + 1..]
+                    .iter()
+                    .position(|x| x.is_sync)
+                    .map(|idx| exact + 1 + idx)
+            }
+        }
+    }
+
+    fn sample_at(&self, time: i64) -> usize {
+        let samples = &self.samples;
+        let decoded = samples.partition_point(|x| x.start_time as i64 <= time);
+
+        // Samples are only reordered for presentation within a GOP, so the
+        // match is in the GOP of the last sample decoded by `time`, or in an
+        // earlier one if that GOP starts being presented after `time`.
+        let mut end = decoded.max(1);
+        end += samples[end..]
+            .iter()
+            .position(|x| x.is_sync)
+            .unwrap_or(samples.len() - end);
+
+        let mut last = decoded.saturating_sub(1);
+        loop {
+            let start = samples[..=last]
+                .iter()
+                .rposition(|x| x.is_sync)
+                .unwrap_or(0);
+
+            let found = (start..end)
+                .filter(|&idx| samples[idx].presentation_time() <= time)
+                .max_by_key(|&idx| samples[idx].presentation_time());
+
+            if let Some(idx) = found {
+                return idx;
+            }
+
+            if start == 0 {
+                return (0..end)
+                    .min_by_key(|&idx| samples[idx].presentation_time())
+                    .unwrap_or(0);
+            }
+
+            end = start;
+            last = start - 1;
+        }
+    }
+
+    /// Adds the samples of a track fragment in decode order, so a fragment
+    /// loaded out of order lands before those decoded after it. Returns
+    /// where the samples went.
     pub(crate) fn add_traf(
         &mut self,
         base_moof_offset: u64,
         chunk_index: u32,
         traf: crate::TrafBox,
         offsets: &mut BTreeSet<u64>,
+    ) -> Range<usize> {
+        let first = self.samples.len();
+        self.append_traf(base_moof_offset, chunk_index, traf, offsets);
+
+        let count = self.samples.len() - first;
+        let Some(start_time) = self.samples.get(first).map(|x| x.start_time) else {
+            return first..first;
+        };
+
+        let pos = self.samples[..first].partition_point(|x| x.start_time < start_time);
+        if pos < first {
+            self.samples[pos..].rotate_right(count);
+
+            let shift = |idx: usize| {
+                if idx >= first {
+                    idx - (first - pos)
+                } else {
+                    idx + count
+                }
+            };
+            let subsamples = self.subsamples.split_off(&pos);
+            self.subsamples
+                .extend(subsamples.into_iter().map(|(idx, x)| (shift(idx), x)));
+            let encryption = self.encryption.split_off(&pos);
+            self.encryption
+                .extend(encryption.into_iter().map(|(idx, x)| (shift(idx), x)));
+        }
+
+        pos..pos + count
+    }
+
+    fn append_traf(
+        &mut self,
+        base_moof_offset: u64,
+        chunk_index: u32,
+        traf: crate::TrafBox,
+        offsets: &mut BTreeSet<u64>,
     ) {
         let base_data_offset = traf.tfhd.base_data_offset.unwrap_or(base_moof_offset);
         offsets.insert(base_data_offset);
 
-        let default_sample_size = traf.tfhd.default_sample_size.unwrap_or(0);
-        let default_sample_duration = traf.tfhd.default_sample_duration.unwrap_or(0);
+        let trex = self.trex.clone().unwrap_or_default();
+        let default_sample_size = traf
+            .tfhd
+            .default_sample_size
+            .unwrap_or(trex.default_sample_size);
+        let default_sample_duration = traf
+            .tfhd
+            .default_sample_duration
+            .unwrap_or(trex.default_sample_duration);
+        let default_sample_flags = traf
+            .tfhd
+            .default_sample_flags
+            .unwrap_or(trex.default_sample_flags);
+        let sample_description_index = traf
+            .tfhd
+            .sample_description_index
+            .unwrap_or(trex.default_sample_description_index)
+            .max(1);
         let base_start_time = traf
             .tfdt
             .map(|x| x.base_media_decode_time)
@@ -169,6 +588,40 @@ impl Mp4Track {
             })
             .unwrap_or(0);
 
+        if let Some(ref subs) = traf.subs {
+            let first = self.samples.len();
+            self.subsamples
+                .extend(subs.samples().map(|(idx, x)| (first + idx, x.to_vec())));
+        }
+
+        if let Some(senc) = traf.senc {
+            let iv_size = self
+                .mdia
+                .minf
+                .stbl
+                .stsd
+                .entry(sample_description_index)
+                .and_then(|x| x.sinf())
+                .and_then(|x| x.tenc())
+                .map(|x| x.default_per_sample_iv_size)
+                .ok_or(BoxError::InvalidData("senc without tenc"));
+
+            match iv_size.and_then(|x| senc.samples(x)) {
+                Ok(samples) => {
+                    let first = self.samples.len();
+                    self.encryption.extend(
+                        samples
+                            .into_iter()
+                            .enumerate()
+                            .map(|(idx, x)| (first + idx, x)),
+                    );
+                }
+                Err(_err) => {
+                    debug!(track_id = self.track_id, error = %_err, "skipping senc");
+                }
+            }
+        }
+
         let Some(trun) = traf.trun else {
             return;
         };
@@ -190,6 +643,14 @@ impl Mp4Track {
 
             let rendering_offset = trun.sample_cts.get(sample_idx).copied().unwrap_or(0) as i32;
 
+            let flags = SampleFlags::from(
+                trun.sample_flags
+                    .get(sample_idx)
+                    .copied()
+                    .or(trun.first_sample_flags.filter(|_| sample_idx == 0))
+                    .unwrap_or(default_sample_flags),
+            );
+
             self.samples.push(Mp4SampleOffset {
                 chunk_id: chunk_index,
                 offset: (base_data_offset as i64
@@ -199,7 +660,9 @@ impl Mp4Track {
                 duration,
                 start_time: base_start_time + start_time_offset,
                 rendering_offset,
-                is_sync: sample_idx == 0,
+                is_sync: !flags.is_non_sync,
+                sample_description_index,
+                flags,
             });
 
             sample_offset += size as u64;
@@ -208,6 +671,279 @@ impl Mp4Track {
     }
 }
 
+pub(crate) struct Mp4TrackWriter {
+    trak: TrakBox,
+    sample_count: u32,
+    duration: u64,
+    chunk_offsets: Vec<u64>,
+    chunk_samples: u32,
+    chunk_duration: u64,
+    chunk_buffer: BytesMut,
+}
+
+impl Mp4TrackWriter {
+    pub(crate) fn new(track_id: u32, config: &TrackConfig) -> Self {
+        let mut trak = TrakBox::default();
+        trak.tkhd.track_id = track_id;
+        trak.mdia.mdhd.timescale = config.timescale;
+        trak.mdia.mdhd.language = config.language.clone();
+        trak.mdia.hdlr.handler_type = config.track_type.into();
+
+        let stbl = &mut trak.mdia.minf.stbl;
+        stbl.stss = Some(StssBox::default());
+
+        match &config.media_conf {
+            MediaConfig::AvcConfig(avc) => {
+                trak.tkhd.set_width(avc.width);
+                trak.tkhd.set_height(avc.height);
+                trak.mdia.minf.vmhd = Some(VmhdBox::default());
+                stbl.stsd.entries.push(SampleEntry::Avc1(Avc1Box::new(avc)));
+            }
+
+            MediaConfig::HevcConfig(hevc) => {
+                trak.tkhd.set_width(hevc.width);
+                trak.tkhd.set_height(hevc.height);
+                trak.mdia.minf.vmhd = Some(VmhdBox::default());
+                stbl.stsd
+                    .entries
+                    .push(SampleEntry::Hev1(Hev1Box::new(hevc)));
+            }
+
+            MediaConfig::Vp9Config(vp9) => {
+                trak.tkhd.set_width(vp9.width);
+                trak.tkhd.set_height(vp9.height);
+                trak.mdia.minf.vmhd = Some(VmhdBox::default());
+                stbl.stsd.entries.push(SampleEntry::Vp09(Vp09Box::new(vp9)));
+            }
+
+            MediaConfig::Av1Config(av1) => {
+                trak.tkhd.set_width(av1.width);
+                trak.tkhd.set_height(av1.height);
+                trak.mdia.minf.vmhd = Some(VmhdBox::default());
+                stbl.stsd.entries.push(SampleEntry::Av01(Av01Box::new(av1)));
+            }
+
+            MediaConfig::AacConfig(aac) => {
+                trak.mdia.minf.smhd = Some(SmhdBox::default());
+                stbl.stsd.entries.push(SampleEntry::Mp4a(Mp4aBox::new(aac)));
+            }
+
+            MediaConfig::OpusConfig(opus) => {
+                trak.mdia.minf.smhd = Some(SmhdBox::default());
+                stbl.stsd
+                    .entries
+                    .push(SampleEntry::Opus(OpusBox::new(opus)));
+            }
+
+            MediaConfig::FlacConfig(flac) => {
+                trak.mdia.minf.smhd = Some(SmhdBox::default());
+                stbl.stsd
+                    .entries
+                    .push(SampleEntry::Flac(FlacBox::new(flac)));
+            }
+
+            MediaConfig::PcmConfig(pcm) => {
+                trak.mdia.minf.smhd = Some(SmhdBox::default());
+                stbl.stsd.entries.push(SampleEntry::Pcm(PcmBox::new(pcm)));
+            }
+
+            MediaConfig::TtxtConfig(_) => {
+                stbl.stsd.entries.push(SampleEntry::Tx3g(Tx3gBox {
+                    data_reference_index: 1,
+                    ..Default::default()
+                }));
+            }
+
+            MediaConfig::WebVttConfig(webvtt) => {
+                trak.mdia.hdlr.handler_type = HANDLER_TYPE_TEXT_FOURCC.into();
+                stbl.stsd
+                    .entries
+                    .push(SampleEntry::Wvtt(WvttBox::new(webvtt)));
+            }
+
+            MediaConfig::TtmlConfig(ttml) => {
+                trak.mdia.hdlr.handler_type = HANDLER_TYPE_SUBT_FOURCC.into();
+                stbl.stsd
+                    .entries
+                    .push(SampleEntry::Stpp(StppBox::new(ttml)));
+            }
+        }
+
+        Self {
+            trak,
+            sample_count: 0,
+            duration: 0,
+            chunk_offsets: Vec::new(),
+            chunk_samples: 0,
+            chunk_duration: 0,
+            chunk_buffer: BytesMut::new(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn timescale(&self) -> u32 {
+        self.trak.mdia.mdhd.timescale
+    }
+
+    #[inline]
+    pub(crate) fn duration(&self) -> u64 {
+        self.duration
+    }
+
+    /// A chunk is closed once it holds about one second of media.
+    #[inline]
+    pub(crate) fn is_chunk_full(&self) -> bool {
+        self.chunk_samples > 0 && self.chunk_duration >= self.timescale() as u64
+    }
+
+    pub(crate) fn add_sample(&mut self, sample: &Mp4Sample) {
+        self.sample_count += 1;
+
+        let sample_count = self.sample_count;
+        let stbl = &mut self.trak.mdia.minf.stbl;
+
+        match stbl.stts.entries.last_mut() {
+            Some(entry) if entry.sample_delta == sample.duration => entry.sample_count += 1,
+            _ => stbl.stts.entries.push(SttsEntry {
+                sample_count: 1,
+                sample_delta: sample.duration,
+            }),
+        }
+
+        if sample.rendering_offset != 0 || stbl.ctts.is_some() {
+            let ctts = stbl.ctts.get_or_insert_with(|| {
+                let mut ctts = CttsBox::default();
+                if sample_count > 1 {
+                    ctts.entries.push(CttsEntry {
+                        sample_count: sample_count - 1,
+                        sample_offset: 0,
+                    });
+                }
+                ctts
+            });
+
+            match ctts.entries.last_mut() {
+                Some(entry) if entry.sample_offset == sample.rendering_offset => {
+                    entry.sample_count += 1
+                }
+                _ => ctts.entries.push(CttsEntry {
+                    sample_count: 1,
+                    sample_offset: sample.rendering_offset,
+                }),
+            }
+
+            // version 0 offsets are unsigned
+            if sample.rendering_offset < 0 {
+                ctts.version = 1;
+            }
+        }
+
+        if sample.is_sync {
+            if let Some(stss) = stbl.stss.as_mut() {
+                stss.entries.push(sample_count);
+            }
+        }
+
+        stbl.stsz.sample_sizes.push(sample.bytes.len() as u32);
+        stbl.stsz.sample_count = self.sample_count;
+
+        self.duration += sample.duration as u64;
+        self.chunk_samples += 1;
+        self.chunk_duration += sample.duration as u64;
+        self.chunk_buffer.extend_from_slice(&sample.bytes);
+    }
+
+    /// Takes the pending chunk payload, registering it in `stsc` as if it
+    /// was written at `offset`.
+    pub(crate) fn take_chunk(&mut self, offset: u64) -> Option<Bytes> {
+        if self.chunk_samples == 0 {
+            return None;
+        }
+
+        self.chunk_offsets.push(offset);
+
+        let chunk_samples = self.chunk_samples;
+        let stsc = &mut self.trak.mdia.minf.stbl.stsc;
+        let same_layout = stsc
+            .entries
+            .last()
+            .map(|x| x.samples_per_chunk == chunk_samples)
+            .unwrap_or(false);
+
+        if !same_layout {
+            stsc.entries.push(StscEntry {
+                first_chunk: self.chunk_offsets.len() as u32,
+                samples_per_chunk: chunk_samples,
+                sample_description_index: 1,
+                first_sample: self.sample_count - chunk_samples + 1,
+            });
+        }
+
+        self.chunk_samples = 0;
+        self.chunk_duration = 0;
+
+        Some(self.chunk_buffer.split().freeze())
+    }
+
+    pub(crate) fn write_end(mut self, movie_timescale: u32) -> TrakBox {
+        let sample_count = self.sample_count;
+        let timescale = self.timescale().max(1) as u64;
+        let stbl = &mut self.trak.mdia.minf.stbl;
+
+        let first_size = stbl.stsz.sample_sizes.first().copied().unwrap_or(0);
+        if first_size > 0 && stbl.stsz.sample_sizes.iter().all(|&x| x == first_size) {
+            stbl.stsz.sample_size = first_size;
+            stbl.stsz.sample_sizes.clear();
+        }
+
+        if stbl
+            .stss
+            .as_ref()
+            .map(|x| x.entries.len() as u32 == sample_count)
+            .unwrap_or(false)
+        {
+            stbl.stss = None;
+        }
+
+        if self.chunk_offsets.last().copied().unwrap_or(0) > u32::MAX as u64 {
+            stbl.co64 = Some(Co64Box {
+                entries: self.chunk_offsets,
+                ..Default::default()
+            });
+        } else {
+            stbl.stco = Some(StcoBox {
+                entries: self.chunk_offsets.into_iter().map(|x| x as u32).collect(),
+                ..Default::default()
+            });
+        }
+
+        self.trak.mdia.mdhd.duration = self.duration;
+        self.trak.tkhd.duration = self.duration * movie_timescale as u64 / timescale;
+
+        if let Some(opus) = stbl.stsd.opus().filter(|x| x.dops.pre_skip > 0) {
+            let pre_skip = opus.dops.pre_skip as u64 * timescale / 48000;
+
+            // a zero duration covers the whole media, e.g. in an init segment
+            let duration = self.duration.saturating_sub(pre_skip);
+            self.trak.tkhd.duration = duration * movie_timescale as u64 / timescale;
+            self.trak.edts = Some(EdtsBox {
+                elst: Some(ElstBox {
+                    version: 0,
+                    flags: 0,
+                    entries: vec![ElstEntry {
+                        segment_duration: self.trak.tkhd.duration,
+                        media_time: pre_skip as i64,
+                        media_rate: 1,
+                        media_rate_fraction: 0,
+                    }],
+                }),
+            });
+        }
+
+        self.trak
+    }
+}
+
 trait RunLenghtItem {
     type Value: Clone;
 
@@ -320,3 +1056,522 @@ fn run_len_iter<E: RunLenghtItem, I: IntoIterator<Item = E>>(
         repeat = x.count();
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::avc_config;
+    use crate::{
+        AacConfig, EncvBox, FourCC, FrmaBox, Mp4Sample, OpusConfig, SchiBox, SchmBox, SencBox,
+        SinfBox, SubsBox, SubsEntry, SubsampleEncryption, TencBox, TfdtBox, TfhdBox, TrafBox,
+        TrexBox, TrunBox, TtmlConfig,
+    };
+
+    fn track() -> Mp4Track {
+        let config = avc_config();
+        let mut writer = Mp4TrackWriter::new(1, &config);
+
+        // GOPs of 10 frames in decode order I P B B P B B P B B, 100 ticks
+        // per frame and a 200 tick composition offset for the anchors.
+        for idx in 0..30u32 {
+            let pos = idx % 10;
+            let rendering_offset = match pos {
+                0 => 100,
+                _ if pos % 3 == 1 => 300,
+                _ => 0,
+            };
+
+            writer.add_sample(&Mp4Sample {
+                start_time: idx as u64 * 100,
+                duration: 100,
+                rendering_offset,
+                is_sync: pos == 0,
+                bytes: Bytes::from_static(&[0]),
+            });
+        }
+        writer.take_chunk(0);
+
+        Mp4Track::new(writer.write_end(1000), &mut BTreeSet::new()).unwrap()
+    }
+
+    #[test]
+    fn test_seek() {
+        let track = track();
+
+        // frame 11 (P) is decoded at 1100 and presented at 1400
+        assert_eq!(track.seek(1400, SeekMode::Exact), Some(11));
+        assert_eq!(track.seek(1450, SeekMode::Exact), Some(11));
+        // frame 12 (B) is decoded at 1200 and presented at 1200
+        assert_eq!(track.seek(1250, SeekMode::Exact), Some(12));
+        // the I frame of the second GOP is presented at 1100
+        assert_eq!(track.seek(1100, SeekMode::Exact), Some(10));
+
+        assert_eq!(track.seek(1450, SeekMode::PreviousSync), Some(10));
+        assert_eq!(track.seek(1450, SeekMode::NextSync), Some(20));
+        assert_eq!(track.seek(1100, SeekMode::NextSync), Some(10));
+
+        assert_eq!(track.seek(0, SeekMode::Exact), Some(0));
+        assert_eq!(track.seek(0, SeekMode::NextSync), Some(0));
+        assert_eq!(track.seek(100_000, SeekMode::PreviousSync), Some(20));
+        assert_eq!(track.seek(100_000, SeekMode::NextSync), None);
+    }
+
+    #[test]
+    fn test_edit_list() {
+        let mut config = TrackConfig::from(MediaConfig::AacConfig(AacConfig::default()));
+        config.timescale = 48000;
+
+        let mut writer = Mp4TrackWriter::new(1, &config);
+        for idx in 0..100u64 {
+            writer.add_sample(&Mp4Sample {
+                start_time: idx * 1024,
+                duration: 1024,
+                rendering_offset: 0,
+                is_sync: true,
+                bytes: Bytes::from_static(&[0]),
+            });
+        }
+        writer.take_chunk(0);
+
+        let entry = |segment_duration, media_time, media_rate| ElstEntry {
+            segment_duration,
+            media_time,
+            media_rate,
+            media_rate_fraction: 0,
+        };
+
+        // 500ms of nothing, a 100ms still of the sample at 4096, then the
+        // media without its 2112 samples of priming
+        let mut trak = writer.write_end(1000);
+        trak.edts = Some(EdtsBox {
+            elst: Some(ElstBox {
+                entries: vec![entry(500, -1, 1), entry(100, 4096, 0), entry(2000, 2112, 1)],
+                ..Default::default()
+            }),
+        });
+
+        let mut track = Mp4Track::new(trak, &mut BTreeSet::new()).unwrap();
+        track.movie_timescale = 1000;
+
+        assert_eq!(track.presentation_time(3), Some(64));
+
+        track.apply_edit_list = true;
+        assert_eq!(track.presentation_time(0), None);
+        assert_eq!(track.presentation_time(1), None);
+        assert_eq!(track.presentation_time(2), Some(599));
+        assert_eq!(track.presentation_time(3), Some(620));
+        assert_eq!(track.presentation_time(4), Some(500));
+
+        assert_eq!(track.media_time(250), None);
+        assert_eq!(track.media_time(550), Some(4096));
+        assert_eq!(track.media_time(700), Some(2112 + 4800));
+        assert_eq!(track.media_time(3000), None);
+    }
+
+    #[test]
+    fn test_edit_list_media_rate() {
+        let mut config = TrackConfig::from(MediaConfig::AacConfig(AacConfig::default()));
+        config.timescale = 48000;
+
+        let mut writer = Mp4TrackWriter::new(1, &config);
+        for idx in 0..100u64 {
+            writer.add_sample(&Mp4Sample {
+                start_time: idx * 1024,
+                duration: 1024,
+                rendering_offset: 0,
+                is_sync: true,
+                bytes: Bytes::from_static(&[0]),
+            });
+        }
+        writer.take_chunk(0);
+
+        // one second backwards, then the media at 1.5x
+        let mut trak = writer.write_end(1000);
+        trak.edts = Some(EdtsBox {
+            elst: Some(ElstBox {
+                entries: vec![
+                    ElstEntry {
+                        segment_duration: 1000,
+                        media_time: 48000,
+                        media_rate: -1i16 as u16,
+                        media_rate_fraction: 0,
+                    },
+                    ElstEntry {
+                        segment_duration: 1000,
+                        media_time: 0,
+                        media_rate: 1,
+                        media_rate_fraction: 0x8000,
+                    },
+                ],
+                ..Default::default()
+            }),
+        });
+
+        let mut track = Mp4Track::new(trak, &mut BTreeSet::new()).unwrap();
+        track.movie_timescale = 1000;
+        track.apply_edit_list = true;
+
+        let rates: Vec<_> = track.edit_segments().iter().map(|x| x.media_rate).collect();
+        assert_eq!(rates, [-0x10000, 0x18000]);
+
+        assert_eq!(track.media_time(500), Some(24000));
+        assert_eq!(track.media_time(1500), Some(36000));
+        assert_eq!(track.presentation_time(3), Some(1042));
+    }
+
+    #[test]
+    fn test_opus_pre_skip() {
+        let config = TrackConfig::from(MediaConfig::OpusConfig(OpusConfig::default()));
+        let mut writer = Mp4TrackWriter::new(1, &config);
+        for idx in 0..100u64 {
+            writer.add_sample(&Mp4Sample {
+                start_time: idx * 960,
+                duration: 960,
+                rendering_offset: 0,
+                is_sync: true,
+                bytes: Bytes::from_static(&[0]),
+            });
+        }
+        writer.take_chunk(0);
+
+        // the 312 samples of pre-skip are trimmed by the written edit list
+        let trak = writer.write_end(1000);
+        let elst = trak.edts.as_ref().and_then(|x| x.elst.as_ref()).unwrap();
+        assert_eq!(elst.entries[0].media_time, 312);
+        assert_eq!(elst.entries[0].segment_duration, (96000 - 312) / 48);
+
+        let mut track = Mp4Track::new(trak, &mut BTreeSet::new()).unwrap();
+        track.movie_timescale = 1000;
+        track.apply_edit_list = true;
+        assert_eq!(track.media_time(0), Some(312));
+        assert_eq!(track.presentation_time(1), Some(13));
+
+        // and implied when a muxer left the edit list out
+        track.edts = None;
+        assert_eq!(track.edit_segments()[0].media_time, Some(312));
+        assert_eq!(track.media_time(0), Some(312));
+    }
+
+    #[test]
+    fn test_sample_flags() {
+        let flags = SampleFlags {
+            is_leading: 2,
+            depends_on: 1,
+            is_depended_on: 2,
+            has_redundancy: 1,
+            padding_value: 5,
+            is_non_sync: true,
+            degradation_priority: 0x1234,
+        };
+        assert_eq!(u32::from(flags), 0x099B_1234);
+        assert_eq!(SampleFlags::from(0x099B_1234), flags);
+        assert_eq!(u32::from(SampleFlags::NON_SYNC), 0x0101_0000);
+    }
+
+    #[test]
+    fn test_add_traf_sample_flags() {
+        let mut track = track();
+        let count = track.samples.len();
+
+        // audio-like defaults from trex: every sample is sync
+        track.trex = Some(TrexBox {
+            track_id: 1,
+            default_sample_duration: 100,
+            default_sample_size: 1,
+            default_sample_flags: SampleFlags::SYNC.into(),
+            ..Default::default()
+        });
+
+        let traf = |tfhd_flags: Option<u32>, first_sample_flags: Option<u32>| TrafBox {
+            tfhd: TfhdBox {
+                track_id: 1,
+                default_sample_flags: tfhd_flags,
+                ..Default::default()
+            },
+            tfdt: None,
+            trun: Some(TrunBox {
+                sample_count: 3,
+                first_sample_flags,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        track.add_traf(0, 1, traf(None, None), &mut BTreeSet::new());
+        assert!(track.samples[count..].iter().all(|x| x.is_sync));
+        assert_eq!(track.samples[count].duration, 100);
+        assert_eq!(track.samples[count].start_time, 3000);
+
+        // video: first_sample_flags marks the I frame, tfhd the rest
+        track.add_traf(
+            0,
+            2,
+            traf(
+                Some(SampleFlags::NON_SYNC.into()),
+                Some(SampleFlags::SYNC.into()),
+            ),
+            &mut BTreeSet::new(),
+        );
+        let samples = &track.samples[count + 3..];
+        assert_eq!(
+            samples.iter().map(|x| x.is_sync).collect::<Vec<_>>(),
+            vec![true, false, false]
+        );
+        assert_eq!(samples[0].flags.depends_on, 2);
+        assert_eq!(samples[1].flags, SampleFlags::NON_SYNC);
+    }
+
+    #[test]
+    fn test_add_traf_out_of_order() {
+        let mut track = track();
+        let count = track.samples.len();
+
+        let traf = |base_media_decode_time: u64| TrafBox {
+            tfhd: TfhdBox {
+                track_id: 1,
+                default_sample_duration: Some(100),
+                ..Default::default()
+            },
+            tfdt: Some(TfdtBox {
+                base_media_decode_time,
+                ..Default::default()
+            }),
+            trun: Some(TrunBox {
+                sample_count: 5,
+                ..Default::default()
+            }),
+            subs: Some(SubsBox {
+                entries: vec![SubsEntry {
+                    sample_delta: 1,
+                    subsamples: vec![Subsample::default()],
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        // the second fragment is loaded first, e.g. after a seek
+        let range = track.add_traf(0, 2, traf(3500), &mut BTreeSet::new());
+        assert_eq!(range, count..count + 5);
+        let range = track.add_traf(0, 1, traf(3000), &mut BTreeSet::new());
+        assert_eq!(range, count..count + 5);
+
+        assert!(track
+            .samples
+            .windows(2)
+            .all(|x| x[0].start_time < x[1].start_time));
+        assert_eq!(track.samples[count + 5].start_time, 3500);
+        assert_eq!(
+            track.subsamples.keys().copied().collect::<Vec<_>>(),
+            vec![count, count + 5]
+        );
+    }
+
+    #[test]
+    fn test_sample_description_index() {
+        let mut track = track();
+        assert!(track
+            .samples
+            .iter()
+            .all(|x| x.sample_description_index == 1));
+        assert!(matches!(track.sample_entry(0), Some(SampleEntry::Avc1(_))));
+
+        // a mid-stream switch to new parameters in a later fragment
+        let mut avc1 = track.mdia.minf.stbl.stsd.avc1().unwrap().clone();
+        avc1.width = 640;
+        track
+            .mdia
+            .minf
+            .stbl
+            .stsd
+            .entries
+            .push(SampleEntry::Avc1(avc1));
+
+        track.add_traf(
+            0,
+            1,
+            TrafBox {
+                tfhd: TfhdBox {
+                    track_id: 1,
+                    sample_description_index: Some(2),
+                    ..Default::default()
+                },
+                tfdt: None,
+                trun: Some(TrunBox {
+                    sample_count: 1,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            &mut BTreeSet::new(),
+        );
+
+        let idx = track.samples.len() - 1;
+        assert_eq!(track.samples[idx].sample_description_index, 2);
+        match track.sample_entry(idx) {
+            Some(SampleEntry::Avc1(avc1)) => assert_eq!(avc1.width, 640),
+            _ => panic!("expected the second avc1 entry"),
+        }
+    }
+
+    #[test]
+    fn test_ttml_subsamples() {
+        let config = TrackConfig::from(MediaConfig::TtmlConfig(TtmlConfig::default()));
+        let mut writer = Mp4TrackWriter::new(1, &config);
+        writer.add_sample(&Mp4Sample {
+            start_time: 0,
+            duration: 1000,
+            rendering_offset: 0,
+            is_sync: true,
+            bytes: Bytes::from_static(b"<tt/>"),
+        });
+        writer.take_chunk(0);
+
+        let mut track = Mp4Track::new(writer.write_end(1000), &mut BTreeSet::new()).unwrap();
+        assert_eq!(track.track_type(), TrackType::Subtitle);
+        assert_eq!(track.media_type().unwrap(), MediaType::TTML);
+        assert!(track.subsamples(0).is_empty());
+
+        // the second sample of the fragment carries an image
+        track.add_traf(
+            0,
+            1,
+            TrafBox {
+                tfhd: TfhdBox {
+                    track_id: 1,
+                    default_sample_duration: Some(1000),
+                    ..Default::default()
+                },
+                tfdt: None,
+                trun: Some(TrunBox {
+                    sample_count: 2,
+                    sample_sizes: vec![5, 105],
+                    ..Default::default()
+                }),
+                subs: Some(SubsBox {
+                    entries: vec![SubsEntry {
+                        sample_delta: 2,
+                        subsamples: vec![
+                            Subsample {
+                                size: 5,
+                                ..Default::default()
+                            },
+                            Subsample {
+                                size: 100,
+                                ..Default::default()
+                            },
+                        ],
+                    }],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            &mut BTreeSet::new(),
+        );
+
+        assert!(track.subsamples(1).is_empty());
+        assert_eq!(track.subsamples(2).len(), 2);
+    }
+
+    fn protect(track: &mut Mp4Track, scheme_type: FourCC, tenc: TencBox) {
+        let entry = &mut track.mdia.minf.stbl.stsd.entries[0];
+        let sinf = SinfBox {
+            frma: FrmaBox {
+                data_format: u32::from(entry.get_type()).into(),
+            },
+            schm: Some(SchmBox {
+                scheme_type,
+                scheme_version: 0x00010000,
+                ..Default::default()
+            }),
+            schi: Some(SchiBox { tenc: Some(tenc) }),
+        };
+        *entry = SampleEntry::Encv(EncvBox::new(entry.clone(), sinf));
+    }
+
+    fn add_senc(track: &mut Mp4Track, sample_count: u32, senc: SencBox) {
+        track.add_traf(
+            0,
+            1,
+            TrafBox {
+                tfhd: TfhdBox {
+                    track_id: 1,
+                    ..Default::default()
+                },
+                trun: Some(TrunBox {
+                    sample_count,
+                    ..Default::default()
+                }),
+                senc: Some(senc),
+                ..Default::default()
+            },
+            &mut BTreeSet::new(),
+        );
+    }
+
+    #[test]
+    fn test_senc() {
+        let mut track = track();
+        protect(
+            &mut track,
+            SchmBox::CENC,
+            TencBox {
+                default_is_protected: 1,
+                default_per_sample_iv_size: 8,
+                ..Default::default()
+            },
+        );
+        let count = track.samples.len();
+
+        let samples = vec![
+            SampleEncryption {
+                iv: vec![1; 8],
+                subsamples: vec![SubsampleEncryption {
+                    clear_bytes: 10,
+                    encrypted_bytes: 32,
+                }],
+            },
+            SampleEncryption {
+                iv: vec![2; 8],
+                subsamples: vec![],
+            },
+        ];
+        add_senc(&mut track, 2, SencBox::new(&samples));
+
+        assert_eq!(track.sample_encryption(count - 1), None);
+        assert_eq!(track.sample_encryption(count), Some(&samples[0]));
+        assert_eq!(track.sample_encryption(count + 1), Some(&samples[1]));
+    }
+
+    #[test]
+    fn test_senc_constant_iv() {
+        let mut track = track();
+        protect(
+            &mut track,
+            SchmBox::CBCS,
+            TencBox {
+                version: 1,
+                default_crypt_byte_block: 1,
+                default_skip_byte_block: 9,
+                default_is_protected: 1,
+                default_per_sample_iv_size: 0,
+                default_constant_iv: Some(vec![0x42; 16]),
+                ..Default::default()
+            },
+        );
+        let count = track.samples.len();
+
+        // neither IVs nor sub-samples, so the entries take no space at all
+        let senc = SencBox::new(&vec![SampleEncryption::default(); 3]);
+        assert!(senc.data.is_empty());
+        add_senc(&mut track, 3, senc);
+
+        assert_eq!(track.samples.len(), count + 3);
+        for idx in count..count + 3 {
+            assert_eq!(
+                track.sample_encryption(idx),
+                Some(&SampleEncryption::default())
+            );
+        }
+    }
+}
@@ -167,6 +167,14 @@ const HANDLER_TYPE_AUDIO_FOURCC: [u8; 4] = [b's', b'o', b'u', b'n'];
 const HANDLER_TYPE_SUBTITLE: &str = "sbtl";
 const HANDLER_TYPE_SUBTITLE_FOURCC: [u8; 4] = [b's', b'b', b't', b'l'];
 
+// ISO/IEC 14496-30 text tracks, e.g. WebVTT
+const HANDLER_TYPE_TEXT: &str = "text";
+pub(crate) const HANDLER_TYPE_TEXT_FOURCC: [u8; 4] = [b't', b'e', b'x', b't'];
+
+// ISO/IEC 14496-12 subtitle tracks, e.g. TTML
+const HANDLER_TYPE_SUBT: &str = "subt";
+pub(crate) const HANDLER_TYPE_SUBT_FOURCC: [u8; 4] = [b's', b'u', b'b', b't'];
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TrackType {
     Video,
@@ -192,7 +200,9 @@ impl TryFrom<&str> for TrackType {
         match handler {
             HANDLER_TYPE_VIDEO => Ok(TrackType::Video),
             HANDLER_TYPE_AUDIO => Ok(TrackType::Audio),
-            HANDLER_TYPE_SUBTITLE => Ok(TrackType::Subtitle),
+            HANDLER_TYPE_SUBTITLE | HANDLER_TYPE_TEXT | HANDLER_TYPE_SUBT => {
+                Ok(TrackType::Subtitle)
+            }
             _ => Err(BoxError::InvalidData("unsupported handler type")),
         }
     }
@@ -203,7 +213,9 @@ impl From<&FourCC> for TrackType {
         match fourcc.value {
             HANDLER_TYPE_VIDEO_FOURCC => TrackType::Video,
             HANDLER_TYPE_AUDIO_FOURCC => TrackType::Audio,
-            HANDLER_TYPE_SUBTITLE_FOURCC => TrackType::Subtitle,
+            HANDLER_TYPE_SUBTITLE_FOURCC | HANDLER_TYPE_TEXT_FOURCC | HANDLER_TYPE_SUBT_FOURCC => {
+                TrackType::Subtitle
+            }
             other => TrackType::Other(other.into()),
         }
     }
@@ -215,7 +227,7 @@ impl From<TrackType> for FourCC {
             TrackType::Video => HANDLER_TYPE_VIDEO_FOURCC.into(),
             TrackType::Audio => HANDLER_TYPE_AUDIO_FOURCC.into(),
             TrackType::Subtitle => HANDLER_TYPE_SUBTITLE_FOURCC.into(),
-            TrackType::Other(inner) => inner.into(),
+            TrackType::Other(inner) => inner,
         }
     }
 }
@@ -223,16 +235,36 @@ impl From<TrackType> for FourCC {
 const MEDIA_TYPE_H264: &str = "h264";
 const MEDIA_TYPE_H265: &str = "h265";
 const MEDIA_TYPE_VP9: &str = "vp9";
+const MEDIA_TYPE_AV1: &str = "av1";
 const MEDIA_TYPE_AAC: &str = "aac";
+const MEDIA_TYPE_OPUS: &str = "opus";
+const MEDIA_TYPE_FLAC: &str = "flac";
+const MEDIA_TYPE_AC3: &str = "ac3";
+const MEDIA_TYPE_EAC3: &str = "eac3";
+const MEDIA_TYPE_PCM: &str = "pcm";
+const MEDIA_TYPE_ALAC: &str = "alac";
 const MEDIA_TYPE_TTXT: &str = "ttxt";
+const MEDIA_TYPE_WEBVTT: &str = "wvtt";
+const MEDIA_TYPE_TTML: &str = "ttml";
+const MEDIA_TYPE_CEA608: &str = "cea608";
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MediaType {
     H264,
     H265,
     VP9,
+    AV1,
     AAC,
+    OPUS,
+    FLAC,
+    AC3,
+    EAC3,
+    PCM,
+    ALAC,
     TTXT,
+    WEBVTT,
+    TTML,
+    CEA608,
 }
 
 impl fmt::Display for MediaType {
@@ -249,8 +281,18 @@ impl TryFrom<&str> for MediaType {
             MEDIA_TYPE_H264 => Ok(MediaType::H264),
             MEDIA_TYPE_H265 => Ok(MediaType::H265),
             MEDIA_TYPE_VP9 => Ok(MediaType::VP9),
+            MEDIA_TYPE_AV1 => Ok(MediaType::AV1),
             MEDIA_TYPE_AAC => Ok(MediaType::AAC),
+            MEDIA_TYPE_OPUS => Ok(MediaType::OPUS),
+            MEDIA_TYPE_FLAC => Ok(MediaType::FLAC),
+            MEDIA_TYPE_AC3 => Ok(MediaType::AC3),
+            MEDIA_TYPE_EAC3 => Ok(MediaType::EAC3),
+            MEDIA_TYPE_PCM => Ok(MediaType::PCM),
+            MEDIA_TYPE_ALAC => Ok(MediaType::ALAC),
             MEDIA_TYPE_TTXT => Ok(MediaType::TTXT),
+            MEDIA_TYPE_WEBVTT => Ok(MediaType::WEBVTT),
+            MEDIA_TYPE_TTML => Ok(MediaType::TTML),
+            MEDIA_TYPE_CEA608 => Ok(MediaType::CEA608),
             _ => Err(BoxError::InvalidData("unsupported media type")),
         }
     }
@@ -262,8 +304,18 @@ impl From<MediaType> for &str {
             MediaType::H264 => MEDIA_TYPE_H264,
             MediaType::H265 => MEDIA_TYPE_H265,
             MediaType::VP9 => MEDIA_TYPE_VP9,
+            MediaType::AV1 => MEDIA_TYPE_AV1,
             MediaType::AAC => MEDIA_TYPE_AAC,
+            MediaType::OPUS => MEDIA_TYPE_OPUS,
+            MediaType::FLAC => MEDIA_TYPE_FLAC,
+            MediaType::AC3 => MEDIA_TYPE_AC3,
+            MediaType::EAC3 => MEDIA_TYPE_EAC3,
+            MediaType::PCM => MEDIA_TYPE_PCM,
+            MediaType::ALAC => MEDIA_TYPE_ALAC,
             MediaType::TTXT => MEDIA_TYPE_TTXT,
+            MediaType::WEBVTT => MEDIA_TYPE_WEBVTT,
+            MediaType::TTML => MEDIA_TYPE_TTML,
+            MediaType::CEA608 => MEDIA_TYPE_CEA608,
         }
     }
 }
@@ -274,8 +326,18 @@ impl From<&MediaType> for &str {
             MediaType::H264 => MEDIA_TYPE_H264,
             MediaType::H265 => MEDIA_TYPE_H265,
             MediaType::VP9 => MEDIA_TYPE_VP9,
+            MediaType::AV1 => MEDIA_TYPE_AV1,
             MediaType::AAC => MEDIA_TYPE_AAC,
+            MediaType::OPUS => MEDIA_TYPE_OPUS,
+            MediaType::FLAC => MEDIA_TYPE_FLAC,
+            MediaType::AC3 => MEDIA_TYPE_AC3,
+            MediaType::EAC3 => MEDIA_TYPE_EAC3,
+            MediaType::PCM => MEDIA_TYPE_PCM,
+            MediaType::ALAC => MEDIA_TYPE_ALAC,
             MediaType::TTXT => MEDIA_TYPE_TTXT,
+            MediaType::WEBVTT => MEDIA_TYPE_WEBVTT,
+            MediaType::TTML => MEDIA_TYPE_TTML,
+            MediaType::CEA608 => MEDIA_TYPE_CEA608,
         }
     }
 }
@@ -575,10 +637,24 @@ pub struct AvcConfig {
     pub pic_param_set: Vec<u8>,
 }
 
+/// HEVC sample entry flavour. With `hvc1` parameter sets live only in
+/// `hvcC`, with `hev1` they may also be repeated in band.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum HevcBoxType {
+    #[default]
+    Hev1,
+    Hvc1,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Default)]
 pub struct HevcConfig {
     pub width: u16,
     pub height: u16,
+    pub box_type: HevcBoxType,
+    pub video_param_sets: Vec<Vec<u8>>,
+    pub seq_param_sets: Vec<Vec<u8>>,
+    pub pic_param_sets: Vec<Vec<u8>>,
+    pub sei: Vec<Vec<u8>>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Default)]
@@ -587,6 +663,25 @@ pub struct Vp9Config {
     pub height: u16,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Av1Config {
+    pub width: u16,
+    pub height: u16,
+    pub seq_profile: u8,
+    pub seq_level_idx_0: u8,
+    pub seq_tier_0: bool,
+
+    /// 8, 10 or 12.
+    pub bit_depth: u8,
+    pub monochrome: bool,
+    pub chroma_subsampling_x: bool,
+    pub chroma_subsampling_y: bool,
+    pub chroma_sample_position: u8,
+
+    /// Sequence header OBU to carry in `av1C`.
+    pub config_obus: Vec<u8>,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct AacConfig {
     pub bitrate: u32,
@@ -606,16 +701,111 @@ impl Default for AacConfig {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct OpusConfig {
+    pub channel_count: u8,
+
+    /// Encoder delay in 48 kHz samples, trimmed through the edit list.
+    pub pre_skip: u16,
+    pub input_sample_rate: u32,
+    pub output_gain: i16,
+    pub channel_mapping_family: u8,
+    pub channel_mapping: Option<OpusChannelMapping>,
+}
+
+impl Default for OpusConfig {
+    fn default() -> Self {
+        Self {
+            channel_count: 2,
+            pre_skip: 312,
+            input_sample_rate: 48000,
+            output_gain: 0,
+            channel_mapping_family: 0,
+            channel_mapping: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct FlacConfig {
+    pub stream_info: FlacStreamInfo,
+
+    /// Metadata blocks to carry after STREAMINFO, e.g. VORBIS_COMMENT.
+    pub metadata_blocks: Vec<FlacMetadataBlock>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Endianness {
+    #[default]
+    Big,
+    Little,
+}
+
+/// Uncompressed audio sample format.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct PcmConfig {
+    pub sample_rate: u32,
+    pub channel_count: u16,
+
+    /// Bits per sample.
+    pub sample_size: u16,
+    pub endianness: Endianness,
+
+    /// IEEE 754 floating point rather than signed integer samples.
+    pub float: bool,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Default)]
 pub struct TtxtConfig {}
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct WebVttConfig {
+    /// WebVTT file header up to the first cue, carried in `vttC`.
+    pub config: String,
+    pub source_label: Option<String>,
+}
+
+impl Default for WebVttConfig {
+    fn default() -> Self {
+        Self {
+            config: String::from("WEBVTT"),
+            source_label: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TtmlConfig {
+    pub namespace: String,
+    pub schema_location: String,
+
+    /// MIME types of image subsamples, empty for text only documents.
+    pub auxiliary_mime_types: String,
+}
+
+impl Default for TtmlConfig {
+    fn default() -> Self {
+        Self {
+            namespace: String::from("http://www.w3.org/ns/ttml"),
+            schema_location: String::new(),
+            auxiliary_mime_types: String::new(),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum MediaConfig {
     AvcConfig(AvcConfig),
     HevcConfig(HevcConfig),
     Vp9Config(Vp9Config),
+    Av1Config(Av1Config),
     AacConfig(AacConfig),
+    OpusConfig(OpusConfig),
+    FlacConfig(FlacConfig),
+    PcmConfig(PcmConfig),
     TtxtConfig(TtxtConfig),
+    WebVttConfig(WebVttConfig),
+    TtmlConfig(TtmlConfig),
 }
 
 #[derive(Debug)]
@@ -651,6 +841,69 @@ impl fmt::Display for Mp4Sample {
     }
 }
 
+/// Sample flags as stored in `trex`, `tfhd` and `trun` (ISO/IEC 14496-12
+/// 8.8.3.1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct SampleFlags {
+    pub is_leading: u8,
+    pub depends_on: u8,
+    pub is_depended_on: u8,
+    pub has_redundancy: u8,
+    pub padding_value: u8,
+    pub is_non_sync: bool,
+    pub degradation_priority: u16,
+}
+
+impl SampleFlags {
+    /// A sample that does not depend on others.
+    pub const SYNC: SampleFlags = SampleFlags {
+        is_leading: 0,
+        depends_on: 2,
+        is_depended_on: 0,
+        has_redundancy: 0,
+        padding_value: 0,
+        is_non_sync: false,
+        degradation_priority: 0,
+    };
+
+    /// A sample predicted from others.
+    pub const NON_SYNC: SampleFlags = SampleFlags {
+        is_leading: 0,
+        depends_on: 1,
+        is_depended_on: 0,
+        has_redundancy: 0,
+        padding_value: 0,
+        is_non_sync: true,
+        degradation_priority: 0,
+    };
+}
+
+impl From<u32> for SampleFlags {
+    fn from(flags: u32) -> Self {
+        SampleFlags {
+            is_leading: (flags >> 26 & 0b11) as u8,
+            depends_on: (flags >> 24 & 0b11) as u8,
+            is_depended_on: (flags >> 22 & 0b11) as u8,
+            has_redundancy: (flags >> 20 & 0b11) as u8,
+            padding_value: (flags >> 17 & 0b111) as u8,
+            is_non_sync: flags >> 16 & 1 == 1,
+            degradation_priority: flags as u16,
+        }
+    }
+}
+
+impl From<SampleFlags> for u32 {
+    fn from(flags: SampleFlags) -> Self {
+        (flags.is_leading as u32 & 0b11) << 26
+            | (flags.depends_on as u32 & 0b11) << 24
+            | (flags.is_depended_on as u32 & 0b11) << 22
+            | (flags.has_redundancy as u32 & 0b11) << 20
+            | (flags.padding_value as u32 & 0b111) << 17
+            | (flags.is_non_sync as u32) << 16
+            | flags.degradation_priority as u32
+    }
+}
+
 pub fn creation_time(creation_time: u64) -> u64 {
     // convert from MP4 epoch (1904-01-01) to Unix epoch (1970-01-01)
     if creation_time >= 2082844800 {
@@ -698,17 +951,17 @@ pub enum MetadataKey {
 
 pub trait Metadata<'a> {
     /// The video's title
-    fn title(&self) -> Option<Cow<str>>;
+    fn title(&self) -> Option<Cow<'_, str>>;
     /// The video's release year
     fn year(&self) -> Option<u32>;
     /// The video's poster (cover art)
     fn poster(&self) -> Option<&[u8]>;
     /// The video's summary
-    fn summary(&self) -> Option<Cow<str>>;
+    fn summary(&self) -> Option<Cow<'_, str>>;
 }
 
 impl<'a, T: Metadata<'a>> Metadata<'a> for &'a T {
-    fn title(&self) -> Option<Cow<str>> {
+    fn title(&self) -> Option<Cow<'_, str>> {
         (**self).title()
     }
 
@@ -720,13 +973,13 @@ impl<'a, T: Metadata<'a>> Metadata<'a> for &'a T {
         (**self).poster()
     }
 
-    fn summary(&self) -> Option<Cow<str>> {
+    fn summary(&self) -> Option<Cow<'_, str>> {
         (**self).summary()
     }
 }
 
 impl<'a, T: Metadata<'a>> Metadata<'a> for Option<T> {
-    fn title(&self) -> Option<Cow<str>> {
+    fn title(&self) -> Option<Cow<'_, str>> {
         self.as_ref().and_then(|t| t.title())
     }
 
@@ -738,7 +991,7 @@ impl<'a, T: Metadata<'a>> Metadata<'a> for Option<T> {
         self.as_ref().and_then(|t| t.poster())
     }
 
-    fn summary(&self) -> Option<Cow<str>> {
+    fn summary(&self) -> Option<Cow<'_, str>> {
         self.as_ref().and_then(|t| t.summary())
     }
 }
@@ -0,0 +1,293 @@
+use futures::{Stream, StreamExt};
+use tokio::io::{AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt, SeekFrom};
+
+use crate::error::BoxError;
+use crate::track::Mp4TrackWriter;
+use crate::{BoxHeader, BoxType, FourCC, FtypBox, MoovBox, Mp4Sample, TrackConfig, WriteBox};
+use crate::{Result, HEADER_SIZE};
+
+/// File-level settings for [`Mp4Writer`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Mp4Config {
+    /// `ftyp` major brand, e.g. `isom`.
+    pub major_brand: FourCC,
+    /// `ftyp` minor version.
+    pub minor_version: u32,
+    /// `ftyp` compatible brands.
+    pub compatible_brands: Vec<FourCC>,
+    /// Movie timescale, used for the `mvhd` duration.
+    pub timescale: u32,
+}
+
+/// Progressive MP4 muxer.
+///
+/// Samples are appended to a single `mdat` as they arrive, interleaved in
+/// chunks of about one second per track; the sample tables are collected in
+/// memory and written out as `moov` by [`Mp4Writer::write_end`].
+pub struct Mp4Writer<W> {
+    writer: W,
+    tracks: Vec<Mp4TrackWriter>,
+    mdat_pos: u64,
+    timescale: u32,
+    duration: u64,
+}
+
+impl<W> Mp4Writer<W>
+where
+    W: AsyncWrite + AsyncSeek + Unpin,
+{
+    /// Writes `ftyp` and the `mdat` header and returns a writer ready to
+    /// accept tracks and samples.
+    pub async fn write_start(mut writer: W, config: &Mp4Config) -> Result<Self> {
+        let ftyp = FtypBox {
+            major_brand: config.major_brand,
+            minor_version: config.minor_version,
+            compatible_brands: config.compatible_brands.clone(),
+        };
+        write_box(&mut writer, &ftyp).await?;
+
+        // `wide` reserves room for a 64-bit `mdat` header in case the media
+        // data outgrows 4 GiB.
+        let mdat_pos = writer.stream_position().await?;
+        let mut buf = Vec::with_capacity(2 * HEADER_SIZE as usize);
+        BoxHeader::new(BoxType::WideBox, HEADER_SIZE).write(&mut buf)?;
+        BoxHeader::new(BoxType::MdatBox, HEADER_SIZE).write(&mut buf)?;
+        writer.write_all(&buf).await?;
+
+        Ok(Self {
+            writer,
+            tracks: Vec::new(),
+            mdat_pos,
+            timescale: config.timescale,
+            duration: 0,
+        })
+    }
+
+    /// Adds a track and returns its track id.
+    pub fn add_track(&mut self, config: &TrackConfig) -> Result<u32> {
+        let track_id = self.tracks.len() as u32 + 1;
+        self.tracks.push(Mp4TrackWriter::new(track_id, config));
+
+        Ok(track_id)
+    }
+
+    /// Appends a sample to the track, flushing the track's pending chunk to
+    /// `mdat` first if it is full.
+    ///
+    /// Returns [`BoxError::TrakNotFound`] for an unknown `track_id`.
+    pub async fn write_sample(&mut self, track_id: u32, sample: &Mp4Sample) -> Result<()> {
+        if track_id == 0 {
+            return Err(BoxError::TrakNotFound(track_id));
+        }
+
+        let Some(track) = self.tracks.get_mut(track_id as usize - 1) else {
+            return Err(BoxError::TrakNotFound(track_id));
+        };
+
+        if track.is_chunk_full() {
+            let offset = self.writer.stream_position().await?;
+            if let Some(chunk) = track.take_chunk(offset) {
+                self.writer.write_all(&chunk).await?;
+            }
+        }
+
+        track.add_sample(sample);
+
+        let duration = track.duration() * self.timescale as u64 / track.timescale().max(1) as u64;
+        self.duration = self.duration.max(duration);
+
+        Ok(())
+    }
+
+    /// Writes every sample of `samples` to the track, see
+    /// [`Mp4Writer::write_sample`].
+    pub async fn write_samples<S>(&mut self, track_id: u32, samples: S) -> Result<()>
+    where
+        S: Stream<Item = Mp4Sample>,
+    {
+        futures::pin_mut!(samples);
+
+        while let Some(sample) = samples.next().await {
+            self.write_sample(track_id, &sample).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn update_mdat_size(&mut self) -> Result<()> {
+        let mdat_end = self.writer.stream_position().await?;
+        let mdat_size = mdat_end - self.mdat_pos - HEADER_SIZE;
+
+        let mut buf = Vec::with_capacity(2 * HEADER_SIZE as usize);
+        if mdat_size > u32::MAX as u64 {
+            BoxHeader::new(BoxType::MdatBox, mdat_size + HEADER_SIZE).write(&mut buf)?;
+            self.writer.seek(SeekFrom::Start(self.mdat_pos)).await?;
+        } else {
+            BoxHeader::new(BoxType::MdatBox, mdat_size).write(&mut buf)?;
+            self.writer
+                .seek(SeekFrom::Start(self.mdat_pos + HEADER_SIZE))
+                .await?;
+        }
+        self.writer.write_all(&buf).await?;
+        self.writer.seek(SeekFrom::Start(mdat_end)).await?;
+
+        Ok(())
+    }
+
+    /// Flushes the remaining chunks, patches the `mdat` size and writes
+    /// `moov`. No samples can be added afterwards.
+    pub async fn write_end(&mut self) -> Result<()> {
+        for track in self.tracks.iter_mut() {
+            let offset = self.writer.stream_position().await?;
+            if let Some(chunk) = track.take_chunk(offset) {
+                self.writer.write_all(&chunk).await?;
+            }
+        }
+
+        self.update_mdat_size().await?;
+
+        let mut moov = MoovBox::default();
+        moov.mvhd.timescale = self.timescale;
+        moov.mvhd.duration = self.duration;
+        moov.mvhd.next_track_id = self.tracks.len() as u32 + 1;
+
+        for track in self.tracks.drain(..) {
+            moov.traks.push(track.write_end(self.timescale));
+        }
+
+        write_box(&mut self.writer, &moov).await?;
+        self.writer.flush().await?;
+
+        Ok(())
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+}
+
+pub(crate) async fn write_box<W, B>(writer: &mut W, bx: &B) -> Result<u64>
+where
+    W: AsyncWrite + Unpin,
+    B: for<'b> WriteBox<&'b mut Vec<u8>>,
+{
+    let mut buf = Vec::new();
+    bx.write_box(&mut buf)?;
+    writer.write_all(&buf).await?;
+
+    Ok(buf.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::test_util::{audio_sample, avc_config, config, video_sample};
+    use crate::{AacConfig, MediaConfig, Mp4File, TrackType};
+
+    #[tokio::test]
+    async fn test_write_read() {
+        let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config())
+            .await
+            .unwrap();
+
+        let video = writer.add_track(&avc_config()).unwrap();
+
+        let audio = writer
+            .add_track(&TrackConfig::from(MediaConfig::AacConfig(
+                AacConfig::default(),
+            )))
+            .unwrap();
+
+        for idx in 0..100 {
+            writer
+                .write_sample(video, &video_sample(idx))
+                .await
+                .unwrap();
+            writer
+                .write_sample(audio, &audio_sample(idx))
+                .await
+                .unwrap();
+        }
+        writer.write_end().await.unwrap();
+
+        let mut reader = Cursor::new(writer.into_writer().into_inner());
+        let mut mp4_file = Mp4File::new(&mut reader);
+        assert!(mp4_file.read_header().await.unwrap());
+        assert_eq!(mp4_file.tracks.len(), 2);
+
+        let track = mp4_file.tracks.get(&video).unwrap();
+        assert_eq!(track.track_type(), TrackType::Video);
+        assert_eq!(track.samples.len(), 100);
+        assert_eq!(track.duration, 4000);
+        assert!(track.mdia.minf.stbl.ctts.is_some());
+        assert_eq!(
+            track.mdia.minf.stbl.stss.as_ref().unwrap().entries,
+            vec![1, 26, 51, 76]
+        );
+
+        let track = mp4_file.tracks.get(&audio).unwrap();
+        assert_eq!(track.track_type(), TrackType::Audio);
+        assert_eq!(track.samples.len(), 100);
+        assert!(track.mdia.minf.stbl.stss.is_none());
+        assert_eq!(track.mdia.minf.stbl.stsz.sample_size, 32);
+
+        for idx in 0..100u32 {
+            let expected = video_sample(idx);
+            let samp = mp4_file.tracks.get(&video).unwrap().samples[idx as usize].clone();
+            assert_eq!(samp.start_time, expected.start_time);
+            assert_eq!(samp.rendering_offset, expected.rendering_offset);
+            assert_eq!(samp.is_sync, expected.is_sync);
+
+            let data = mp4_file
+                .read_sample_data(video, idx as usize)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(data, expected.bytes);
+
+            let data = mp4_file
+                .read_sample_data(audio, idx as usize)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(data, audio_sample(idx).bytes);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_negative_rendering_offset() {
+        let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config())
+            .await
+            .unwrap();
+        let video = writer.add_track(&avc_config()).unwrap();
+
+        // I P B, with the B frame shown before the P frame
+        let rendering_offset = |idx: u32| match idx % 3 {
+            0 => 0,
+            1 => 40,
+            _ => -40,
+        };
+        for idx in 0..9 {
+            let sample = Mp4Sample {
+                rendering_offset: rendering_offset(idx),
+                is_sync: idx == 0,
+                ..video_sample(idx)
+            };
+            writer.write_sample(video, &sample).await.unwrap();
+        }
+        writer.write_end().await.unwrap();
+
+        let mut reader = Cursor::new(writer.into_writer().into_inner());
+        let mut mp4_file = Mp4File::new(&mut reader);
+        assert!(mp4_file.read_header().await.unwrap());
+
+        let track = mp4_file.tracks.get(&video).unwrap();
+        assert_eq!(track.mdia.minf.stbl.ctts.as_ref().unwrap().version, 1);
+        for (idx, samp) in track.samples.iter().enumerate() {
+            assert_eq!(samp.rendering_offset, rendering_offset(idx as u32));
+        }
+    }
+}